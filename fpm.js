@@ -22,5 +22,119 @@
     }
 })();
 
+(function () {
+    // `fpm build` writes the search index of the package (or of each version of a versioned
+    // package) in `-/search/index.json`, and the url of the index of the scope of the page in
+    // the `fpm-search-index` meta tag. The urls in an index are relative to its scope.
+    const SEARCH_INDEX_META = 'meta[name="fpm-search-index"]';
+    const SEARCH_INDEX_URL = "-/search/index.json";
+    const SEARCH_INPUT_ID = "fpm-search-input";
+    const SEARCH_RESULTS_ID = "fpm-search-results";
+    const MAX_SEARCH_RESULTS = 10;
+    // Keep in sync with `fpm::search::tokenize()`.
+    const SEPARATORS = /[\s!-\/:-@\[-`{-~।॥“”‘’—–…«»、。，؟،]+/;
+
+    var search_index = null;
+
+    function search_index_url() {
+        var meta = document.querySelector(SEARCH_INDEX_META);
+        return new URL(meta ? meta.content : SEARCH_INDEX_URL, document.baseURI);
+    }
+
+    function load_search_index() {
+        if (search_index === null) {
+            search_index = fetch(search_index_url())
+                .then(function (r) { return r.ok ? r.json() : null; })
+                .catch(function () { return null; });
+        }
+        return search_index;
+    }
+
+    function tokenize(text) {
+        return text.toLowerCase().split(SEPARATORS).filter(function (t) {
+            return Array.from(t).length > 1;
+        });
+    }
+
+    function search(index, query) {
+        var scores = null;
+        tokenize(query).forEach(function (token) {
+            var matched = {};
+            Object.keys(index.terms).forEach(function (term) {
+                if (!term.startsWith(token)) {
+                    return;
+                }
+                index.terms[term].forEach(function (posting) {
+                    matched[posting[0]] = (matched[posting[0]] || 0) + posting[1];
+                });
+            });
+            if (scores === null) {
+                scores = matched;
+                return;
+            }
+            Object.keys(scores).forEach(function (doc) {
+                if (matched[doc] === undefined) {
+                    delete scores[doc];
+                } else {
+                    scores[doc] += matched[doc];
+                }
+            });
+        });
+        return Object.keys(scores || {})
+            .sort(function (a, b) { return scores[b] - scores[a]; })
+            .slice(0, MAX_SEARCH_RESULTS)
+            .map(function (doc) { return index.documents[doc]; });
+    }
+
+    function render_results(container, results) {
+        // the index is in `<scope>/-/search/`
+        var scope = new URL("../../", search_index_url());
+        container.innerHTML = "";
+        results.forEach(function (doc) {
+            var link = document.createElement("a");
+            link.href = new URL(doc.url, scope).href;
+            link.style.display = "block";
+            link.style.padding = "8px 0";
+            var title = document.createElement("div");
+            title.style.fontWeight = "bold";
+            title.textContent = doc.title;
+            var snippet = document.createElement("div");
+            snippet.textContent = doc.snippet;
+            link.appendChild(title);
+            link.appendChild(snippet);
+            container.appendChild(link);
+        });
+    }
+
+    window.fpm_search = function (query) {
+        return load_search_index().then(function (index) {
+            return index === null ? [] : search(index, query);
+        });
+    }
+
+    function init_search() {
+        var input = document.getElementById(SEARCH_INPUT_ID);
+        var container = document.getElementById(SEARCH_RESULTS_ID);
+        if (!input || !container) {
+            return;
+        }
+        input.addEventListener("focus", load_search_index);
+        input.addEventListener("input", function () {
+            var query = input.value;
+            window.fpm_search(query).then(function (results) {
+                if (input.value === query) {
+                    render_results(container, results);
+                }
+            });
+        });
+    }
+
+    if (document.readyState === "loading") {
+        document.addEventListener("DOMContentLoaded", init_search);
+    } else {
+        init_search();
+    }
+})();
+
 /*! instant.page v5.1.0 - (C) 2019-2020 Alexandre Dieulot - https://instant.page/license */
 let t,e;const n=new Set,o=document.createElement("link"),i=o.relList&&o.relList.supports&&o.relList.supports("prefetch")&&window.IntersectionObserver&&"isIntersecting"in IntersectionObserverEntry.prototype,s="instantAllowQueryString"in document.body.dataset,a="instantAllowExternalLinks"in document.body.dataset,r="instantWhitelist"in document.body.dataset,c="instantMousedownShortcut"in document.body.dataset,d=1111;let l=65,u=!1,f=!1,m=!1;if("instantIntensity"in document.body.dataset){const t=document.body.dataset.instantIntensity;if("mousedown"==t.substr(0,"mousedown".length))u=!0,"mousedown-only"==t&&(f=!0);else if("viewport"==t.substr(0,"viewport".length))navigator.connection&&(navigator.connection.saveData||navigator.connection.effectiveType&&navigator.connection.effectiveType.includes("2g"))||("viewport"==t?document.documentElement.clientWidth*document.documentElement.clientHeight<45e4&&(m=!0):"viewport-all"==t&&(m=!0));else{const e=parseInt(t);isNaN(e)||(l=e)}}if(i){const n={capture:!0,passive:!0};if(f||document.addEventListener("touchstart",function(t){e=performance.now();const n=t.target.closest("a");if(!h(n))return;v(n.href)},n),u?c||document.addEventListener("mousedown",function(t){const e=t.target.closest("a");if(!h(e))return;v(e.href)},n):document.addEventListener("mouseover",function(n){if(performance.now()-e<d)return;const o=n.target.closest("a");if(!h(o))return;o.addEventListener("mouseout",p,{passive:!0}),t=setTimeout(()=>{v(o.href),t=void 0},l)},n),c&&document.addEventListener("mousedown",function(t){if(performance.now()-e<d)return;const n=t.target.closest("a");if(t.which>1||t.metaKey||t.ctrlKey)return;if(!n)return;n.addEventListener("click",function(t){1337!=t.detail&&t.preventDefault()},{capture:!0,passive:!1,once:!0});const o=new MouseEvent("click",{view:window,bubbles:!0,cancelable:!1,detail:1337});n.dispatchEvent(o)},n),m){let t;(t=window.requestIdleCallback?t=>{requestIdleCallback(t,{timeout:1500})}:t=>{t()})(()=>{const t=new IntersectionObserver(e=>{e.forEach(e=>{if(e.isIntersecting){const n=e.target;t.unobserve(n),v(n.href)}})});document.querySelectorAll("a").forEach(e=>{h(e)&&t.observe(e)})})}}function p(e){e.relatedTarget&&e.target.closest("a")==e.relatedTarget.closest("a")||t&&(clearTimeout(t),t=void 0)}function h(t){if(t&&t.href&&(!r||"instant"in t.dataset)&&(a||t.origin==location.origin||"instant"in t.dataset)&&["http:","https:"].includes(t.protocol)&&("http:"!=t.protocol||"https:"!=location.protocol)&&(s||!t.search||"instant"in t.dataset)&&!(t.hash&&t.pathname+t.search==location.pathname+location.search||"noInstant"in t.dataset))return!0}function v(t){if(n.has(t))return;const e=document.createElement("link");e.rel="prefetch",e.href=t,document.head.appendChild(e),n.add(t)}
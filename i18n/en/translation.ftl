@@ -48,6 +48,8 @@ welcome-fpm-page-subtitle = Here you find everything that you want to know about
 
 language = Language:
 
+search = Search
+
 crs-heading =
     {$cr-count ->
         [one] CR Modifying this Document
@@ -48,6 +48,8 @@ welcome-fpm-page-subtitle = यहां आपको वह सब कुछ 
 
 language = भाषा :
 
+search = खोजें
+
 crs-heading =
     {$cr-count ->
         [one] CR Modifying this Document
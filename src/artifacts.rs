@@ -0,0 +1,630 @@
+/// `Feed` is the `fpm.feed` entry in `FPM.ftd`. It asks `fpm build` to generate an Atom or RSS
+/// feed for one section of the sitemap, say a blog:
+///
+/// ```ftd
+/// -- fpm.feed: blog/
+/// title: FifthTry Blog
+/// format: rss
+/// limit: 20
+/// ```
+///
+/// Every toc item in the section becomes a feed entry. The date of an entry is read from the
+/// `date` key of the toc item in `fpm.sitemap`, or it falls back to the latest snapshot of the
+/// document in `.history`.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct Feed {
+    pub section: String,
+    pub title: Option<String>,
+    pub format: String,
+    pub limit: Option<i64>,
+}
+
+/// `BuiltDocuments` keeps the ids of the documents `fpm build` wrote an html page for, so
+/// `sitemap.xml` and feeds do not link to documents which failed to build.
+#[derive(Debug, Clone, Default)]
+pub struct BuiltDocuments {
+    ids: std::sync::Arc<std::sync::Mutex<std::collections::BTreeSet<String>>>,
+}
+
+impl BuiltDocuments {
+    pub(crate) fn add(&self, id: &str) {
+        if let Ok(mut ids) = self.ids.lock() {
+            ids.insert(id.to_string());
+        }
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.ids.lock().map(|v| v.contains(id)).unwrap_or(false)
+    }
+
+    /// `ids()` returns the ids of the built `.ftd` and `.md` documents, without `FPM.ftd`.
+    fn ids(&self) -> Vec<String> {
+        self.ids
+            .lock()
+            .map(|v| {
+                v.iter()
+                    .filter(|id| {
+                        id.as_str() != "FPM.ftd" && (id.ends_with(".ftd") || id.ends_with(".md"))
+                    })
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+struct FeedEntry {
+    title: String,
+    url: String,
+    updated: chrono::DateTime<chrono::Utc>,
+    summary: Option<String>,
+}
+
+/// `write()` generates `sitemap.xml`, `robots.txt` and the `fpm.feed` feeds in the build
+/// directory.
+///
+/// Urls in `sitemap.xml` and feeds have to be absolute, so `sitemap.xml` is only generated when
+/// `canonical-url` is set for the package. `robots.txt` is generated when either `canonical-url`
+/// or `fpm.robots` is set.
+pub(crate) async fn write(config: &fpm::Config) -> fpm::Result<()> {
+    let snapshots = snapshots(config).await?;
+
+    if config.package.canonical_url.is_some() {
+        write_sitemap_xml(config, &snapshots)?;
+    }
+
+    if config.package.canonical_url.is_some() || config.package.robots.is_some() {
+        write_robots_txt(config)?;
+    }
+
+    for feed in config.package.feeds.iter() {
+        write_feed(config, feed, &snapshots)?;
+    }
+    Ok(())
+}
+
+/// `base_url()` returns the absolute url of the package, with a trailing `/`.
+pub(crate) fn base_url(package: &fpm::Package) -> String {
+    let url = match package.canonical_url {
+        Some(ref url) => url.to_string(),
+        None => format!("https://{}", package.name),
+    };
+    format!("{}/", url.trim_end_matches('/'))
+}
+
+/// `document_url()` returns the absolute url of the document with the given id, eg `foo/bar.ftd`.
+pub(crate) fn document_url(package: &fpm::Package, id: &str) -> String {
+    let path = fpm::utils::id_to_path(id).replace(std::path::MAIN_SEPARATOR, "/");
+    format!("{}{}", base_url(package), path.trim_start_matches('/'))
+}
+
+/// Snapshots of the documents that are built. For a translation package, documents that are
+/// not yet translated are built from the original package, so original snapshots are used for
+/// them.
+async fn snapshots(config: &fpm::Config) -> fpm::Result<std::collections::BTreeMap<String, u128>> {
+    let mut snapshots = fpm::snapshot::get_latest_snapshots(&config.root).await?;
+    if let Ok(original_path) = config.original_path() {
+        for (id, timestamp) in fpm::snapshot::get_latest_snapshots(&original_path).await? {
+            snapshots.entry(id).or_insert(timestamp);
+        }
+    }
+    Ok(snapshots)
+}
+
+fn write_sitemap_xml(
+    config: &fpm::Config,
+    snapshots: &std::collections::BTreeMap<String, u128>,
+) -> fpm::Result<()> {
+    let mut urls = std::collections::BTreeMap::new();
+    for id in config.built_documents.ids() {
+        if fpm::redirects::find(&config.package, id.as_str()).is_some() {
+            continue;
+        }
+        let url = document_url(&config.package, id.as_str());
+        let last_modified = snapshots.get(&id).map(fpm::utils::nanos_to_rfc3339);
+        urls.insert(url, last_modified);
+    }
+
+    std::fs::write(config.build_dir().join("sitemap.xml"), sitemap_xml(&urls))?;
+    Ok(())
+}
+
+/// `sitemap_xml()` returns the `sitemap.xml` listing `urls`, with their optional last
+/// modification time.
+fn sitemap_xml(urls: &std::collections::BTreeMap<String, Option<String>>) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for (url, last_modified) in urls {
+        xml.push_str(format!("  <url>\n    <loc>{}</loc>\n", escape(url.as_str())).as_str());
+        if let Some(last_modified) = last_modified {
+            xml.push_str(format!("    <lastmod>{}</lastmod>\n", last_modified).as_str());
+        }
+        xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+fn write_robots_txt(config: &fpm::Config) -> fpm::Result<()> {
+    std::fs::write(
+        config.build_dir().join("robots.txt"),
+        robots_txt(&config.package),
+    )?;
+    Ok(())
+}
+
+fn robots_txt(package: &fpm::Package) -> String {
+    let robots = match package.robots {
+        Some(ref robots) => format!("{}\n", robots.trim()),
+        None => "User-agent: *\nAllow: /\n".to_string(),
+    };
+    if package.canonical_url.is_some() && !robots.contains("Sitemap:") {
+        return format!("{}\nSitemap: {}sitemap.xml\n", robots, base_url(package));
+    }
+    robots
+}
+
+fn write_feed(
+    config: &fpm::Config,
+    feed: &Feed,
+    snapshots: &std::collections::BTreeMap<String, u128>,
+) -> fpm::Result<()> {
+    let sitemap = match config.sitemap {
+        Some(ref sitemap) => sitemap,
+        None => {
+            return Err(fpm::Error::UsageError {
+                message: format!(
+                    "`fpm.feed: {}` needs `fpm.sitemap` in FPM.ftd",
+                    feed.section
+                ),
+            })
+        }
+    };
+    let section = match sitemap
+        .sections
+        .iter()
+        .find(|s| fpm::sitemap::Sitemap::ids_matches(s.id.as_str(), feed.section.as_str()))
+    {
+        Some(section) => section,
+        None => {
+            return Err(fpm::Error::UsageError {
+                message: format!(
+                    "`fpm.feed: {}`: no such section in fpm.sitemap",
+                    feed.section
+                ),
+            })
+        }
+    };
+
+    let documents_root = match config.original_path() {
+        Ok(original_path) => original_path,
+        Err(_) => config.root.clone(),
+    };
+    let mut entries = vec![];
+    for subsection in section.subsections.iter() {
+        for toc in subsection.toc.iter() {
+            collect_entries(config, toc, &documents_root, snapshots, &mut entries);
+        }
+    }
+    entries.sort_by(|a, b| b.updated.cmp(&a.updated));
+    if let Some(limit) = feed.limit {
+        entries.truncate(limit.max(0) as usize);
+    }
+
+    let section_path = section
+        .id
+        .trim()
+        .replace("index.html", "")
+        .trim_matches('/')
+        .to_string();
+    let section_url = format!("{}{}", base_url(&config.package), section_path);
+    let title = feed
+        .title
+        .clone()
+        .or_else(|| section.title.clone())
+        .unwrap_or_else(|| config.package.name.to_string());
+
+    let (file_name, content) = match feed.format.as_str() {
+        "atom" => {
+            let feed_url = join(section_url.as_str(), "atom.xml");
+            (
+                "atom.xml",
+                atom(
+                    &config.package,
+                    title.as_str(),
+                    &section_url,
+                    &feed_url,
+                    &entries,
+                ),
+            )
+        }
+        "rss" => (
+            "rss.xml",
+            rss(
+                &config.package,
+                title.as_str(),
+                section_url.as_str(),
+                &entries,
+            ),
+        ),
+        t => {
+            return Err(fpm::Error::UsageError {
+                message: format!(
+                    "`fpm.feed: {}`: format can be `atom` or `rss`, found: `{}`",
+                    feed.section, t
+                ),
+            })
+        }
+    };
+
+    let dir = config.build_dir().join(section_path.as_str());
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(file_name), content)?;
+    return Ok(());
+
+    fn collect_entries(
+        config: &fpm::Config,
+        toc: &fpm::sitemap::TocItem,
+        documents_root: &camino::Utf8PathBuf,
+        snapshots: &std::collections::BTreeMap<String, u128>,
+        entries: &mut Vec<FeedEntry>,
+    ) {
+        if toc.file_location.is_some() && is_built(config, documents_root, toc.id.as_str()) {
+            let updated = toc
+                .extra_data
+                .get("date")
+                .and_then(|v| parse_date(v.as_str()))
+                .or_else(|| {
+                    fpm::Config::get_file_name(documents_root, toc.id.as_str())
+                        .ok()
+                        .and_then(|id| snapshots.get(&id))
+                        .map(|nanos| {
+                            chrono::DateTime::<chrono::Utc>::from(
+                                std::time::SystemTime::UNIX_EPOCH
+                                    + std::time::Duration::from_nanos(*nanos as u64),
+                            )
+                        })
+                });
+            if let Some(updated) = updated {
+                entries.push(FeedEntry {
+                    title: toc.title.clone().unwrap_or_else(|| toc.id.to_string()),
+                    url: join(
+                        base_url(&config.package).as_str(),
+                        toc.id.trim().trim_start_matches('/'),
+                    ),
+                    updated,
+                    summary: toc.extra_data.get("description").cloned(),
+                });
+            }
+        }
+        for child in toc.children.iter() {
+            collect_entries(config, child, documents_root, snapshots, entries);
+        }
+    }
+
+    /// `is_built()` tells if the document of the toc item was built, by its file name or, for a
+    /// document with a url in the sitemap, by its url.
+    fn is_built(config: &fpm::Config, documents_root: &camino::Utf8PathBuf, id: &str) -> bool {
+        let url_id = format!("{}/index.ftd", id.trim().trim_matches('/'));
+        fpm::Config::get_file_name(documents_root, id)
+            .map(|v| config.built_documents.contains(v.as_str()))
+            .unwrap_or(false)
+            || config.built_documents.contains(url_id.as_str())
+            || config
+                .built_documents
+                .contains(url_id.replace(".ftd", ".md").as_str())
+    }
+}
+
+fn atom(
+    package: &fpm::Package,
+    title: &str,
+    section_url: &str,
+    feed_url: &str,
+    entries: &[FeedEntry],
+) -> String {
+    let updated = entries
+        .iter()
+        .map(|e| e.updated)
+        .max()
+        .map(|v| v.to_rfc3339())
+        .unwrap_or_else(|| "1970-01-01T00:00:00+00:00".to_string());
+    let mut xml = format!(
+        indoc::indoc! {"
+            <?xml version=\"1.0\" encoding=\"utf-8\"?>
+            <feed xmlns=\"http://www.w3.org/2005/Atom\">
+              <title>{title}</title>
+              <id>{feed_url}</id>
+              <link href=\"{section_url}\"/>
+              <link rel=\"self\" href=\"{feed_url}\"/>
+              <updated>{updated}</updated>
+              <author><name>{author}</name></author>
+        "},
+        title = escape(title),
+        feed_url = escape(feed_url),
+        section_url = escape(section_url),
+        updated = updated,
+        author = escape(package.name.as_str()),
+    );
+    for entry in entries {
+        xml.push_str(
+            format!(
+                "  <entry>\n    <title>{title}</title>\n    <id>{url}</id>\n    \
+                <link href=\"{url}\"/>\n    <updated>{updated}</updated>\n",
+                title = escape(entry.title.as_str()),
+                url = escape(entry.url.as_str()),
+                updated = entry.updated.to_rfc3339(),
+            )
+            .as_str(),
+        );
+        if let Some(ref summary) = entry.summary {
+            xml.push_str(format!("    <summary>{}</summary>\n", escape(summary)).as_str());
+        }
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+fn rss(package: &fpm::Package, title: &str, section_url: &str, entries: &[FeedEntry]) -> String {
+    let description = package.about.clone().unwrap_or_else(|| title.to_string());
+    let mut xml = format!(
+        indoc::indoc! {"
+            <?xml version=\"1.0\" encoding=\"utf-8\"?>
+            <rss version=\"2.0\">
+            <channel>
+              <title>{title}</title>
+              <link>{section_url}</link>
+              <description>{description}</description>
+        "},
+        title = escape(title),
+        section_url = escape(section_url),
+        description = escape(description.trim()),
+    );
+    if let Some(ref language) = package.language {
+        xml.push_str(format!("  <language>{}</language>\n", escape(language)).as_str());
+    }
+    for entry in entries {
+        xml.push_str(
+            format!(
+                "  <item>\n    <title>{title}</title>\n    <link>{url}</link>\n    \
+                <guid>{url}</guid>\n    <pubDate>{date}</pubDate>\n",
+                title = escape(entry.title.as_str()),
+                url = escape(entry.url.as_str()),
+                date = entry.updated.to_rfc2822(),
+            )
+            .as_str(),
+        );
+        if let Some(ref summary) = entry.summary {
+            xml.push_str(format!("    <description>{}</description>\n", escape(summary)).as_str());
+        }
+        xml.push_str("  </item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+/// `parse_date()` accepts dates like `2022-04-01` or `2022-04-01T10:00:00+05:30`.
+pub(crate) fn parse_date(s: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let s = s.trim();
+    if let Ok(v) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Some(v.with_timezone(&chrono::Utc));
+    }
+    chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .map(|v| chrono::DateTime::<chrono::Utc>::from_utc(v.and_hms(0, 0, 0), chrono::Utc))
+}
+
+/// `join()` returns `path` relative to `base`, or `path` itself if it is an absolute url.
+pub(crate) fn join(base: &str, path: &str) -> String {
+    if path.contains("://") {
+        return path.to_string();
+    }
+    format!(
+        "{}/{}",
+        base.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+/// `escape()` escapes the text for use in xml and html attributes and text.
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod test {
+    fn package() -> fpm::Package {
+        let mut package = fpm::Package::new("fifthtry.com");
+        package.canonical_url = Some("https://www.fifthtry.com".to_string());
+        package.about = Some("FifthTry & friends".to_string());
+        package.language = Some("en".to_string());
+        package
+    }
+
+    fn entries() -> Vec<super::FeedEntry> {
+        vec![super::FeedEntry {
+            title: "Hello <World>".to_string(),
+            url: "https://www.fifthtry.com/blog/hello/".to_string(),
+            updated: super::parse_date("2022-04-01").unwrap(),
+            summary: Some("First post".to_string()),
+        }]
+    }
+
+    #[test]
+    fn parse_date() {
+        assert_eq!(
+            super::parse_date("2022-04-01").map(|v| v.to_rfc3339()),
+            Some("2022-04-01T00:00:00+00:00".to_string())
+        );
+        assert_eq!(
+            super::parse_date(" 2022-04-01T10:00:00+05:30 ").map(|v| v.to_rfc3339()),
+            Some("2022-04-01T04:30:00+00:00".to_string())
+        );
+        assert_eq!(super::parse_date("1st April"), None);
+        assert_eq!(super::parse_date("2022-13-01"), None);
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            super::escape("<a href=\"/?a=1&b='2'\">"),
+            "&lt;a href=&quot;/?a=1&amp;b=&#39;2&#39;&quot;&gt;"
+        );
+        assert_eq!(super::escape("Hello World"), "Hello World");
+    }
+
+    #[test]
+    fn document_url() {
+        let package = package();
+        assert_eq!(super::base_url(&package), "https://www.fifthtry.com/");
+        assert_eq!(
+            super::document_url(&package, "index.ftd"),
+            "https://www.fifthtry.com/"
+        );
+        assert_eq!(
+            super::document_url(&package, "blog/hello.ftd"),
+            "https://www.fifthtry.com/blog/hello/"
+        );
+        assert_eq!(
+            super::base_url(&fpm::Package::new("fifthtry.com")),
+            "https://fifthtry.com/"
+        );
+    }
+
+    #[test]
+    fn join() {
+        assert_eq!(
+            super::join("https://www.fifthtry.com/", "/blog/"),
+            "https://www.fifthtry.com/blog/"
+        );
+        assert_eq!(
+            super::join("https://www.fifthtry.com", "https://example.com/a.png"),
+            "https://example.com/a.png"
+        );
+    }
+
+    #[test]
+    fn built_documents() {
+        let built = super::BuiltDocuments::default();
+        for id in ["FPM.ftd", "index.ftd", "blog/hello.md", "logo.png"] {
+            built.add(id);
+        }
+        // a document which failed to build is never added
+        assert_eq!(built.ids(), vec!["blog/hello.md", "index.ftd"]);
+        assert!(built.contains("logo.png"));
+        assert!(!built.contains("fail_doc.ftd"));
+    }
+
+    #[test]
+    fn sitemap_xml() {
+        let mut urls = std::collections::BTreeMap::new();
+        urls.insert(
+            "https://www.fifthtry.com/blog/?a&b".to_string(),
+            Some("2022-04-01T00:00:00+00:00".to_string()),
+        );
+        urls.insert("https://www.fifthtry.com/".to_string(), None);
+        assert_eq!(
+            super::sitemap_xml(&urls),
+            indoc::indoc! {r#"
+                <?xml version="1.0" encoding="UTF-8"?>
+                <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+                  <url>
+                    <loc>https://www.fifthtry.com/</loc>
+                  </url>
+                  <url>
+                    <loc>https://www.fifthtry.com/blog/?a&amp;b</loc>
+                    <lastmod>2022-04-01T00:00:00+00:00</lastmod>
+                  </url>
+                </urlset>
+            "#}
+        );
+    }
+
+    #[test]
+    fn robots_txt() {
+        let mut package = package();
+        assert_eq!(
+            super::robots_txt(&package),
+            "User-agent: *\nAllow: /\n\nSitemap: https://www.fifthtry.com/sitemap.xml\n"
+        );
+        package.robots = Some("User-agent: *\nDisallow: /drafts/\n".to_string());
+        assert_eq!(
+            super::robots_txt(&package),
+            "User-agent: *\nDisallow: /drafts/\n\nSitemap: https://www.fifthtry.com/sitemap.xml\n"
+        );
+        package.canonical_url = None;
+        assert_eq!(
+            super::robots_txt(&package),
+            "User-agent: *\nDisallow: /drafts/\n"
+        );
+    }
+
+    #[test]
+    fn atom() {
+        assert_eq!(
+            super::atom(
+                &package(),
+                "Blog",
+                "https://www.fifthtry.com/blog",
+                "https://www.fifthtry.com/blog/atom.xml",
+                &entries()
+            ),
+            indoc::indoc! {r#"
+                <?xml version="1.0" encoding="utf-8"?>
+                <feed xmlns="http://www.w3.org/2005/Atom">
+                  <title>Blog</title>
+                  <id>https://www.fifthtry.com/blog/atom.xml</id>
+                  <link href="https://www.fifthtry.com/blog"/>
+                  <link rel="self" href="https://www.fifthtry.com/blog/atom.xml"/>
+                  <updated>2022-04-01T00:00:00+00:00</updated>
+                  <author><name>fifthtry.com</name></author>
+                  <entry>
+                    <title>Hello &lt;World&gt;</title>
+                    <id>https://www.fifthtry.com/blog/hello/</id>
+                    <link href="https://www.fifthtry.com/blog/hello/"/>
+                    <updated>2022-04-01T00:00:00+00:00</updated>
+                    <summary>First post</summary>
+                  </entry>
+                </feed>
+            "#}
+        );
+    }
+
+    #[test]
+    fn rss() {
+        assert_eq!(
+            super::rss(
+                &package(),
+                "Blog",
+                "https://www.fifthtry.com/blog",
+                &entries()
+            ),
+            indoc::indoc! {r#"
+                <?xml version="1.0" encoding="utf-8"?>
+                <rss version="2.0">
+                <channel>
+                  <title>Blog</title>
+                  <link>https://www.fifthtry.com/blog</link>
+                  <description>FifthTry &amp; friends</description>
+                  <language>en</language>
+                  <item>
+                    <title>Hello &lt;World&gt;</title>
+                    <link>https://www.fifthtry.com/blog/hello/</link>
+                    <guid>https://www.fifthtry.com/blog/hello/</guid>
+                    <pubDate>Fri, 01 Apr 2022 00:00:00 +0000</pubDate>
+                    <description>First post</description>
+                  </item>
+                </channel>
+                </rss>
+            "#}
+        );
+    }
+}
@@ -5,11 +5,21 @@ pub async fn build(
     file: Option<&str>,
     base_url: &str,
     ignore_failed: bool,
+    optimize_assets: bool,
 ) -> fpm::Result<()> {
     use fpm::utils::HasElements;
     use itertools::Itertools;
 
     tokio::fs::create_dir_all(config.build_dir()).await?;
+    // the translation status pages of an original package need the whole translation packages
+    fpm::dependency::ensure_translations(config).await?;
+    if optimize_assets {
+        config.shared_assets = Some(fpm::optimize::SharedAssets::write(config, base_url)?);
+    }
+    if config.package.search && file.is_none() {
+        config.search_index = Some(Default::default());
+    }
+    config.transclusions = fpm::transclude::Transclusions::read(config);
     // let skip_failed = ignore_failed.unwrap_or(false);
     // Process static assets for the dependencies
     let dependencies = if let Some(package) = config.package.translation_of.as_ref() {
@@ -46,31 +56,55 @@ pub async fn build(
         );
     }
 
-    if config.package.versioned {
-        fpm::version::build_version(config, file, base_url, ignore_failed, &asset_documents)
+    // documents transcluding `file` are built along with it
+    let documents = match file {
+        Some(file) => std::iter::once(file.to_string())
+            .chain(config.transclusions.dependents(file))
+            .map(Some)
+            .collect(),
+        None => vec![None],
+    };
+    for document in documents.iter().map(|v| v.as_deref()) {
+        if config.package.versioned {
+            fpm::version::build_version(
+                config,
+                document,
+                base_url,
+                ignore_failed,
+                &asset_documents,
+            )
             .await?;
-    } else {
-        match (
-            config.package.translation_of.is_some(),
-            config.package.translations.has_elements(),
-        ) {
-            (true, true) => {
-                // No package can be both a translation of something and has its own
-                // translations, when building `config` we ensured this was rejected
-                unreachable!()
-            }
-            (true, false) => {
-                build_with_original(config, file, base_url, ignore_failed, &asset_documents).await
-            }
-            (false, false) => {
-                build_simple(config, file, base_url, ignore_failed, &asset_documents).await
-            }
-            (false, true) => {
-                build_with_translations(config, file, base_url, ignore_failed, &asset_documents)
+        } else {
+            match (
+                config.package.translation_of.is_some(),
+                config.package.translations.has_elements(),
+            ) {
+                (true, true) => {
+                    // No package can be both a translation of something and has its own
+                    // translations, when building `config` we ensured this was rejected
+                    unreachable!()
+                }
+                (true, false) => {
+                    build_with_original(config, document, base_url, ignore_failed, &asset_documents)
+                        .await
+                }
+                (false, false) => {
+                    build_simple(config, document, base_url, ignore_failed, &asset_documents).await
+                }
+                (false, true) => {
+                    build_with_translations(
+                        config,
+                        document,
+                        base_url,
+                        ignore_failed,
+                        &asset_documents,
+                    )
                     .await
-            }
-        }?;
+                }
+            }?;
+        }
     }
+    config.transclusions.write(config)?;
 
     for dep in dependencies {
         let static_files = std::collections::BTreeMap::from_iter(
@@ -99,6 +133,19 @@ pub async fn build(
         )
         .await?;
     }
+
+    if file.is_none() {
+        fpm::redirects::write(config, base_url)?;
+        fpm::artifacts::write(config).await?;
+    }
+
+    if let Some(ref search_index) = config.search_index {
+        search_index.write(config)?;
+    }
+
+    if optimize_assets {
+        fpm::optimize::precompress(&config.build_dir())?;
+    }
     Ok(())
 }
 
@@ -247,6 +294,32 @@ async fn build_with_translations(
         .await?;
         fpm::utils::print_end("Processed translation-status.ftd", start);
     }
+    // Add /-/translation-matrix page
+    {
+        let translation_matrix = fpm::Document {
+            id: "-/translation-matrix.ftd".to_string(),
+            content: fpm::translation_matrix(config).await?,
+            parent_path: config.root.as_str().to_string(),
+            package_name: config.package.name.clone(),
+        };
+
+        print!("Processing translation-matrix.ftd ... ");
+        let start = std::time::Instant::now();
+        std::io::stdout().flush()?;
+
+        process_ftd(
+            config,
+            &translation_matrix,
+            None,
+            None,
+            Default::default(),
+            base_url,
+            asset_documents,
+            true,
+        )
+        .await?;
+        fpm::utils::print_end("Processed translation-matrix.ftd", start);
+    }
     Ok(())
 }
 
@@ -562,6 +635,9 @@ pub(crate) async fn process_file(
                 })
             }
         }
+        if matches!(main, fpm::File::Ftd(_) | fpm::File::Markdown(_)) {
+            config.built_documents.add(main.get_id().as_str());
+        }
         fpm::utils::print_end(
             format!("Processed {}/{}", package.name.as_str(), main.get_id()).as_str(),
             start,
@@ -682,6 +758,9 @@ pub(crate) async fn process_file(
             }
         }
     }
+    if matches!(main, fpm::File::Ftd(_) | fpm::File::Markdown(_)) {
+        config.built_documents.add(main.get_id().as_str());
+    }
     fpm::utils::print_end(
         format!("Processed {}/{}", package.name.as_str(), main.get_id()).as_str(),
         start,
@@ -756,6 +835,18 @@ async fn process_code(
     base_url: &str,
     asset_documents: &std::collections::HashMap<String, String>,
 ) -> fpm::Result<()> {
+    // `fpm.highlighted-code` is generated for every import of `fpm`, so the code is highlighted
+    // once here
+    let highlighted_config;
+    let config = match highlighted_code(config, main) {
+        Some(html) => {
+            let mut c = config.clone();
+            c.highlighted_code = Some(html);
+            highlighted_config = c;
+            &highlighted_config
+        }
+        None => config,
+    };
     let main = if let Some(main) = convert_to_ftd(config, main)? {
         main
     } else {
@@ -818,6 +909,29 @@ async fn process_code(
     fn convert_to_ftd_extension(name: &str) -> fpm::Result<String> {
         Ok(format!("{}.ftd", name))
     }
+
+    /// `highlighted_code()` returns the syntax highlighted content of the code file, if
+    /// `highlight-code` is enabled for the package.
+    fn highlighted_code(config: &fpm::Config, doc: &fpm::Document) -> Option<String> {
+        use colorize::AnsiColor;
+
+        if !config.package.highlight_code {
+            return None;
+        }
+        let ext = fpm::utils::get_extension(doc.id.as_str()).ok()?;
+        match fpm::highlight::to_html(
+            config,
+            doc.content.as_str(),
+            ext.as_str(),
+            &Default::default(),
+        ) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                warning!(format!("{}: {}", doc.id, e));
+                None
+            }
+        }
+    }
 }
 
 async fn process_markdown(
@@ -935,10 +1049,13 @@ pub(crate) async fn process_ftd(
         if config.is_translation_package() {
             use std::io::Write;
 
-            let original_snapshots =
-                fpm::snapshot::get_latest_snapshots(&config.original_path()?).await?;
-            let translation_status =
-                fpm::translation::get_translation_status_counts(&original_snapshots, &config.root)?;
+            let original_path = config.original_path()?;
+            let original_snapshots = fpm::snapshot::get_latest_snapshots(&original_path).await?;
+            let translation_status = fpm::translation::get_translation_status_counts(
+                &original_snapshots,
+                &config.root,
+                &original_path,
+            )?;
             let content = std::fs::read_to_string(config.root.join(main.id.as_str()))?;
             let fpm = {
                 let mut translation_status_summary = format!(
@@ -954,6 +1071,15 @@ pub(crate) async fn process_ftd(
                     out_dated = translation_status.out_dated,
                     upto_date = translation_status.upto_date,
                 );
+                if let Some(out_dated_sections) = translation_status.out_dated_sections {
+                    translation_status_summary = format!(
+                        indoc::indoc! {"
+                            {translation_status_summary}out-dated-sections: {out_dated_sections}
+                        "},
+                        translation_status_summary = translation_status_summary,
+                        out_dated_sections = out_dated_sections
+                    );
+                }
                 if let Some(last_modified_on) = translation_status.last_modified_on {
                     translation_status_summary = format!(
                         indoc::indoc! {"
@@ -1036,20 +1162,27 @@ pub(crate) async fn process_ftd(
     let (fallback, message, final_main) = if main.id.eq("-.ftd") {
         (None, None, main)
     } else {
+        let content = fpm::transclude::expand(config, &main.id, main.content.as_str())?;
         let new_main = fpm::Document {
             content: config
                 .package
-                .get_prefixed_body(main.content.as_str(), &main.id, true),
+                .get_prefixed_body(content.as_str(), &main.id, true),
             id: main.id,
             parent_path: main.parent_path,
             package_name: main.package_name,
         };
-        let new_fallback = fallback.map(|fb| fpm::Document {
-            content: config
-                .package
-                .get_prefixed_body(fb.content.as_str(), &fb.id, true),
-            ..fb.to_owned()
-        });
+        let new_fallback = match fallback {
+            Some(fb) => {
+                let content = fpm::transclude::expand(config, &fb.id, fb.content.as_str())?;
+                Some(fpm::Document {
+                    content: config
+                        .package
+                        .get_prefixed_body(content.as_str(), &fb.id, true),
+                    ..fb.to_owned()
+                })
+            }
+            None => None,
+        };
         (new_fallback, message, new_main)
     };
     match (fallback, message) {
@@ -1116,7 +1249,12 @@ pub(crate) async fn process_ftd(
                 Ok(v) => v,
                 Err(e) => {
                     return Err(fpm::Error::PackageError {
-                        message: format!("failed to parse {:?}", &e),
+                        message: format!(
+                            "failed to parse {:?}",
+                            config
+                                .transclusions
+                                .locate(&main.id, main.content.as_str(), e)
+                        ),
                     });
                 }
             };
@@ -1126,15 +1264,33 @@ pub(crate) async fn process_ftd(
         };
         let ftd_doc = main_ftd_doc.to_rt("main", &main.id);
 
+        if let Some(ref search_index) = config.search_index {
+            search_index.add(
+                config,
+                main.id.as_str(),
+                fpm::utils::document_language(config, None),
+                doc_title.as_str(),
+                &main_ftd_doc,
+                &ftd_doc,
+            );
+        }
+
         let mut f = tokio::fs::File::create(new_file_path).await?;
         // TODO: return Document
         let file_content = fpm::utils::replace_markers(
             fpm::ftd_html(),
             config,
+            fpm::utils::document_language(config, None),
             main.id_to_path().as_str(),
             doc_title.as_str(),
             base_url,
             &ftd_doc,
+            &fpm::meta::Meta::from_document(
+                &config.package,
+                config.sitemap.as_ref(),
+                main.id.as_str(),
+                &main_ftd_doc,
+            ),
         );
 
         f.write_all(file_content.as_bytes()).await?;
@@ -1152,6 +1308,7 @@ pub(crate) async fn process_ftd(
     ) -> fpm::Result<Vec<u8>> {
         use tokio::io::AsyncWriteExt;
 
+        let lang = fpm::utils::document_language(config, translated_data.status.as_deref());
         let lib = fpm::Library {
             config: config.clone(),
             markdown: None,
@@ -1168,7 +1325,12 @@ pub(crate) async fn process_ftd(
                 Ok(v) => v,
                 Err(e) => {
                     return Err(fpm::Error::PackageError {
-                        message: format!("failed to parse {:?}", &e),
+                        message: format!(
+                            "failed to parse {:?}",
+                            config
+                                .transclusions
+                                .locate(&main.id, main.content.as_str(), e)
+                        ),
                     });
                 }
             };
@@ -1189,6 +1351,17 @@ pub(crate) async fn process_ftd(
         };
         let message_rt_doc = message_ftd_doc.to_rt("message", &main.id);
 
+        if let Some(ref search_index) = config.search_index {
+            search_index.add(
+                config,
+                main.id.as_str(),
+                lang,
+                doc_title.as_str(),
+                &main_ftd_doc,
+                &main_rt_doc,
+            );
+        }
+
         let mut f = tokio::fs::File::create(new_file_path).await?;
 
         let file_content = fpm::utils::replace_markers(
@@ -1211,10 +1384,17 @@ pub(crate) async fn process_ftd(
                 )
                 .as_str(),
             config,
+            lang,
             main.id_to_path().as_str(),
             doc_title.as_str(),
             base_url,
             &main_rt_doc,
+            &fpm::meta::Meta::from_document(
+                &config.package,
+                config.sitemap.as_ref(),
+                main.id.as_str(),
+                &main_ftd_doc,
+            ),
         );
 
         f.write_all(file_content.as_bytes()).await?;
@@ -1233,6 +1413,7 @@ pub(crate) async fn process_ftd(
         _do_write: bool, // TODO: Do it later
     ) -> fpm::Result<Vec<u8>> {
         use tokio::io::AsyncWriteExt;
+        let lang = fpm::utils::document_language(config, translated_data.status.as_deref());
         let lib = fpm::Library {
             config: config.clone(),
             markdown: None,
@@ -1249,7 +1430,12 @@ pub(crate) async fn process_ftd(
                 Ok(v) => v,
                 Err(e) => {
                     return Err(fpm::Error::PackageError {
-                        message: format!("failed to parse {:?}", &e),
+                        message: format!(
+                            "failed to parse {:?}",
+                            config
+                                .transclusions
+                                .locate(&main.id, main.content.as_str(), e)
+                        ),
                     });
                 }
             };
@@ -1280,12 +1466,28 @@ pub(crate) async fn process_ftd(
             Ok(v) => v,
             Err(e) => {
                 return Err(fpm::Error::PackageError {
-                    message: format!("failed to parse {:?}", &e),
+                    message: format!(
+                        "failed to parse {:?}",
+                        config
+                            .transclusions
+                            .locate(&fallback.id, fallback.content.as_str(), e)
+                    ),
                 });
             }
         };
         let fallback_rt_doc = fallback_ftd_doc.to_rt("fallback", &fallback.id);
 
+        if let Some(ref search_index) = config.search_index {
+            search_index.add(
+                config,
+                main.id.as_str(),
+                lang,
+                doc_title.as_str(),
+                &main_ftd_doc,
+                &main_rt_doc,
+            );
+        }
+
         let mut f = tokio::fs::File::create(new_file_path).await?;
 
         let file_content = fpm::utils::replace_markers(
@@ -1324,10 +1526,17 @@ pub(crate) async fn process_ftd(
                 )
                 .as_str(),
             config,
+            lang,
             main.id_to_path().as_str(),
             doc_title.as_str(),
             base_url,
             &main_rt_doc,
+            &fpm::meta::Meta::from_document(
+                &config.package,
+                config.sitemap.as_ref(),
+                main.id.as_str(),
+                &main_ftd_doc,
+            ),
         );
 
         f.write_all(file_content.as_bytes()).await?;
@@ -0,0 +1,177 @@
+/// `check()` warns about problems with the package which do not stop it from being built.
+///
+/// It lists the fpm UI strings which are not translated to the language of the package, or to a
+/// language the package or its theme ship resources for in `FPM/i18n/<lang>/`, and so are shown
+/// in the language they fall back to. It also checks the links between the documents of the
+/// package, see `check_links()`.
+pub async fn check(config: &fpm::Config) -> fpm::Result<()> {
+    use colorize::AnsiColor;
+
+    let mut languages = fpm::i18n::languages(config);
+    if let Some(ref lang) = config.package.language {
+        languages.insert(lang.to_string());
+    }
+
+    let mut count = 0;
+    for lang in languages.iter() {
+        // every other language falls back to English, which is complete
+        if lang == "en" || lang.starts_with("en-") {
+            continue;
+        }
+        for res in ["terms", "translation"] {
+            for (key, fallback) in fpm::i18n::missing_keys(config, lang, res) {
+                warning!(format!(
+                    "i18n: `{}` of {}.ftl is not translated to `{}`, `{}` is used",
+                    key, res, lang, fallback
+                ));
+                count += 1;
+            }
+        }
+    }
+
+    let documents = config.get_files(&config.package).await?;
+    for message in check_links(&config.package, &documents) {
+        warning!(message);
+        count += 1;
+    }
+
+    if count == 0 {
+        println!("Everything is fine");
+    } else {
+        println!("{} warnings", count);
+    }
+    Ok(())
+}
+
+/// `check_links()` returns a message for each link to a path of the package, eg `link: /guide/`,
+/// `[guide](/guide/)` or `href="/guide/"`, that is neither a file of the package nor an
+/// `fpm.redirect`. Links to a redirected document still work, but are reported so that they can
+/// be updated to the new location, and so is an `fpm.redirect` to a path which does not exist.
+fn check_links(package: &fpm::Package, documents: &[fpm::File]) -> Vec<String> {
+    let mut paths = std::collections::HashSet::new();
+    for document in documents {
+        let id = document.get_id();
+        paths.insert(fpm::redirects::normalize(id.as_str()));
+        paths.insert(id);
+    }
+    let exists = |path: &str| {
+        let path = fpm::redirects::normalize(path);
+        paths.contains(path.as_str()) || fpm::redirects::find(package, path.as_str()).is_some()
+    };
+
+    let mut messages = vec![];
+    for document in documents {
+        let (id, content) = match document {
+            fpm::File::Ftd(d) | fpm::File::Markdown(d) => (d.id.as_str(), d.content.as_str()),
+            _ => continue,
+        };
+        for (line_number, link) in links(content) {
+            if let Some(redirect) = fpm::redirects::find(package, link.as_str()) {
+                messages.push(format!(
+                    "{}:{}: `{}` is moved to `{}`",
+                    id, line_number, link, redirect.to
+                ));
+            } else if !exists(link.as_str()) {
+                messages.push(format!("{}:{}: broken link `{}`", id, line_number, link));
+            }
+        }
+    }
+    for redirect in package.redirects.iter() {
+        let to = redirect.to.trim();
+        if !to.contains("://") && !exists(to) {
+            messages.push(format!(
+                "FPM.ftd: `{}` is redirected to `{}`, which does not exist",
+                redirect.from, to
+            ));
+        }
+    }
+    messages
+}
+
+lazy_static! {
+    static ref LINK: regex::Regex =
+        regex::Regex::new(r#"(?:^\s*link\s*:\s*|\]\(|href=")(/[^\s)"]*)"#).unwrap();
+}
+
+/// `links()` returns the line number and the path of the links to the package in `content`.
+/// Links to other hosts, and to the files fpm serves under `/-/`, are skipped.
+fn links(content: &str) -> Vec<(usize, String)> {
+    let mut links = vec![];
+    for (index, line) in content.lines().enumerate() {
+        for captures in LINK.captures_iter(line) {
+            let link = captures[1]
+                .split(|c| c == '#' || c == '?')
+                .next()
+                .unwrap_or_default();
+            if link.starts_with("//") || link.starts_with("/-/") {
+                continue;
+            }
+            links.push((index + 1, link.to_string()));
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod test {
+    fn document(id: &str, content: &str) -> fpm::File {
+        fpm::File::Ftd(fpm::Document {
+            package_name: "fpm-check-test".to_string(),
+            id: id.to_string(),
+            content: content.to_string(),
+            parent_path: "".to_string(),
+        })
+    }
+
+    #[test]
+    fn links() {
+        let content = indoc::indoc! {"
+            -- ft.toc:
+            link: /guide/install/#linux
+
+            See [the guide](/guide/?lang=hi) or <a href=\"/logo.png\">the logo</a>.
+            [external](https://example.com/) [asset](/-/fpm.dev/x.css) [cdn](//cdn.example.com/)
+        "};
+        assert_eq!(
+            super::links(content),
+            vec![
+                (2, "/guide/install/".to_string()),
+                (4, "/guide/".to_string()),
+                (4, "/logo.png".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn check_links() {
+        let mut package = fpm::Package::new("fpm-check-test");
+        package.redirects = vec![
+            fpm::redirects::Redirect {
+                from: "old-guide/".to_string(),
+                to: "/guide/".to_string(),
+                permanent: true,
+            },
+            fpm::redirects::Redirect {
+                from: "old-about/".to_string(),
+                to: "/about/".to_string(),
+                permanent: true,
+            },
+        ];
+        let documents = vec![
+            document(
+                "index.ftd",
+                "[guide](/guide/)\n[old](/old-guide/)\n[gone](/gone/)\n[root](/)",
+            ),
+            document("guide.ftd", "-- ft.markdown:\n\n[home](/index.ftd)"),
+        ];
+        assert_eq!(
+            super::check_links(&package, &documents),
+            vec![
+                "index.ftd:2: `/old-guide/` is moved to `/guide/`".to_string(),
+                "index.ftd:3: broken link `/gone/`".to_string(),
+                "FPM.ftd: `old-about/` is redirected to `/about/`, which does not exist"
+                    .to_string(),
+            ]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub(crate) mod build;
+pub(crate) mod check;
 pub(crate) mod diff;
 pub(crate) mod mark_upto_date;
 pub(crate) mod serve;
@@ -7,5 +8,9 @@ pub(crate) mod start_tracking;
 pub(crate) mod status;
 pub(crate) mod stop_tracking;
 pub(crate) mod sync;
+pub(crate) mod translate;
+pub(crate) mod translation_export;
+pub(crate) mod translation_import;
+pub(crate) mod translation_lint;
 pub(crate) mod translation_status;
 pub(crate) mod update;
@@ -127,30 +127,185 @@ async fn server_static_file(
         Err(_e) => actix_web::HttpResponse::NotFound().body("TODO".as_bytes()),
     }
 }
-async fn serve_static(req: actix_web::HttpRequest) -> actix_web::HttpResponse {
+fn handle_redirect(redirect: &fpm::redirects::Redirect) -> actix_web::HttpResponse {
+    let mut response = if redirect.permanent {
+        actix_web::HttpResponse::MovedPermanently()
+    } else {
+        actix_web::HttpResponse::Found()
+    };
+    response
+        .insert_header((actix_web::http::header::LOCATION, redirect.url("/")))
+        .finish()
+}
+
+/// `handle_language_negotiation()` redirects to the translation, or the original, of the package
+/// in the language that best matches the `Accept-Language` header of the request, if it is not
+/// the language of the package.
+fn handle_language_negotiation(
+    req: &actix_web::HttpRequest,
+    config: &fpm::Config,
+) -> Option<actix_web::HttpResponse> {
+    let accept_language = req
+        .headers()
+        .get(actix_web::http::header::ACCEPT_LANGUAGE)?
+        .to_str()
+        .ok()?;
+    let packages = config.package.language_packages();
+    let languages = packages
+        .iter()
+        .filter_map(|v| v.language.as_deref())
+        .collect::<Vec<&str>>();
+    let language = best_language(accept_language, &languages)?;
+    if config.package.language.as_deref() == Some(language) {
+        return None;
+    }
+    let package = packages
+        .iter()
+        .find(|v| v.language.as_deref() == Some(language))?;
+    Some(
+        actix_web::HttpResponse::Found()
+            .insert_header((
+                actix_web::http::header::LOCATION,
+                fpm::artifacts::base_url(package),
+            ))
+            .insert_header((actix_web::http::header::VARY, "Accept-Language"))
+            .finish(),
+    )
+}
+
+/// `best_language()` returns the language of `languages` that best matches the value of an
+/// `Accept-Language` header. A language matches a tag with the same language, so `pt` matches
+/// `pt-BR` and the other way around, but an exact match is preferred.
+fn best_language<'a>(accept_language: &str, languages: &[&'a str]) -> Option<&'a str> {
+    let mut accepted = accept_language
+        .split(',')
+        .filter_map(|v| {
+            let mut parts = v.split(';');
+            let tag = parts.next()?.trim();
+            let q = parts
+                .find_map(|v| v.trim().strip_prefix("q="))
+                .and_then(|v| v.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((tag, q))
+        })
+        .filter(|(tag, q)| !tag.is_empty() && *q > 0.0)
+        .collect::<Vec<(&str, f32)>>();
+    // the sort is stable, so tags with the same weight keep their order
+    accepted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    let code = |v: &str| v.split('-').next().unwrap_or(v).to_lowercase();
+    for (tag, _) in accepted {
+        if tag == "*" {
+            return None;
+        }
+        if let Some(v) = languages
+            .iter()
+            .find(|v| v.eq_ignore_ascii_case(tag))
+            .or_else(|| languages.iter().find(|v| code(v) == code(tag)))
+        {
+            return Some(v);
+        }
+    }
+    None
+}
+
+async fn serve_static(
+    req: actix_web::HttpRequest,
+    extra_data: actix_web::web::Data<serde_json::Map<String, serde_json::Value>>,
+    negotiate_language: actix_web::web::Data<bool>,
+) -> actix_web::HttpResponse {
     let mut config = fpm::Config::read(None).await.unwrap();
+    config.extra_data = extra_data.get_ref().clone();
     let path: std::path::PathBuf = req.match_info().query("path").parse().unwrap();
 
     let favicon = std::path::PathBuf::new().join("favicon.ico");
-    if path.starts_with("-/") {
+    if let Some(redirect) = fpm::redirects::find(&config.package, &path.to_string_lossy()) {
+        handle_redirect(redirect)
+    } else if path.starts_with("-/") {
         handle_dash(&req, &config, path).await
     } else if path.eq(&favicon) {
         server_static_file(&req, favicon).await
     } else if path.eq(&std::path::PathBuf::new().join("")) {
-        handle_ftd(&mut config, path.join("index")).await
+        match Some(&req)
+            .filter(|_| *negotiate_language.get_ref())
+            .and_then(|req| handle_language_negotiation(req, &config))
+        {
+            Some(response) => response,
+            None => handle_ftd(&mut config, path.join("index")).await,
+        }
     } else {
         handle_ftd(&mut config, path).await
     }
 }
 
 #[actix_web::main]
-pub async fn serve(port: &str) -> std::io::Result<()> {
+pub async fn serve(
+    port: &str,
+    extra_data: serde_json::Map<String, serde_json::Value>,
+    negotiate_language: bool,
+) -> std::io::Result<()> {
     println!("### Server Started ###");
     println!("Go to: http://127.0.0.1:{}", port);
-    actix_web::HttpServer::new(|| {
-        actix_web::App::new().route("/{path:.*}", actix_web::web::get().to(serve_static))
+    let extra_data = actix_web::web::Data::new(extra_data);
+    let negotiate_language = actix_web::web::Data::new(negotiate_language);
+    actix_web::HttpServer::new(move || {
+        actix_web::App::new()
+            .app_data(extra_data.clone())
+            .app_data(negotiate_language.clone())
+            .route("/{path:.*}", actix_web::web::get().to(serve_static))
     })
     .bind(format!("127.0.0.1:{}", port))?
     .run()
     .await
 }
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn best_language() {
+        let languages = ["en", "hi", "pt-BR"];
+        assert_eq!(
+            super::best_language("hi-IN,hi;q=0.9,en;q=0.8", &languages),
+            Some("hi")
+        );
+        assert_eq!(
+            super::best_language("fr;q=0.9,pt;q=0.5,en;q=0.1", &languages),
+            Some("pt-BR")
+        );
+        assert_eq!(super::best_language("en;q=0.5,hi", &languages), Some("hi"));
+        assert_eq!(super::best_language("fr, *;q=0.5", &languages), None);
+    }
+
+    #[test]
+    fn handle_redirect() {
+        let mut redirect = fpm::redirects::Redirect {
+            from: "guide/install/".to_string(),
+            to: "/docs/install/".to_string(),
+            permanent: true,
+        };
+        let response = super::handle_redirect(&redirect);
+        assert_eq!(
+            response.status(),
+            actix_web::http::StatusCode::MOVED_PERMANENTLY
+        );
+        assert_eq!(
+            response
+                .headers()
+                .get(actix_web::http::header::LOCATION)
+                .unwrap(),
+            "/docs/install/"
+        );
+
+        redirect.to = "https://blog.example.com/".to_string();
+        redirect.permanent = false;
+        let response = super::handle_redirect(&redirect);
+        assert_eq!(response.status(), actix_web::http::StatusCode::FOUND);
+        assert_eq!(
+            response
+                .headers()
+                .get(actix_web::http::header::LOCATION)
+                .unwrap(),
+            "https://blog.example.com/"
+        );
+    }
+}
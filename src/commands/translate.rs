@@ -0,0 +1,64 @@
+/// `translate()` writes a draft translation of the document `file` of the original package, with
+/// its text translated by the machine translation `provider`.
+///
+/// The structure of the document is kept: section names, headers other than text ones and code
+/// are not translated. The draft is never marked up to date, so it stays `Never marked` until it
+/// is reviewed and `fpm mark-upto-date` is run on it. An existing translation is overwritten only
+/// if `force` is set, and is then not up to date anymore.
+pub async fn translate(
+    config: &fpm::Config,
+    file: &str,
+    provider: &str,
+    force: bool,
+) -> fpm::Result<()> {
+    if !config.is_translation_package() {
+        return Err(fpm::Error::UsageError {
+            message: "`translate` works only when `translation-of` is set.".to_string(),
+        });
+    }
+    let provider = fpm::machine_translation::provider(config, provider)?;
+
+    let original_path = config.original_path()?;
+    let original_snapshots = fpm::snapshot::get_latest_snapshots(&original_path).await?;
+    if !original_snapshots.contains_key(file) {
+        return Err(fpm::Error::UsageError {
+            message: format!("`{}` is not a document of the original package", file),
+        });
+    }
+
+    let target_path = config.root.join(file);
+    let track_path = fpm::utils::track_path(file, config.root.as_str());
+    if target_path.exists() {
+        if !force {
+            return Err(fpm::Error::UsageError {
+                message: format!(
+                    "{} is already translated, use `--force` to overwrite it",
+                    file
+                ),
+            });
+        }
+        if track_path.exists() {
+            tokio::fs::remove_file(&track_path).await?;
+        }
+    }
+
+    let source = tokio::fs::read_to_string(original_path.join(file)).await?;
+    let (source_language, target_language) = fpm::commands::translation_export::languages(config);
+    let (translated, count) = fpm::machine_translation::translate(
+        provider.as_ref(),
+        file,
+        source.as_str(),
+        source_language.as_str(),
+        target_language.as_str(),
+    )?;
+    if let Some((dir, _)) = target_path.as_str().rsplit_once('/') {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    tokio::fs::write(&target_path, translated).await?;
+    println!("{} is translated, {} segments", file, count);
+    println!(
+        "Review the translation, and run `fpm mark-upto-date {}` when it is done",
+        file
+    );
+    Ok(())
+}
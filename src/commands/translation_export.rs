@@ -0,0 +1,347 @@
+/// `translation_export()` writes the translation units of the documents of the original package,
+/// with the translation of each unit, if any, as XLIFF 1.2 or gettext PO, so translators can work
+/// on them in their CAT tools. It is written to `output`, or printed if there is no `output`.
+///
+/// Units of out-dated documents carry the text of the original the translation was marked up to
+/// date with, and the diff from it, so translators can see what changed. Units using terms of the
+/// glossary of the package carry them as hints.
+pub async fn translation_export(
+    config: &fpm::Config,
+    format: &str,
+    output: Option<&str>,
+) -> fpm::Result<()> {
+    let format = Format::parse(format)?;
+    let units = translation_units(config).await?;
+    let exported = match format {
+        Format::Xliff => to_xliff(config, &units),
+        Format::Po => to_po(config, &units),
+    };
+    match output {
+        Some(output) => {
+            tokio::fs::write(output, exported).await?;
+            println!("{} translation units written to {}", units.len(), output);
+        }
+        None => print!("{}", exported),
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Format {
+    Xliff,
+    Po,
+}
+
+impl Format {
+    pub(crate) fn parse(s: &str) -> fpm::Result<Format> {
+        match s {
+            "xliff" | "xlf" => Ok(Format::Xliff),
+            "po" => Ok(Format::Po),
+            _ => Err(fpm::Error::UsageError {
+                message: format!("unknown format `{}`, expected `xliff` or `po`", s),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum UnitState {
+    /// not translated yet
+    New,
+    /// translated, but the translation is not marked up to date with the original
+    NeedsReview,
+    Translated,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TranslationUnit {
+    /// id of the document in the original package
+    pub file: String,
+    /// id of the `fpm::segment::Segment` in the document
+    pub id: String,
+    pub line: usize,
+    pub source: String,
+    pub target: Option<String>,
+    /// `previous` is the source the translation was last marked up to date with, if it changed
+    pub previous: Option<String>,
+    pub state: UnitState,
+    /// `hints` are the glossary entries for the terms used in the source
+    pub hints: Vec<String>,
+}
+
+pub(crate) async fn translation_units(config: &fpm::Config) -> fpm::Result<Vec<TranslationUnit>> {
+    let original_path = config.original_path()?;
+    let original_snapshots = fpm::snapshot::get_latest_snapshots(&original_path).await?;
+    let translation_status = fpm::commands::translation_status::get_translation_status(
+        &original_snapshots,
+        &config.root,
+        &original_path,
+    )?;
+    let glossary = fpm::glossary::Glossary::read(config)?;
+
+    let mut units = vec![];
+    for (file, status) in translation_status.iter() {
+        let source = match std::fs::read_to_string(original_path.join(file)) {
+            Ok(v) => v,
+            // not a text document
+            Err(_) => continue,
+        };
+        let segments = fpm::segment::segments(file, source.as_str());
+        if segments.is_empty() {
+            continue;
+        }
+
+        let targets = match status {
+            fpm::commands::translation_status::TranslationStatus::Missing => Default::default(),
+            _ => segment_map(file, config.root.join(file))?,
+        };
+        let previous = match status {
+            fpm::commands::translation_status::TranslationStatus::Outdated { .. } => {
+                let track_path = fpm::utils::track_path(file, config.root.as_str());
+                match fpm::tracker::get_tracks(config.root.as_str(), &track_path)?
+                    .get(file)
+                    .and_then(|v| v.last_merged_version)
+                {
+                    Some(last_merged_version) => segment_map(
+                        file,
+                        fpm::utils::history_path(
+                            file,
+                            original_path.as_str(),
+                            &last_merged_version,
+                        ),
+                    )?,
+                    None => Default::default(),
+                }
+            }
+            _ => Default::default(),
+        };
+
+        for segment in segments {
+            // a never marked translation may still be a copy of the original
+            let target = targets
+                .get(&segment.id)
+                .filter(|v| **v != segment.text)
+                .cloned();
+            let previous = previous
+                .get(&segment.id)
+                .filter(|v| **v != segment.text)
+                .cloned();
+            let state = match (&target, status) {
+                (None, _) => UnitState::New,
+                (Some(_), fpm::commands::translation_status::TranslationStatus::UptoDate) => {
+                    UnitState::Translated
+                }
+                // the original text of the unit did not change since it was marked
+                (
+                    Some(_),
+                    fpm::commands::translation_status::TranslationStatus::Outdated { .. },
+                ) if previous.is_none() => UnitState::Translated,
+                (Some(_), _) => UnitState::NeedsReview,
+            };
+            units.push(TranslationUnit {
+                file: file.to_string(),
+                id: segment.id,
+                line: segment.line,
+                source: segment.text,
+                target,
+                previous,
+                state,
+                hints: glossary.hints(segment.text.as_str()),
+            });
+        }
+    }
+    Ok(units)
+}
+
+/// `segment_map()` returns the text of each segment of the document `file` stored at `path`.
+fn segment_map(
+    file: &str,
+    path: camino::Utf8PathBuf,
+) -> fpm::Result<std::collections::BTreeMap<String, String>> {
+    if !path.exists() {
+        return Ok(Default::default());
+    }
+    let content = std::fs::read_to_string(path)?;
+    Ok(fpm::segment::segments(file, content.as_str())
+        .into_iter()
+        .map(|v| (v.id, v.text))
+        .collect())
+}
+
+/// `languages()` returns the languages of the original package and of this translation.
+pub(crate) fn languages(config: &fpm::Config) -> (String, String) {
+    let source = config
+        .package
+        .translation_of
+        .as_ref()
+        .as_ref()
+        .and_then(|v| v.language.clone())
+        .unwrap_or_else(|| "en".to_string());
+    let target = config
+        .package
+        .language
+        .clone()
+        .unwrap_or_else(|| "und".to_string());
+    (source, target)
+}
+
+pub(crate) fn to_xliff(config: &fpm::Config, units: &[TranslationUnit]) -> String {
+    let (source_language, target_language) = languages(config);
+    let mut xliff = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <xliff version=\"1.2\" xmlns=\"urn:oasis:names:tc:xliff:document:1.2\">\n"
+        .to_string();
+    let mut current_file: Option<&str> = None;
+    for unit in units {
+        if current_file != Some(unit.file.as_str()) {
+            if current_file.is_some() {
+                xliff.push_str("    </body>\n  </file>\n");
+            }
+            xliff.push_str(
+                format!(
+                    "  <file original=\"{}\" source-language=\"{}\" target-language=\"{}\" \
+                    datatype=\"plaintext\">\n    <body>\n",
+                    xml_escape(unit.file.as_str()),
+                    xml_escape(source_language.as_str()),
+                    xml_escape(target_language.as_str()),
+                )
+                .as_str(),
+            );
+            current_file = Some(unit.file.as_str());
+        }
+        xliff.push_str(
+            format!(
+                "      <trans-unit id=\"{}\" xml:space=\"preserve\">\n        <source>{}</source>\n",
+                xml_escape(unit.id.as_str()),
+                xml_escape(unit.source.as_str())
+            )
+            .as_str(),
+        );
+        if let Some(ref target) = unit.target {
+            xliff.push_str(
+                format!(
+                    "        <target state=\"{}\">{}</target>\n",
+                    match unit.state {
+                        UnitState::New => "new",
+                        UnitState::NeedsReview => "needs-review-translation",
+                        UnitState::Translated => "translated",
+                    },
+                    xml_escape(target.as_str())
+                )
+                .as_str(),
+            );
+        }
+        xliff.push_str(
+            format!(
+                "        <context-group purpose=\"location\">\
+                <context context-type=\"sourcefile\">{}</context>\
+                <context context-type=\"linenumber\">{}</context></context-group>\n",
+                xml_escape(unit.file.as_str()),
+                unit.line
+            )
+            .as_str(),
+        );
+        if let Some(ref previous) = unit.previous {
+            let diff = diffy::create_patch(previous.as_str(), unit.source.as_str()).to_string();
+            xliff.push_str(
+                format!(
+                    "        <note from=\"fpm\">The original changed since the translation was \
+                    marked up to date:\n{}</note>\n",
+                    xml_escape(diff.as_str())
+                )
+                .as_str(),
+            );
+        }
+        for hint in unit.hints.iter() {
+            xliff.push_str(
+                format!(
+                    "        <note from=\"glossary\">{}</note>\n",
+                    xml_escape(hint.as_str())
+                )
+                .as_str(),
+            );
+        }
+        xliff.push_str("      </trans-unit>\n");
+    }
+    if current_file.is_some() {
+        xliff.push_str("    </body>\n  </file>\n");
+    }
+    xliff.push_str("</xliff>\n");
+    xliff
+}
+
+pub(crate) fn to_po(config: &fpm::Config, units: &[TranslationUnit]) -> String {
+    let (_, target_language) = languages(config);
+    let mut po = format!(
+        "msgid \"\"\nmsgstr \"\"\n\"Project-Id-Version: {}\\n\"\n\"Language: {}\\n\"\n\
+        \"MIME-Version: 1.0\\n\"\n\"Content-Type: text/plain; charset=UTF-8\\n\"\n\
+        \"Content-Transfer-Encoding: 8bit\\n\"\n",
+        po_escape(config.package.name.as_str()),
+        po_escape(target_language.as_str()),
+    );
+    for unit in units {
+        po.push('\n');
+        for hint in unit.hints.iter() {
+            po.push_str(format!("#. {}\n", hint).as_str());
+        }
+        po.push_str(format!("#: {}:{}\n", unit.file, unit.line).as_str());
+        if unit.state == UnitState::NeedsReview {
+            po.push_str("#, fuzzy\n");
+        }
+        if let Some(ref previous) = unit.previous {
+            po.push_str(
+                po_string(previous.as_str())
+                    .lines()
+                    .enumerate()
+                    .map(|(i, l)| {
+                        if i == 0 {
+                            format!("#| msgid {}\n", l)
+                        } else {
+                            format!("#| {}\n", l)
+                        }
+                    })
+                    .collect::<String>()
+                    .as_str(),
+            );
+        }
+        po.push_str(
+            format!(
+                "msgctxt {}\nmsgid {}\nmsgstr {}\n",
+                po_string(format!("{}|{}", unit.file, unit.id).as_str()),
+                po_string(unit.source.as_str()),
+                po_string(unit.target.as_deref().unwrap_or_default()),
+            )
+            .as_str(),
+        );
+    }
+    po
+}
+
+pub(crate) fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn po_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+/// `po_string()` quotes `s`, a multi line string is written as one quoted string per line.
+fn po_string(s: &str) -> String {
+    if !s.contains('\n') {
+        return format!("\"{}\"", po_escape(s));
+    }
+    let lines = s.split_inclusive('\n').collect::<Vec<&str>>();
+    format!(
+        "\"\"\n{}",
+        lines
+            .iter()
+            .map(|l| format!("\"{}\"", po_escape(l)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    )
+}
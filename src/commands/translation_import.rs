@@ -0,0 +1,497 @@
+/// `translation_import()` reads the translation units of an XLIFF or PO file, written by
+/// `fpm translation export` and filled in by translators, and writes the translated documents.
+///
+/// A translated document is the original document with the text of each unit replaced by its
+/// translation. Units without a translation keep the text of the existing translated document, or
+/// the text of the original. Units whose source is not the current text of the original, as the
+/// original changed after the export, are skipped and reported. Documents whose units are all
+/// translated, and not marked fuzzy or needing review, are synced and marked up to date with the
+/// original.
+pub async fn translation_import(
+    config: &fpm::Config,
+    path: &str,
+    format: Option<&str>,
+) -> fpm::Result<()> {
+    let original_package = match config.package.translation_of.as_ref() {
+        Some(ref original) => original,
+        None => {
+            return Err(fpm::Error::UsageError {
+                message: "`translation import` works only when `translation-of` is set."
+                    .to_string(),
+            })
+        }
+    };
+    let format = match format {
+        Some(format) => fpm::commands::translation_export::Format::parse(format)?,
+        None => fpm::commands::translation_export::Format::parse(
+            path.rsplit_once('.')
+                .map(|(_, ext)| ext)
+                .unwrap_or_default(),
+        )?,
+    };
+    let content = tokio::fs::read_to_string(path).await?;
+    let units = match format {
+        fpm::commands::translation_export::Format::Xliff => from_xliff(content.as_str()),
+        fpm::commands::translation_export::Format::Po => from_po(content.as_str()),
+    };
+
+    let mut files: std::collections::BTreeMap<String, std::collections::BTreeMap<String, Unit>> =
+        Default::default();
+    for unit in units {
+        files
+            .entry(unit.file.to_string())
+            .or_default()
+            .insert(unit.id.to_string(), unit);
+    }
+
+    let original_path = config.original_path()?;
+    let original_snapshots = fpm::snapshot::get_latest_snapshots(&original_path).await?;
+    if let Some(file) = files.keys().find(|v| !original_snapshots.contains_key(*v)) {
+        return Err(fpm::Error::UsageError {
+            message: format!(
+                "{}: `{}` is not a document of the original package `{}`",
+                path, file, original_package.name
+            ),
+        });
+    }
+
+    let mut completed = vec![];
+    for (file, units) in files.iter() {
+        let source = tokio::fs::read_to_string(original_path.join(file)).await?;
+        let units = current_units(file, source.as_str(), units);
+        let target_path = config.root.join(file);
+        let existing = if target_path.exists() {
+            let content = tokio::fs::read_to_string(&target_path).await?;
+            fpm::segment::segments(file, content.as_str())
+                .into_iter()
+                .map(|v| (v.id, v.text))
+                .collect()
+        } else {
+            std::collections::BTreeMap::new()
+        };
+
+        let translated = fpm::segment::replace(file, source.as_str(), |segment| {
+            units
+                .get(&segment.id)
+                .filter(|v| !v.target.is_empty())
+                .map(|v| v.target.to_string())
+                .or_else(|| existing.get(&segment.id).cloned())
+        });
+        if let Some((dir, _)) = target_path.as_str().rsplit_once('/') {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&target_path, translated).await?;
+        println!("{} is translated", file);
+
+        if fpm::segment::segments(file, source.as_str())
+            .iter()
+            .all(|segment| {
+                units
+                    .get(&segment.id)
+                    .map(|v| v.done && !v.target.is_empty())
+                    .unwrap_or(false)
+            })
+        {
+            completed.push(file.to_string());
+        }
+    }
+
+    if completed.is_empty() {
+        return Ok(());
+    }
+    fpm::sync(config, Some(completed.clone())).await?;
+    for file in completed {
+        fpm::mark_upto_date(config, file.as_str(), None).await?;
+    }
+    Ok(())
+}
+
+/// `current_units()` returns the units of `file` whose source is still the text of the segment
+/// in the original document `source`, and reports the others.
+fn current_units<'a>(
+    file: &str,
+    source: &str,
+    units: &'a std::collections::BTreeMap<String, Unit>,
+) -> std::collections::BTreeMap<String, &'a Unit> {
+    use colorize::AnsiColor;
+
+    let segments = fpm::segment::segments(file, source)
+        .into_iter()
+        .map(|v| (v.id, v.text))
+        .collect::<std::collections::BTreeMap<String, String>>();
+    let mut current = std::collections::BTreeMap::new();
+    for (id, unit) in units.iter() {
+        match segments.get(id) {
+            Some(text) if text == &unit.source => {
+                current.insert(id.to_string(), unit);
+            }
+            Some(_) => warning!(format!(
+                "{}: skipping `{}`, the original changed after it was exported",
+                file, id
+            )),
+            None => warning!(format!(
+                "{}: skipping `{}`, it is not in the original anymore",
+                file, id
+            )),
+        }
+    }
+    current
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Unit {
+    file: String,
+    id: String,
+    /// `source` is the text of the original the unit was exported with
+    source: String,
+    target: String,
+    /// `done` is false if the translation is missing, empty, fuzzy or needs review
+    done: bool,
+}
+
+fn from_xliff(content: &str) -> Vec<Unit> {
+    lazy_static! {
+        static ref FILE: regex::Regex =
+            regex::Regex::new(r#"(?s)<file\s[^>]*original="([^"]*)"[^>]*>(.*?)</file>"#).unwrap();
+        static ref UNIT: regex::Regex =
+            regex::Regex::new(r#"(?s)<trans-unit\s[^>]*id="([^"]*)"[^>]*>(.*?)</trans-unit>"#)
+                .unwrap();
+        static ref SOURCE: regex::Regex =
+            regex::Regex::new(r#"(?s)<source(\s[^>]*)?>(.*?)</source>"#).unwrap();
+        static ref TARGET: regex::Regex =
+            regex::Regex::new(r#"(?s)<target(\s[^>]*)?>(.*?)</target>"#).unwrap();
+        static ref STATE: regex::Regex = regex::Regex::new(r#"state="([^"]*)""#).unwrap();
+    }
+
+    let mut units = vec![];
+    for file in FILE.captures_iter(content) {
+        for unit in UNIT.captures_iter(&file[2]) {
+            let target = match TARGET.captures(&unit[2]) {
+                Some(v) => v,
+                None => continue,
+            };
+            let state = target
+                .get(1)
+                .and_then(|v| STATE.captures(v.as_str()))
+                .map(|v| v[1].to_string());
+            let text = xml_unescape(&target[2]);
+            units.push(Unit {
+                file: xml_unescape(&file[1]),
+                id: xml_unescape(&unit[1]),
+                source: SOURCE
+                    .captures(&unit[2])
+                    .map(|v| xml_unescape(&v[2]))
+                    .unwrap_or_default(),
+                // a target without a state is a draft, it is not marked up to date
+                done: !text.is_empty()
+                    && matches!(
+                        state.as_deref(),
+                        Some("translated") | Some("final") | Some("signed-off")
+                    ),
+                target: text,
+            });
+        }
+    }
+    units
+}
+
+/// `xml_unescape()` replaces the predefined entities, and the character references, like `&#10;`
+/// or `&#xA;`, with the characters they stand for.
+fn xml_unescape(s: &str) -> String {
+    let mut output = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let c = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "amp" => Some('&'),
+                entity => entity
+                    .strip_prefix('#')
+                    .and_then(|v| match v.strip_prefix(|c| c == 'x' || c == 'X') {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => v.parse::<u32>().ok(),
+                    })
+                    .and_then(std::char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match c {
+            Some((c, end)) => {
+                output.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn from_po(content: &str) -> Vec<Unit> {
+    #[derive(Default)]
+    struct Entry {
+        fuzzy: bool,
+        msgctxt: String,
+        msgid: String,
+        msgstr: String,
+    }
+
+    fn unit(entry: &Entry) -> Option<Unit> {
+        let (file, id) = entry.msgctxt.split_once('|')?;
+        Some(Unit {
+            file: file.to_string(),
+            id: id.to_string(),
+            source: entry.msgid.to_string(),
+            target: entry.msgstr.to_string(),
+            done: !entry.fuzzy && !entry.msgstr.is_empty(),
+        })
+    }
+
+    let mut units = vec![];
+    let mut entry = Entry::default();
+    // the field continuation strings are added to
+    let mut field = "";
+    for line in content.lines().map(|v| v.trim()) {
+        if line.is_empty() {
+            units.extend(unit(&entry));
+            entry = Entry::default();
+            field = "";
+        } else if let Some(flags) = line.strip_prefix("#,") {
+            entry.fuzzy = flags.split(',').any(|v| v.trim() == "fuzzy");
+        } else if line.starts_with('#') {
+            continue;
+        } else if let Some(v) = line.strip_prefix("msgctxt ") {
+            field = "msgctxt";
+            entry.msgctxt = po_unescape(v);
+        } else if let Some(v) = line.strip_prefix("msgid ") {
+            field = "msgid";
+            entry.msgid = po_unescape(v);
+        } else if let Some(v) = line.strip_prefix("msgstr ") {
+            field = "msgstr";
+            entry.msgstr = po_unescape(v);
+        } else if line.starts_with('"') {
+            match field {
+                "msgctxt" => entry.msgctxt.push_str(po_unescape(line).as_str()),
+                "msgid" => entry.msgid.push_str(po_unescape(line).as_str()),
+                "msgstr" => entry.msgstr.push_str(po_unescape(line).as_str()),
+                _ => {}
+            }
+        }
+    }
+    units.extend(unit(&entry));
+    units
+}
+
+/// `po_unescape()` returns the content of the quoted PO string `s`.
+fn po_unescape(s: &str) -> String {
+    let s = s.trim();
+    let s = s.strip_prefix('"').unwrap_or(s);
+    let s = s.strip_suffix('"').unwrap_or(s);
+    let mut output = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => output.push('\n'),
+            Some('t') => output.push('\t'),
+            Some(c) => output.push(c),
+            None => {}
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn from_po() {
+        let po = indoc::indoc! {r#"
+            msgid ""
+            msgstr ""
+            "Language: hi\n"
+
+            #: index.ftd:3
+            msgctxt "index.ftd|ftd.text#1.caption"
+            msgid "Hello World"
+            msgstr "नमस्ते दुनिया"
+
+            #: index.ftd:12
+            #, fuzzy
+            msgctxt "index.ftd|ftd.text#2.body"
+            msgid ""
+            "Some \"markdown\" text,\n"
+            "over two lines."
+            msgstr ""
+            "कुछ \"markdown\" पाठ,\n"
+            "दो पंक्तियों में।"
+        "#};
+        assert_eq!(
+            super::from_po(po),
+            vec![
+                super::Unit {
+                    file: "index.ftd".to_string(),
+                    id: "ftd.text#1.caption".to_string(),
+                    source: "Hello World".to_string(),
+                    target: "नमस्ते दुनिया".to_string(),
+                    done: true,
+                },
+                super::Unit {
+                    file: "index.ftd".to_string(),
+                    id: "ftd.text#2.body".to_string(),
+                    source: "Some \"markdown\" text,\nover two lines.".to_string(),
+                    target: "कुछ \"markdown\" पाठ,\nदो पंक्तियों में।".to_string(),
+                    done: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn from_xliff() {
+        let xliff = indoc::indoc! {r#"
+            <?xml version="1.0" encoding="UTF-8"?>
+            <xliff version="1.2" xmlns="urn:oasis:names:tc:xliff:document:1.2">
+              <file original="index.ftd" source-language="en" target-language="hi">
+                <body>
+                  <trans-unit id="ftd.text#1.caption">
+                    <source>Hello&#10;World &amp; &#x263A;</source>
+                    <target state="final">नमस्ते&#xA;दुनिया &amp;amp;</target>
+                  </trans-unit>
+                  <trans-unit id="ftd.text#2.caption">
+                    <source>Draft</source>
+                    <target>मसौदा</target>
+                  </trans-unit>
+                  <trans-unit id="ftd.text#3.caption">
+                    <source>Empty</source>
+                    <target state="translated"></target>
+                  </trans-unit>
+                  <trans-unit id="ftd.text#4.caption">
+                    <source>New</source>
+                  </trans-unit>
+                </body>
+              </file>
+            </xliff>
+        "#};
+        let units = super::from_xliff(xliff);
+        assert_eq!(
+            units
+                .iter()
+                .map(|v| (v.source.as_str(), v.target.as_str(), v.done))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Hello\nWorld & ☺", "नमस्ते\nदुनिया &amp;", true),
+                ("Draft", "मसौदा", false),
+                ("Empty", "", false),
+            ]
+        );
+        assert_eq!(super::xml_unescape("a &b; &#xZZ; &"), "a &b; &#xZZ; &");
+    }
+
+    fn exported_units() -> Vec<fpm::commands::translation_export::TranslationUnit> {
+        use fpm::commands::translation_export::{TranslationUnit, UnitState};
+
+        let unit =
+            |id: &str, source: &str, target: Option<&str>, state: UnitState| TranslationUnit {
+                file: "guide/index.ftd".to_string(),
+                id: id.to_string(),
+                line: 1,
+                source: source.to_string(),
+                target: target.map(|v| v.to_string()),
+                previous: None,
+                state,
+                hints: vec!["FPM: do not translate".to_string()],
+            };
+        vec![
+            unit(
+                "ftd.text#1.caption",
+                "Hello <World> & \"friends\"",
+                Some("नमस्ते <दुनिया> & \"दोस्तों\""),
+                UnitState::Translated,
+            ),
+            unit(
+                "ftd.text#2.body",
+                "Some *markdown*,\n\tover two lines.\n",
+                Some("कुछ *markdown*,\n\tदो पंक्तियों में।\n"),
+                UnitState::NeedsReview,
+            ),
+            unit("ftd.text#3.caption", "Not yet", None, UnitState::New),
+        ]
+    }
+
+    fn imported_units() -> Vec<super::Unit> {
+        exported_units()
+            .into_iter()
+            .filter(|v| v.target.is_some())
+            .map(|v| super::Unit {
+                file: v.file,
+                id: v.id,
+                source: v.source,
+                target: v.target.unwrap_or_default(),
+                done: v.state == fpm::commands::translation_export::UnitState::Translated,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn xliff_round_trip() {
+        let mut config = fpm::Config::for_test(camino::Utf8PathBuf::from("."));
+        config.package.language = Some("hi".to_string());
+        let xliff = fpm::commands::translation_export::to_xliff(&config, &exported_units());
+        assert_eq!(super::from_xliff(xliff.as_str()), imported_units());
+    }
+
+    #[test]
+    fn po_round_trip() {
+        let mut config = fpm::Config::for_test(camino::Utf8PathBuf::from("."));
+        config.package.language = Some("hi".to_string());
+        let po = fpm::commands::translation_export::to_po(&config, &exported_units());
+        let mut expected = imported_units();
+        // untranslated units are exported with an empty `msgstr`
+        expected.push(super::Unit {
+            file: "guide/index.ftd".to_string(),
+            id: "ftd.text#3.caption".to_string(),
+            source: "Not yet".to_string(),
+            target: "".to_string(),
+            done: false,
+        });
+        assert_eq!(super::from_po(po.as_str()), expected);
+    }
+
+    #[test]
+    fn current_units() {
+        let source = "-- ftd.text: Hello World\n\n-- ftd.text:\n\nSome text.\n";
+        let segments = fpm::segment::segments("index.ftd", source);
+        let unit = |id: &str, source: &str| super::Unit {
+            file: "index.ftd".to_string(),
+            id: id.to_string(),
+            source: source.to_string(),
+            target: "translated".to_string(),
+            done: true,
+        };
+        let units = vec![
+            unit(segments[0].id.as_str(), "Hello World"),
+            unit(segments[1].id.as_str(), "Some old text."),
+            unit("ftd.text#9.caption", "Removed"),
+        ]
+        .into_iter()
+        .map(|v| (v.id.to_string(), v))
+        .collect();
+        assert_eq!(
+            super::current_units("index.ftd", source, &units)
+                .into_keys()
+                .collect::<Vec<String>>(),
+            vec![segments[0].id.to_string()]
+        );
+    }
+}
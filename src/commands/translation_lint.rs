@@ -0,0 +1,61 @@
+/// `translation_lint()` checks the translated documents against the glossary of the package,
+/// `FPM/glossary.ftd`, and prints each violation with the document id and line. Only `files` are
+/// checked, if any are given. An error is returned if there are violations.
+pub async fn translation_lint(config: &fpm::Config, files: &[String]) -> fpm::Result<()> {
+    if !config.is_translation_package() {
+        return Err(fpm::Error::UsageError {
+            message: "`translation lint` works only when `translation-of` is set.".to_string(),
+        });
+    }
+    let glossary = fpm::glossary::Glossary::read(config)?;
+    if glossary.is_empty() {
+        println!("No glossary found, add terms to FPM/glossary.ftd");
+        return Ok(());
+    }
+
+    let original_path = config.original_path()?;
+    let original_snapshots = fpm::snapshot::get_latest_snapshots(&original_path).await?;
+    let mut count = 0;
+    for file in original_snapshots.keys() {
+        if !files.is_empty() && !files.contains(file) {
+            continue;
+        }
+        let target_path = config.root.join(file);
+        if !target_path.exists() {
+            continue;
+        }
+        let source = match tokio::fs::read_to_string(original_path.join(file)).await {
+            Ok(v) => v,
+            // not a text document
+            Err(_) => continue,
+        };
+        let target = tokio::fs::read_to_string(&target_path).await?;
+        let targets = fpm::segment::segments(file, target.as_str())
+            .into_iter()
+            .map(|v| (v.id.to_string(), v))
+            .collect::<std::collections::BTreeMap<String, fpm::segment::Segment>>();
+        for segment in fpm::segment::segments(file, source.as_str()) {
+            let translated = match targets.get(&segment.id) {
+                // an untranslated segment is not a violation
+                Some(v) if v.text != segment.text => v,
+                _ => continue,
+            };
+            for (line, violation) in glossary.check(segment.text.as_str(), translated.text.as_str())
+            {
+                // the translation is assumed to keep the lines of the original, a term on a line
+                // past the end of the translation is reported on its last line
+                let line = translated.line + line.min(translated.text.lines().count().max(1) - 1);
+                println!("{}:{}: {}: {}", file, line, segment.id, violation);
+                count += 1;
+            }
+        }
+    }
+
+    if count > 0 {
+        return Err(fpm::Error::UsageError {
+            message: format!("{} glossary violations found", count),
+        });
+    }
+    println!("No glossary violations found");
+    Ok(())
+}
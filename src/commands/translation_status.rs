@@ -1,9 +1,21 @@
-pub async fn translation_status(config: &fpm::Config) -> fpm::Result<()> {
+/// `translation_status()` prints the translation status of the documents of the package, or of
+/// each translation of an original package.
+///
+/// `format` is `text`, `json` or `csv`. Only the documents with a status in `filter`, if it is not
+/// empty, are listed. If a document has a status in `fail_on` an error is returned after the
+/// status is printed, so CI can stop a release with missing or out-dated translations.
+pub async fn translation_status(
+    config: &mut fpm::Config,
+    format: &str,
+    filter: &[String],
+    fail_on: &[String],
+) -> fpm::Result<()> {
     // it can be original package or translation
-    if config.is_translation_package() {
-        translation_package_status(config).await?;
+    let packages = if config.is_translation_package() {
+        translation_package_status(config).await?
     } else if !config.package.translations.is_empty() {
-        original_package_status(config).await?;
+        fpm::dependency::ensure_translations(config).await?;
+        original_package_status(config).await?
     } else {
         return Err(fpm::Error::UsageError {
             message:
@@ -11,29 +23,241 @@ pub async fn translation_status(config: &fpm::Config) -> fpm::Result<()> {
                     .to_string(),
         });
     };
-    Ok(())
+
+    match format {
+        "json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&to_json(&packages, filter))?
+        ),
+        "csv" => print!("{}", to_csv(&packages, filter)?),
+        _ => print_text(config, &packages, filter),
+    }
+    check_fail_on(&packages, fail_on)
 }
 
-async fn translation_package_status(config: &fpm::Config) -> fpm::Result<()> {
-    let original_snapshots = fpm::snapshot::get_latest_snapshots(&config.original_path()?).await?;
-    let translation_status = get_translation_status(&original_snapshots, &config.root)?;
-    print_translation_status(&translation_status);
+/// `check_fail_on()` returns an error if some document has a status in `fail_on`.
+fn check_fail_on(packages: &[PackageStatus], fail_on: &[String]) -> fpm::Result<()> {
+    for status in fail_on {
+        let count = packages
+            .iter()
+            .map(|v| match (&v.documents, &v.summary) {
+                (Some(documents), _) => documents.values().filter(|v| v.key() == status).count(),
+                (None, Some(summary)) => summary.count(status.as_str()) as usize,
+                (None, None) => 0,
+            })
+            .sum::<usize>();
+        if count > 0 {
+            return Err(fpm::Error::UsageError {
+                message: format!("{} documents have translation status `{}`", count, status),
+            });
+        }
+    }
     Ok(())
 }
 
-async fn original_package_status(config: &fpm::Config) -> fpm::Result<()> {
+/// `PackageStatus` is the translation status of the documents of a translation package.
+struct PackageStatus {
+    name: String,
+    language: Option<String>,
+    /// `documents` is not known for a translation of an original package if only its `FPM.ftd` is
+    /// downloaded, only its `summary` is
+    documents: Option<std::collections::BTreeMap<String, TranslationStatus>>,
+    summary: Option<fpm::translation::TranslationStatusSummary>,
+}
+
+async fn translation_package_status(config: &fpm::Config) -> fpm::Result<Vec<PackageStatus>> {
+    let original_path = config.original_path()?;
+    let original_snapshots = fpm::snapshot::get_latest_snapshots(&original_path).await?;
+    let translation_status =
+        get_translation_status(&original_snapshots, &config.root, &original_path)?;
+    Ok(vec![PackageStatus {
+        name: config.package.name.to_string(),
+        language: config.package.language.clone(),
+        documents: Some(translation_status),
+        summary: None,
+    }])
+}
+
+async fn original_package_status(config: &fpm::Config) -> fpm::Result<Vec<PackageStatus>> {
+    let snapshots = fpm::snapshot::get_latest_snapshots(&config.root).await?;
+    let mut packages = vec![];
     for translation in config.package.translations.iter() {
-        if let Some(ref status) = translation.translation_status_summary {
-            println!("Status for `{}` package:", translation.name);
-            println!("{}", status.to_string());
+        let root = config.packages_root.join(translation.name.as_str());
+        let documents = if root.join("FPM.ftd").exists() {
+            Some(get_translation_status(&snapshots, &root, &config.root)?)
+        } else {
+            None
+        };
+        packages.push(PackageStatus {
+            name: translation.name.to_string(),
+            language: translation.language.clone(),
+            documents,
+            summary: translation.translation_status_summary.clone(),
+        });
+    }
+    Ok(packages)
+}
+
+/// `documents()` returns the documents of the package with a status in `filter`.
+fn documents<'a>(
+    package: &'a PackageStatus,
+    filter: &'a [String],
+) -> impl Iterator<Item = (&'a String, &'a TranslationStatus)> {
+    package
+        .documents
+        .iter()
+        .flatten()
+        .filter(move |(_, status)| filter.is_empty() || filter.iter().any(|v| v == status.key()))
+}
+
+/// `age()` tells how long ago the out-dated translation was last marked up to date.
+fn age(last_marked_on: &u128) -> String {
+    let now = fpm::get_timestamp_nanosecond();
+    fpm::utils::seconds_to_human((now.saturating_sub(*last_marked_on) / 1_000_000_000) as u64)
+}
+
+fn print_text(config: &fpm::Config, packages: &[PackageStatus], filter: &[String]) {
+    for package in packages {
+        if !config.is_translation_package() {
+            match package.summary {
+                Some(ref status) => {
+                    println!("Status for `{}` package:", package.name);
+                    println!("{}", status.to_string());
+                }
+                None if package.documents.is_some() => {
+                    println!("Status for `{}` package:", package.name);
+                }
+                None => continue,
+            }
+        }
+        for (file, status) in documents(package, filter) {
+            match status {
+                TranslationStatus::Outdated {
+                    sections,
+                    last_marked_on,
+                } => {
+                    println!(
+                        "{}: {} (marked up to date {})",
+                        status.as_str(),
+                        file,
+                        age(last_marked_on).to_lowercase()
+                    );
+                    for section in sections {
+                        println!(
+                            "    {}: {} (line {})",
+                            section.change.as_str(),
+                            section.id,
+                            section.line
+                        );
+                    }
+                }
+                _ => println!("{}: {}", status.as_str(), file),
+            }
         }
     }
-    Ok(())
 }
 
+fn to_json(packages: &[PackageStatus], filter: &[String]) -> serde_json::Value {
+    serde_json::Value::Array(
+        packages
+            .iter()
+            .map(|package| {
+                serde_json::json!({
+                    "package": package.name,
+                    "language": package.language,
+                    "summary": package.summary,
+                    "documents": package.documents.as_ref().map(|_| {
+                        documents(package, filter)
+                            .map(|(file, status)| match status {
+                                TranslationStatus::Outdated {
+                                    sections,
+                                    last_marked_on,
+                                } => serde_json::json!({
+                                    "file": file,
+                                    "status": status.key(),
+                                    "last-marked-on": fpm::utils::nanos_to_rfc3339(last_marked_on),
+                                    "age": age(last_marked_on),
+                                    "sections": sections
+                                        .iter()
+                                        .map(|v| serde_json::json!({
+                                            "id": v.id,
+                                            "change": v.change.as_str(),
+                                            "line": v.line,
+                                        }))
+                                        .collect::<Vec<serde_json::Value>>(),
+                                }),
+                                _ => serde_json::json!({
+                                    "file": file,
+                                    "status": status.key(),
+                                }),
+                            })
+                            .collect::<Vec<serde_json::Value>>()
+                    }),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn to_csv(packages: &[PackageStatus], filter: &[String]) -> fpm::Result<String> {
+    let csv_error = |e: csv::Error| fpm::Error::UsageError {
+        message: format!("failed to write csv: {}", e),
+    };
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer
+        .write_record(&[
+            "package",
+            "language",
+            "file",
+            "status",
+            "last-marked-on",
+            "age",
+            "outdated-sections",
+        ])
+        .map_err(csv_error)?;
+    for package in packages {
+        for (file, status) in documents(package, filter) {
+            let (last_marked_on, age, sections) = match status {
+                TranslationStatus::Outdated {
+                    sections,
+                    last_marked_on,
+                } => (
+                    fpm::utils::nanos_to_rfc3339(last_marked_on),
+                    age(last_marked_on),
+                    sections
+                        .iter()
+                        .map(|v| v.id.as_str())
+                        .collect::<Vec<&str>>()
+                        .join(" "),
+                ),
+                _ => Default::default(),
+            };
+            writer
+                .write_record(&[
+                    package.name.as_str(),
+                    package.language.as_deref().unwrap_or_default(),
+                    file.as_str(),
+                    status.key(),
+                    last_marked_on.as_str(),
+                    age.as_str(),
+                    sections.as_str(),
+                ])
+                .map_err(csv_error)?;
+        }
+    }
+    let csv = writer.into_inner().map_err(|e| fpm::Error::UsageError {
+        message: format!("failed to write csv: {}", e),
+    })?;
+    Ok(String::from_utf8_lossy(csv.as_slice()).to_string())
+}
+
+/// `get_translation_status()` returns the status of the translation, in the package at `path`, of
+/// each document of the original package at `original_path`. A translation marked up to date with
+/// an older version of the original is out-dated only if some section of the original changed.
 pub(crate) fn get_translation_status(
     snapshots: &std::collections::BTreeMap<String, u128>,
     path: &camino::Utf8PathBuf,
+    original_path: &camino::Utf8PathBuf,
 ) -> fpm::Result<std::collections::BTreeMap<String, TranslationStatus>> {
     let mut translation_status = std::collections::BTreeMap::new();
     for (file, timestamp) in snapshots {
@@ -53,8 +277,24 @@ pub(crate) fn get_translation_status(
         }) = tracks.get(file)
         {
             if last_merged_version < timestamp {
-                translation_status.insert(file.clone(), TranslationStatus::Outdated);
-                continue;
+                match fpm::translation::outdated_sections(
+                    original_path,
+                    file,
+                    last_merged_version,
+                    timestamp,
+                ) {
+                    Some(sections) if sections.is_empty() => {}
+                    sections => {
+                        translation_status.insert(
+                            file.clone(),
+                            TranslationStatus::Outdated {
+                                sections: sections.unwrap_or_default(),
+                                last_marked_on: *last_merged_version,
+                            },
+                        );
+                        continue;
+                    }
+                }
             }
             translation_status.insert(file.clone(), TranslationStatus::UptoDate);
         } else {
@@ -64,18 +304,15 @@ pub(crate) fn get_translation_status(
     Ok(translation_status)
 }
 
-fn print_translation_status(
-    translation_status: &std::collections::BTreeMap<String, TranslationStatus>,
-) {
-    for (file, status) in translation_status {
-        println!("{}: {}", status.as_str(), file);
-    }
-}
-
 pub(crate) enum TranslationStatus {
     Missing,
     NeverMarked,
-    Outdated,
+    /// `sections` of the original changed since the translation was marked up to date, it is empty
+    /// if the history of the original is not available
+    Outdated {
+        sections: Vec<fpm::translation::OutdatedSection>,
+        last_marked_on: u128,
+    },
     UptoDate,
 }
 
@@ -84,8 +321,132 @@ impl TranslationStatus {
         match self {
             TranslationStatus::Missing => "Missing",
             TranslationStatus::NeverMarked => "Never marked",
-            TranslationStatus::Outdated => "Out-dated",
+            TranslationStatus::Outdated { .. } => "Out-dated",
             TranslationStatus::UptoDate => "Up to date",
         }
     }
+
+    /// `key()` is the name of the status in the `translation-status` options and output
+    pub(crate) fn key(&self) -> &'static str {
+        match self {
+            TranslationStatus::Missing => "missing",
+            TranslationStatus::NeverMarked => "never-marked",
+            TranslationStatus::Outdated { .. } => "outdated",
+            TranslationStatus::UptoDate => "upto-date",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TranslationStatus;
+
+    fn packages() -> Vec<super::PackageStatus> {
+        let two_days_ago = fpm::get_timestamp_nanosecond() - 2 * 24 * 3600 * 1_000_000_000;
+        vec![
+            super::PackageStatus {
+                name: "hi.example.com".to_string(),
+                language: Some("hi".to_string()),
+                documents: Some(
+                    vec![
+                        ("index.ftd".to_string(), TranslationStatus::UptoDate),
+                        ("about.ftd".to_string(), TranslationStatus::Missing),
+                        (
+                            "guide.ftd".to_string(),
+                            TranslationStatus::Outdated {
+                                sections: vec![fpm::translation::OutdatedSection {
+                                    id: "ftd.text#2".to_string(),
+                                    line: 5,
+                                    change: fpm::translation::SectionChange::Changed,
+                                    diff: "".to_string(),
+                                }],
+                                last_marked_on: two_days_ago,
+                            },
+                        ),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+                summary: None,
+            },
+            // only the `FPM.ftd` of this translation is downloaded
+            super::PackageStatus {
+                name: "fr.example.com".to_string(),
+                language: Some("fr".to_string()),
+                documents: None,
+                summary: Some(fpm::translation::TranslationStatusSummary {
+                    never_marked: 1,
+                    missing: 0,
+                    out_dated: 0,
+                    upto_date: 2,
+                    out_dated_sections: None,
+                    last_modified_on: None,
+                }),
+            },
+        ]
+    }
+
+    #[test]
+    fn to_json() {
+        let json = super::to_json(&packages(), &["outdated".to_string()]);
+        assert_eq!(json[0]["package"], "hi.example.com");
+        assert_eq!(
+            json[0]["documents"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|v| v["file"].as_str().unwrap())
+                .collect::<Vec<&str>>(),
+            vec!["guide.ftd"]
+        );
+        let guide = &json[0]["documents"][0];
+        assert_eq!(guide["status"], "outdated");
+        assert_eq!(guide["age"], "2 days ago");
+        assert_eq!(
+            guide["sections"],
+            serde_json::json!([{"id": "ftd.text#2", "change": "changed", "line": 5}])
+        );
+        assert_eq!(json[1]["documents"], serde_json::Value::Null);
+        assert_eq!(json[1]["summary"]["never-marked"], 1);
+
+        let json = super::to_json(&packages(), &[]);
+        assert_eq!(json[0]["documents"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn to_csv() {
+        let csv = super::to_csv(
+            &packages(),
+            &["missing".to_string(), "upto-date".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            csv,
+            indoc::indoc! {"
+                package,language,file,status,last-marked-on,age,outdated-sections
+                hi.example.com,hi,about.ftd,missing,,,
+                hi.example.com,hi,index.ftd,upto-date,,,
+            "}
+        );
+        let csv = super::to_csv(&packages(), &["outdated".to_string()]).unwrap();
+        assert!(csv.contains(",guide.ftd,outdated,"));
+        assert!(csv.trim_end().ends_with(",2 days ago,ftd.text#2"));
+    }
+
+    #[test]
+    fn check_fail_on() {
+        let packages = packages();
+        assert!(super::check_fail_on(&packages, &[]).is_ok());
+        assert!(super::check_fail_on(&packages, &["never-marked".to_string()]).is_err());
+        assert!(super::check_fail_on(&packages, &["missing".to_string()]).is_err());
+        assert!(super::check_fail_on(
+            &packages,
+            &["upto-date".to_string(), "outdated".to_string()]
+        )
+        .is_err());
+
+        let packages = &packages[1..];
+        assert!(super::check_fail_on(packages, &["missing".to_string()]).is_ok());
+        assert!(super::check_fail_on(packages, &["never-marked".to_string()]).is_err());
+    }
 }
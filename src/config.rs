@@ -43,6 +43,26 @@ pub struct Config {
     /// document.
     /// It is consumed by the `sitemap` processor.
     pub current_document: Option<String>,
+    /// `highlighted_code` is the syntax highlighted html of the code file being built, when
+    /// `highlight-code` is enabled for the package. It is computed once by `fpm build` for each
+    /// code file, and is rendered in the code page, see `fpm::package_info_code()`.
+    pub highlighted_code: Option<String>,
+    /// `shared_assets` is set by `fpm build --optimize-assets`. When set, the generated pages
+    /// link to the content hashed `ftd_js`, `ftd_css` and `fpm_js` files instead of inlining
+    /// them.
+    pub shared_assets: Option<fpm::optimize::SharedAssets>,
+    /// `search_index` collects the text of the documents built by `fpm build` when `search` is
+    /// enabled for the package.
+    pub search_index: Option<fpm::search::SearchIndex>,
+    /// `http_cache` tells how the `http` processor uses the cached responses and fixtures. It
+    /// is set by `fpm build --refresh-http` and `fpm build --http-fixtures`.
+    pub http_cache: fpm::http_cache::HttpCacheMode,
+    /// `transclusions` keeps track of the documents transcluded by each document, see
+    /// `fpm::transclude::expand()`.
+    pub transclusions: fpm::transclude::Transclusions,
+    /// `built_documents` are the documents `fpm build` wrote a page for, `sitemap.xml` and the
+    /// feeds link only to them.
+    pub built_documents: fpm::artifacts::BuiltDocuments,
 }
 
 impl Config {
@@ -304,6 +324,12 @@ impl Config {
             package.ignored_paths = b.get::<Vec<String>>("fpm#ignore")?;
             package.fonts = b.get("fpm#font")?;
             package.sitemap = b.get("fpm#sitemap")?;
+            package.sitemap_titles = b.get("fpm#sitemap-title")?;
+            package.robots = b.get("fpm#robots")?;
+            package.feeds = b.get("fpm#feed")?;
+            package.redirects = b.get("fpm#redirect")?;
+            package.env = b.get("fpm#env")?;
+            package.translation_providers = b.get("fpm#translation-provider")?;
             package
         };
 
@@ -330,6 +356,12 @@ impl Config {
             extra_data: Default::default(),
             sitemap: None,
             current_document: None,
+            highlighted_code: None,
+            shared_assets: None,
+            search_index: None,
+            http_cache: Default::default(),
+            transclusions: Default::default(),
+            built_documents: Default::default(),
         };
 
         let asset_documents = config.get_assets("/").await?;
@@ -345,17 +377,58 @@ impl Config {
                 .map(Some)
         })?;
 
+        if let Some(ref mut sitemap) = config.sitemap {
+            if package.translation_of.is_some() {
+                sitemap.translate_titles(&package.sitemap_titles)?;
+            }
+        }
+
         Ok(config)
     }
 
-    /// `attach_data_string()` sets the value of extra data in fpm::Config,
+    /// `for_test()` returns the config of the package `root`, without reading `FPM.ftd`.
+    #[cfg(test)]
+    pub(crate) fn for_test(root: camino::Utf8PathBuf) -> Config {
+        Config {
+            package: fpm::Package::new("foo"),
+            packages_root: root.join(".packages"),
+            original_directory: root.clone(),
+            root,
+            extra_data: Default::default(),
+            sitemap: None,
+            current_document: None,
+            highlighted_code: None,
+            shared_assets: None,
+            search_index: None,
+            http_cache: Default::default(),
+            transclusions: Default::default(),
+            built_documents: Default::default(),
+        }
+    }
+
+    /// `attach_data_string()` adds to the extra data in fpm::Config,
     /// provided as `data` paramater of type `&str`
     pub fn attach_data_string(&mut self, data: &str) -> fpm::Result<()> {
         self.attach_data(serde_json::from_str(data)?)
     }
 
-    /// `attach_data()` sets the value of extra data in fpm::Config,
-    /// provided as `data` paramater of type `serde_json::Value`
+    /// `attach_data_file()` adds to the extra data in fpm::Config, read from the JSON file at
+    /// `path`
+    pub fn attach_data_file(&mut self, path: &camino::Utf8Path) -> fpm::Result<()> {
+        let data = match std::fs::read_to_string(path) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(fpm::Error::UsageError {
+                    message: format!("failed to read `{}`: {}", path, e),
+                })
+            }
+        };
+        self.attach_data_string(data.as_str())
+    }
+
+    /// `attach_data()` adds to the extra data in fpm::Config,
+    /// provided as `data` paramater of type `serde_json::Value`. Keys already present are
+    /// overwritten, so data attached later wins.
     pub fn attach_data(&mut self, data: serde_json::Value) -> fpm::Result<()> {
         let data = match data {
             serde_json::Value::Object(o) => o,
@@ -365,7 +438,7 @@ impl Config {
                 })
             }
         };
-        self.extra_data = data;
+        self.extra_data.extend(data);
         Ok(())
     }
 
@@ -614,6 +687,13 @@ pub(crate) struct PackageTemp {
     pub canonical_url: Option<String>,
     #[serde(rename = "inherit-auto-imports-from-original")]
     pub import_auto_imports_from_original: bool,
+    pub search: bool,
+    #[serde(rename = "highlight-code")]
+    pub highlight_code: bool,
+    #[serde(rename = "highlight-theme")]
+    pub highlight_theme: Option<String>,
+    #[serde(rename = "highlight-theme-dark")]
+    pub highlight_theme_dark: Option<String>,
 }
 
 impl PackageTemp {
@@ -645,7 +725,17 @@ impl PackageTemp {
             ignored_paths: vec![],
             fonts: vec![],
             import_auto_imports_from_original: self.import_auto_imports_from_original,
+            search: self.search,
+            highlight_code: self.highlight_code,
+            highlight_theme: self.highlight_theme,
+            highlight_theme_dark: self.highlight_theme_dark,
             sitemap: None,
+            robots: None,
+            feeds: vec![],
+            redirects: vec![],
+            env: vec![],
+            translation_providers: vec![],
+            sitemap_titles: vec![],
         }
     }
 }
@@ -677,10 +767,45 @@ pub struct Package {
     /// Note that this too is kind of bad design, we will move fonts to `fpm::Package` struct soon.
     pub fonts: Vec<fpm::Font>,
     pub import_auto_imports_from_original: bool,
+    /// `search` tells `fpm build` to write the full text search index queried by the `search`
+    /// component of `fpm-lib.ftd`.
+    pub search: bool,
+    /// `highlight_code` tells `fpm build` to syntax highlight code files, the result is shown in
+    /// the code page, and is available to `FPM/code.ftd` as `fpm.highlighted-code`.
+    pub highlight_code: bool,
+    /// `highlight_theme` and `highlight_theme_dark` are the names of the syntax highlighting
+    /// themes for light and dark color schemes.
+    pub highlight_theme: Option<String>,
+    pub highlight_theme_dark: Option<String>,
     /// sitemap stores the structure of the package. The structure includes sections, subsections
     /// and table of content (`toc`). This automatically converts the documents in package into the
     /// corresponding to structure.
     pub sitemap: Option<String>,
+    /// `sitemap_titles` are the translations of the titles of the sitemap of the original package,
+    /// declared using `fpm.sitemap-title` in `FPM.ftd` of a translation package.
+    pub sitemap_titles: Vec<fpm::sitemap::SitemapTitle>,
+    /// `robots` is the content of `robots.txt` generated by `fpm build`, set using `fpm.robots`
+    /// in `FPM.ftd`.
+    pub robots: Option<String>,
+    /// `feeds` keeps track of the Atom/RSS feeds to generate for sitemap sections.
+    pub feeds: Vec<fpm::artifacts::Feed>,
+    /// `redirects` keeps track of the moved documents, declared using `fpm.redirect` in
+    /// `FPM.ftd`.
+    pub redirects: Vec<fpm::redirects::Redirect>,
+    /// `env` is the list of environment variables the `env` processor is allowed to read,
+    /// declared using `fpm.env` in `FPM.ftd`.
+    pub env: Vec<EnvVariable>,
+    /// `translation_providers` are the machine translation services `fpm translate` can use,
+    /// declared using `fpm.translation-provider` in `FPM.ftd`.
+    pub translation_providers: Vec<fpm::machine_translation::HttpProvider>,
+}
+
+/// `EnvVariable` is an environment variable the `env` processor may read, with the value to use
+/// if it is not set.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct EnvVariable {
+    pub name: String,
+    pub default: Option<String>,
 }
 
 impl Package {
@@ -701,7 +826,17 @@ impl Package {
             ignored_paths: vec![],
             fonts: vec![],
             import_auto_imports_from_original: true,
+            search: false,
+            highlight_code: false,
+            highlight_theme: None,
+            highlight_theme_dark: None,
             sitemap: None,
+            robots: None,
+            feeds: vec![],
+            redirects: vec![],
+            env: vec![],
+            translation_providers: vec![],
+            sitemap_titles: vec![],
         }
     }
 
@@ -835,6 +970,67 @@ impl Package {
         }
     }
 
+    /// `language_packages()` returns this package, followed by its original and the other
+    /// translations of the original if it is a translation, or by its translations otherwise.
+    pub(crate) fn language_packages(&self) -> Vec<&fpm::Package> {
+        let mut packages = vec![self];
+        match self.translation_of.as_ref() {
+            Some(original) => {
+                packages.push(original);
+                packages.extend(original.translations.iter().filter(|v| v.name != self.name));
+            }
+            None => packages.extend(self.translations.iter()),
+        }
+        packages
+    }
+
+    /// `generate_alternate_urls()` returns the `<link rel="alternate" hreflang>` tags of the
+    /// document at `path` in each package of `language_packages()` with a `language`, and the
+    /// original package as `x-default`, so search engines can serve the page in the language of
+    /// the reader.
+    pub fn generate_alternate_urls(&self, path: &str) -> String {
+        // the pages fpm generates, like `/-/translation-status/`, are not translated
+        if path.trim_start_matches('/').starts_with("-/") {
+            return "".to_string();
+        }
+        let packages = self
+            .language_packages()
+            .into_iter()
+            .filter(|v| v.language.is_some())
+            .collect::<Vec<&fpm::Package>>();
+        if packages.len() < 2 {
+            return "".to_string();
+        }
+        let url = |package: &fpm::Package| {
+            fpm::artifacts::escape(
+                fpm::artifacts::join(fpm::artifacts::base_url(package).as_str(), path).as_str(),
+            )
+        };
+        let mut links = packages
+            .into_iter()
+            .map(|package| {
+                format!(
+                    "\n<link rel=\"alternate\" hreflang=\"{language}\" href=\"{url}\" />",
+                    language =
+                        fpm::artifacts::escape(package.language.as_deref().unwrap_or_default()),
+                    url = url(package)
+                )
+            })
+            .collect::<String>();
+        let original = match self.translation_of.as_ref() {
+            Some(original) => original,
+            None => self,
+        };
+        links.push_str(
+            format!(
+                "\n<link rel=\"alternate\" hreflang=\"x-default\" href=\"{url}\" />",
+                url = url(original)
+            )
+            .as_str(),
+        );
+        links
+    }
+
     /// aliases() returns the list of the available aliases at the package level.
     pub fn aliases(&self) -> fpm::Result<std::collections::BTreeMap<&str, &fpm::Package>> {
         let mut resp = std::collections::BTreeMap::new();
@@ -1241,3 +1437,54 @@ impl Package {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    fn package(name: &str, language: Option<&str>) -> fpm::Package {
+        let mut package = fpm::Package::new(name);
+        package.language = language.map(ToString::to_string);
+        package
+    }
+
+    #[test]
+    fn generate_alternate_urls() {
+        let mut hindi = package("hi.example.com", Some("hi"));
+        hindi.canonical_url = Some("https://example.com/hi/".to_string());
+        let mut original = package("example.com", Some("en"));
+        original.translations = vec![
+            hindi.clone(),
+            package("ar.example.com", Some("ar")),
+            // a translation without a language has no alternate link
+            package("xx.example.com", None),
+        ];
+
+        assert_eq!(
+            original.generate_alternate_urls("guide/intro/"),
+            "\n<link rel=\"alternate\" hreflang=\"en\" href=\"https://example.com/guide/intro/\" />\
+            \n<link rel=\"alternate\" hreflang=\"hi\" href=\"https://example.com/hi/guide/intro/\" />\
+            \n<link rel=\"alternate\" hreflang=\"ar\" href=\"https://ar.example.com/guide/intro/\" />\
+            \n<link rel=\"alternate\" hreflang=\"x-default\" href=\"https://example.com/guide/intro/\" />"
+        );
+
+        // the original is the `x-default` of a translation too
+        hindi.translation_of = Box::new(Some(original.clone()));
+        assert_eq!(
+            hindi.generate_alternate_urls("/"),
+            "\n<link rel=\"alternate\" hreflang=\"hi\" href=\"https://example.com/hi/\" />\
+            \n<link rel=\"alternate\" hreflang=\"en\" href=\"https://example.com/\" />\
+            \n<link rel=\"alternate\" hreflang=\"ar\" href=\"https://ar.example.com/\" />\
+            \n<link rel=\"alternate\" hreflang=\"x-default\" href=\"https://example.com/\" />"
+        );
+
+        assert_eq!(hindi.generate_alternate_urls("-/translation-status/"), "");
+        // only the pages generated by fpm are skipped
+        assert!(original
+            .generate_alternate_urls("blog/-/notes/")
+            .contains("href=\"https://example.com/blog/-/notes/\""));
+        // a package without translations has no alternate links
+        assert_eq!(
+            package("foo.example.com", Some("en")).generate_alternate_urls("guide/"),
+            ""
+        );
+    }
+}
@@ -73,6 +73,41 @@ pub async fn ensure(base_dir: &camino::Utf8PathBuf, package: &mut fpm::Package)
     Ok(())
 }
 
+/// `ensure_translations()` downloads the whole of the translation packages of an original
+/// package, `ensure()` downloads only their `FPM.ftd`, and sets the translation status summary of
+/// each translation from its `.tracks`. Only `fpm build` and `fpm translation-status` need it.
+///
+/// Downloaded packages are kept in `.packages`, so they are downloaded once. If a translation can
+/// not be downloaded, the summary from its `FPM.ftd`, if any, is kept.
+pub(crate) async fn ensure_translations(config: &mut fpm::Config) -> fpm::Result<()> {
+    use colorize::AnsiColor;
+
+    if config.package.translations.is_empty() {
+        return Ok(());
+    }
+    let snapshots = fpm::snapshot::get_latest_snapshots(&config.root).await?;
+    let mut downloaded_package = vec![config.package.name.clone()];
+    for translation in config.package.translations.iter_mut() {
+        let root = config.packages_root.join(translation.name.as_str());
+        if !root.join("FPM.ftd").exists() {
+            if let Err(e) = translation
+                .process(&config.root, &mut downloaded_package, false, true)
+                .await
+            {
+                warning!(format!(
+                    "failed to download translation `{}`, its translation status is not known: {}",
+                    translation.name, e
+                ));
+                continue;
+            }
+        }
+        translation.translation_status_summary = Some(
+            fpm::translation::get_translation_status_counts(&snapshots, &root, &config.root)?,
+        );
+    }
+    Ok(())
+}
+
 #[derive(serde::Deserialize, Debug, Clone)]
 pub(crate) struct DependencyTemp {
     pub name: String,
@@ -443,3 +478,84 @@ impl fpm::Package {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn ensure() {
+        let base_dir = camino::Utf8PathBuf::from_path_buf(
+            std::env::temp_dir().join(format!("fpm-dependency-ensure-{}", std::process::id())),
+        )
+        .unwrap();
+        std::fs::create_dir_all(base_dir.join(".packages")).unwrap();
+        // the `FPM.ftd` of the translation is already there, so nothing is downloaded, and the
+        // rest of the translation package is not needed
+        std::fs::write(
+            base_dir.join(".packages/fpm-test.invalid.ftd"),
+            "-- import: fpm\n\n-- fpm.package: fpm-test.invalid\nlanguage: hi\n\
+            canonical-url: https://example.com/hi/\n",
+        )
+        .unwrap();
+
+        let mut package = fpm::Package::new("foo");
+        package.language = Some("en".to_string());
+        package.translations = vec![fpm::Package::new("fpm-test.invalid")];
+        let result = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(super::ensure(&base_dir, &mut package));
+        let downloaded = base_dir.join(".packages/fpm-test.invalid").exists();
+        std::fs::remove_dir_all(&base_dir).unwrap();
+
+        result.unwrap();
+        assert!(!downloaded);
+        let translation = &package.translations[0];
+        assert_eq!(translation.language.as_deref(), Some("hi"));
+        assert_eq!(
+            translation.canonical_url.as_deref(),
+            Some("https://example.com/hi/")
+        );
+        assert!(translation.translation_status_summary.is_none());
+    }
+
+    #[test]
+    fn ensure_translations() {
+        let root = camino::Utf8PathBuf::from_path_buf(std::env::temp_dir().join(format!(
+            "fpm-dependency-translations-{}",
+            std::process::id()
+        )))
+        .unwrap();
+        std::fs::create_dir_all(root.join(".history")).unwrap();
+        std::fs::write(
+            root.join(".history/.latest.ftd"),
+            "-- import: fpm\n\n-- fpm.snapshot: index.ftd\ntimestamp: 1\n\n\
+            -- fpm.snapshot: about.ftd\ntimestamp: 2\n",
+        )
+        .unwrap();
+        // the translation package is already downloaded, so nothing is fetched
+        let translation = root.join(".packages/fpm-test.invalid");
+        std::fs::create_dir_all(&translation).unwrap();
+        std::fs::write(
+            translation.join("FPM.ftd"),
+            "-- import: fpm\n\n-- fpm.package: fpm-test.invalid\nlanguage: hi\n",
+        )
+        .unwrap();
+        std::fs::write(translation.join("index.ftd"), "-- ftd.text: नमस्ते\n").unwrap();
+
+        let mut config = fpm::Config::for_test(root.clone());
+        config.package.language = Some("en".to_string());
+        config.package.translations = vec![fpm::Package::new("fpm-test.invalid")];
+        let result = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(super::ensure_translations(&mut config));
+        std::fs::remove_dir_all(&root).unwrap();
+
+        result.unwrap();
+        let summary = config.package.translations[0]
+            .translation_status_summary
+            .as_ref()
+            .unwrap();
+        assert_eq!(summary.missing, 1);
+        assert_eq!(summary.never_marked, 1);
+        assert_eq!(summary.out_dated + summary.upto_date, 0);
+    }
+}
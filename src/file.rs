@@ -113,6 +113,7 @@ pub fn package_ignores(
     overrides.add("!FPM")?;
     overrides.add("!rust-toolchain")?;
     overrides.add("!.build")?;
+    overrides.add("!.cache")?;
     for ignored_path in &package.ignored_paths {
         overrides.add(format!("!{}", ignored_path).as_str())?;
     }
@@ -0,0 +1,159 @@
+/// `Glossary` is the terminology of a translation package, read from `FPM/glossary.ftd`:
+///
+/// ```ftd
+/// -- import: fpm
+///
+/// -- fpm.glossary-term: package
+/// translation: पैकेज
+///
+/// -- fpm.do-not-translate: FTD
+/// ```
+///
+/// A term of the original must be translated to its `translation`, a `do-not-translate` term must
+/// be kept as is.
+#[derive(Debug, Default)]
+pub(crate) struct Glossary {
+    pub terms: Vec<Term>,
+    pub do_not_translate: Vec<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub(crate) struct Term {
+    pub term: String,
+    pub translation: String,
+    pub note: Option<String>,
+}
+
+impl Glossary {
+    /// `read()` returns the glossary of the package, it is empty if the package has no
+    /// `FPM/glossary.ftd`.
+    pub(crate) fn read(config: &fpm::Config) -> fpm::Result<Glossary> {
+        let path = config.root.join("FPM").join("glossary.ftd");
+        if !path.exists() {
+            return Ok(Default::default());
+        }
+        let lib = fpm::FPMLibrary::default();
+        let doc = std::fs::read_to_string(&path)?;
+        let b = fpm::doc::parse_ftd("glossary.ftd", doc.as_str(), &lib)?;
+        Ok(Glossary {
+            terms: b.get("fpm#glossary-term")?,
+            do_not_translate: b.get("fpm#do-not-translate")?,
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.do_not_translate.is_empty()
+    }
+
+    /// `hints()` returns the glossary entries for the terms used in `source`, for translators.
+    pub(crate) fn hints(&self, source: &str) -> Vec<String> {
+        let mut hints = vec![];
+        for term in self.terms.iter() {
+            if contains_term(source, term.term.as_str()) {
+                hints.push(match term.note {
+                    Some(ref note) => format!("{} → {} ({})", term.term, term.translation, note),
+                    None => format!("{} → {}", term.term, term.translation),
+                });
+            }
+        }
+        for term in self.do_not_translate.iter() {
+            if contains_term(source, term.as_str()) {
+                hints.push(format!("{}: do not translate", term));
+            }
+        }
+        hints
+    }
+
+    /// `check()` returns the violations of the glossary by `target`, the translation of `source`,
+    /// each with the line of `source`, counted from 0, that uses the term.
+    pub(crate) fn check(&self, source: &str, target: &str) -> Vec<(usize, String)> {
+        let mut violations = vec![];
+        for term in self.terms.iter() {
+            if let Some(line) = term_line(source, term.term.as_str(), true) {
+                if !contains_term(target, term.translation.as_str()) {
+                    violations.push((
+                        line,
+                        format!(
+                            "`{}` should be translated as `{}`",
+                            term.term, term.translation
+                        ),
+                    ));
+                }
+            }
+        }
+        for term in self.do_not_translate.iter() {
+            if let Some(line) = term_line(source, term.as_str(), true) {
+                // a term that is not translated is kept as is, so its case must match
+                if term_line(target, term.as_str(), false).is_none() {
+                    violations.push((line, format!("`{}` should not be translated", term)));
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// `contains_term()` tells if `text` contains the word or phrase `term`, ignoring case.
+fn contains_term(text: &str, term: &str) -> bool {
+    term_line(text, term, true).is_some()
+}
+
+/// `term_line()` returns the line of `text`, counted from 0, where the word or phrase `term` is
+/// first used.
+fn term_line(text: &str, term: &str, ignore_case: bool) -> Option<usize> {
+    let pattern = format!(r"\b{}\b", regex::escape(term.trim()));
+    let start = match regex::RegexBuilder::new(pattern.as_str())
+        .case_insensitive(ignore_case)
+        .build()
+    {
+        Ok(re) => re.find(text)?.start(),
+        Err(_) if ignore_case => text.to_lowercase().find(term.to_lowercase().as_str())?,
+        Err(_) => text.find(term)?,
+    };
+    Some(
+        text.as_bytes()[..start.min(text.len())]
+            .iter()
+            .filter(|b| **b == b'\n')
+            .count(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn check() {
+        let glossary = super::Glossary {
+            terms: vec![super::Term {
+                term: "package".to_string(),
+                translation: "पैकेज".to_string(),
+                note: None,
+            }],
+            do_not_translate: vec!["FTD".to_string()],
+        };
+        let source = "Every FTD Package has an FPM.ftd file";
+        assert_eq!(
+            glossary.hints(source),
+            vec!["package → पैकेज", "FTD: do not translate"]
+        );
+        assert!(glossary
+            .check(source, "हर FTD पैकेज में एक FPM.ftd फ़ाइल होती है")
+            .is_empty());
+        assert_eq!(
+            glossary.check(source, "हर एफटीडी संकुल में एक FPM.ftd फ़ाइल होती है"),
+            vec![
+                (0, "`package` should be translated as `पैकेज`".to_string()),
+                (0, "`FTD` should not be translated".to_string())
+            ]
+        );
+        // the translation must be the whole term, and the line of the term is reported
+        assert_eq!(
+            glossary.check(
+                "Every FTD file\nis part of a Package",
+                "हर FTD फ़ाइल\nपैकेजों का हिस्सा है"
+            ),
+            vec![(1, "`package` should be translated as `पैकेज`".to_string())]
+        );
+        // `packages` is not the term `package`
+        assert!(glossary.hints("Some packages").is_empty());
+    }
+}
@@ -0,0 +1,229 @@
+/// `Highlight` tells how `to_html()` renders code.
+#[derive(Debug, Clone)]
+pub(crate) struct Highlight {
+    /// `line_numbers` shows the number of each line, starting from `start_line`.
+    pub line_numbers: bool,
+    pub start_line: usize,
+    /// `highlighted_lines` are the inclusive ranges of lines to emphasise, `2-4,7`, numbered from
+    /// `start_line`.
+    pub highlighted_lines: Vec<(usize, usize)>,
+}
+
+impl Default for Highlight {
+    fn default() -> Self {
+        Highlight {
+            line_numbers: false,
+            start_line: 1,
+            highlighted_lines: vec![],
+        }
+    }
+}
+
+pub(crate) const DEFAULT_LIGHT_THEME: &str = "InspiredGitHub";
+pub(crate) const DEFAULT_DARK_THEME: &str = "base16-ocean.dark";
+
+lazy_static! {
+    static ref SYNTAXES: syntect::parsing::SyntaxSet =
+        syntect::parsing::SyntaxSet::load_defaults_newlines();
+    static ref THEMES: syntect::highlighting::ThemeSet =
+        syntect::highlighting::ThemeSet::load_defaults();
+}
+
+impl Highlight {
+    /// `parse_lines()` parses the `highlight-lines` header, a comma separated list of line
+    /// numbers and ranges: `2-4,7`.
+    pub(crate) fn parse_lines(s: &str) -> Result<Vec<(usize, usize)>, String> {
+        s.split(',')
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                let (start, end) = v.split_once('-').unwrap_or((v, v));
+                match (start.trim().parse::<usize>(), end.trim().parse::<usize>()) {
+                    (Ok(start), Ok(end)) if start <= end => Ok((start, end)),
+                    _ => Err(format!("invalid line range `{}`, expected `2` or `2-4`", v)),
+                }
+            })
+            .collect()
+    }
+
+    fn is_highlighted(&self, line: usize) -> bool {
+        self.highlighted_lines
+            .iter()
+            .any(|(start, end)| *start <= line && line <= *end)
+    }
+}
+
+/// `to_html()` highlights `code`, written in the language of file extension `lang`, once with the
+/// light and once with the dark theme of the package. The dark version is shown when the browser
+/// prefers dark colors.
+pub(crate) fn to_html(
+    config: &fpm::Config,
+    code: &str,
+    lang: &str,
+    highlight: &Highlight,
+) -> fpm::Result<String> {
+    let light = theme(
+        config.package.highlight_theme.as_deref(),
+        DEFAULT_LIGHT_THEME,
+    )?;
+    let dark = theme(
+        config.package.highlight_theme_dark.as_deref(),
+        DEFAULT_DARK_THEME,
+    )?;
+    Ok(format!(
+        "<div class=\"fpm-code\"><style>{style}</style>{light}{dark}</div>",
+        style = STYLE,
+        light = render(code, lang, light, "fpm-code-light", highlight)?,
+        dark = render(code, lang, dark, "fpm-code-dark", highlight)?,
+    ))
+}
+
+const STYLE: &str = ".fpm-code pre{margin:0;padding:1em;overflow-x:auto}\
+    .fpm-code .fpm-code-dark{display:none}\
+    .fpm-code .fpm-line{display:block;min-height:1.2em}\
+    .fpm-code .fpm-line-highlighted{background:rgba(255,221,0,0.2)}\
+    .fpm-code .fpm-line-number{display:inline-block;min-width:2.5em;padding-right:1em;\
+    text-align:right;opacity:0.5;user-select:none}\
+    @media (prefers-color-scheme: dark){.fpm-code .fpm-code-light{display:none}\
+    .fpm-code .fpm-code-dark{display:block}}";
+
+fn theme(name: Option<&str>, default: &str) -> fpm::Result<&'static syntect::highlighting::Theme> {
+    let name = name.unwrap_or(default);
+    match THEMES.themes.get(name) {
+        Some(theme) => Ok(theme),
+        None => Err(fpm::Error::UsageError {
+            message: format!(
+                "unknown highlight theme `{}`, available themes: {}",
+                name,
+                THEMES
+                    .themes
+                    .keys()
+                    .map(|v| v.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ),
+        }),
+    }
+}
+
+fn render(
+    code: &str,
+    lang: &str,
+    theme: &syntect::highlighting::Theme,
+    class: &str,
+    highlight: &Highlight,
+) -> fpm::Result<String> {
+    let syntax = SYNTAXES
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text());
+    let background = theme
+        .settings
+        .background
+        .map(|c| format!("background-color:#{:02x}{:02x}{:02x};", c.r, c.g, c.b))
+        .unwrap_or_default();
+
+    let mut highlighter = syntect::easy::HighlightLines::new(syntax, theme);
+    let mut html = format!("<pre class=\"{}\" style=\"{}\"><code>", class, background);
+    for (i, line) in syntect::util::LinesWithEndings::from(code).enumerate() {
+        let number = highlight.start_line + i;
+        let regions =
+            highlighter
+                .highlight_line(line, &SYNTAXES)
+                .map_err(|e| fpm::Error::UsageError {
+                    message: format!("failed to highlight `{}` code: {}", lang, e),
+                })?;
+        let line_html = syntect::html::styled_line_to_highlighted_html(
+            regions.as_slice(),
+            syntect::html::IncludeBackground::No,
+        )
+        .map_err(|e| fpm::Error::UsageError {
+            message: format!("failed to highlight `{}` code: {}", lang, e),
+        })?;
+        html.push_str(
+            format!(
+                "<span class=\"fpm-line{}\">{}{}</span>",
+                if highlight.is_highlighted(number) {
+                    " fpm-line-highlighted"
+                } else {
+                    ""
+                },
+                if highlight.line_numbers {
+                    format!("<span class=\"fpm-line-number\">{}</span>", number)
+                } else {
+                    "".to_string()
+                },
+                line_html.trim_end_matches(|c| c == '\n' || c == '\r'),
+            )
+            .as_str(),
+        );
+    }
+    html.push_str("</code></pre>");
+    Ok(html)
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn parse_lines() {
+        assert_eq!(
+            super::Highlight::parse_lines("2-4, 7"),
+            Ok(vec![(2, 4), (7, 7)])
+        );
+        assert!(super::Highlight::parse_lines("4-2").is_err());
+    }
+
+    fn config() -> fpm::Config {
+        fpm::Config::for_test(camino::Utf8PathBuf::from("."))
+    }
+
+    #[test]
+    fn to_html() {
+        let html = super::to_html(
+            &config(),
+            "fn main() {\n    let x = 1;\n}\n",
+            "rs",
+            &super::Highlight {
+                line_numbers: true,
+                start_line: 10,
+                highlighted_lines: vec![(11, 11)],
+            },
+        )
+        .unwrap();
+        assert_eq!(html.matches("<pre class=\"fpm-code-light\"").count(), 1);
+        assert_eq!(html.matches("<pre class=\"fpm-code-dark\"").count(), 1);
+        // each theme renders the three lines
+        assert_eq!(html.matches("<span class=\"fpm-line-number\">").count(), 6);
+        assert_eq!(
+            html.matches(
+                "<span class=\"fpm-line fpm-line-highlighted\">\
+                 <span class=\"fpm-line-number\">11</span>"
+            )
+            .count(),
+            2
+        );
+        assert!(html.contains("<span class=\"fpm-line\"><span class=\"fpm-line-number\">10</span>"));
+        // the tokens are styled by the theme
+        assert!(html.contains("<span style=\"color:"));
+    }
+
+    #[test]
+    fn to_html_plain_text() {
+        let html = super::to_html(&config(), "a < b\n", "unknown", &Default::default()).unwrap();
+        assert!(html.contains("a &lt; b"));
+        assert!(!html.contains("<span class=\"fpm-line-number\">"));
+        assert!(!html.contains("<span class=\"fpm-line fpm-line-highlighted\">"));
+    }
+
+    #[test]
+    fn theme() {
+        let mut config = config();
+        config.package.highlight_theme = Some("Solarized (light)".to_string());
+        let html = super::to_html(&config, "x\n", "rs", &Default::default()).unwrap();
+        assert_ne!(
+            html,
+            super::to_html(&self::config(), "x\n", "rs", &Default::default()).unwrap()
+        );
+        config.package.highlight_theme = Some("missing".to_string());
+        assert!(super::to_html(&config, "x\n", "rs", &Default::default()).is_err());
+    }
+}
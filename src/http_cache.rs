@@ -0,0 +1,301 @@
+/// `HttpCacheMode` tells how `$processor$: http` uses the stored responses. It is set by
+/// `fpm build` flags.
+///
+/// Responses are cached in `.cache/http/` for `cache-ttl` seconds (one hour by default), keyed by
+/// the method, url, headers and body of the request. Secret headers, like the bearer token, are
+/// not part of the key, so a fixture recorded with one token is replayed with another, or without
+/// any. Fixtures are stored in `FPM/http/`, they are meant to be committed with the package so
+/// builds in CI do not need network access.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HttpCacheMode {
+    /// Use the cached response if it is not older than `cache-ttl`.
+    Default,
+    /// `--refresh-http`: always fetch, and update the cache.
+    Refresh,
+    /// `--http-fixtures record`: always fetch, and store the response as a fixture.
+    Record,
+    /// `--http-fixtures replay`: never fetch, fail if the fixture is missing.
+    Replay,
+}
+
+impl Default for HttpCacheMode {
+    fn default() -> Self {
+        HttpCacheMode::Default
+    }
+}
+
+impl HttpCacheMode {
+    pub fn from_flags(refresh: bool, fixtures: Option<&str>) -> fpm::Result<HttpCacheMode> {
+        Ok(match (refresh, fixtures) {
+            (_, Some("record")) => HttpCacheMode::Record,
+            (true, Some("replay")) => {
+                return Err(fpm::Error::UsageError {
+                    message: "--refresh-http can not be used with `--http-fixtures replay`"
+                        .to_string(),
+                })
+            }
+            (_, Some("replay")) => HttpCacheMode::Replay,
+            (_, Some(v)) => {
+                return Err(fpm::Error::UsageError {
+                    message: format!("--http-fixtures can be `record` or `replay`, found: {}", v),
+                })
+            }
+            (true, None) => HttpCacheMode::Refresh,
+            (false, None) => HttpCacheMode::Default,
+        })
+    }
+}
+
+pub(crate) const DEFAULT_TTL: u64 = 3600;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+struct Entry {
+    method: String,
+    url: String,
+    /// sha256 of the request headers, see `Key::headers_hash()`
+    headers: String,
+    body: Option<String>,
+    /// seconds since unix epoch
+    fetched_on: u64,
+    response: String,
+}
+
+/// `Key` identifies a request in the cache and in fixtures.
+#[derive(Debug)]
+pub(crate) struct Key {
+    pub method: String,
+    pub url: String,
+    /// lowercase header names and their values
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+impl Key {
+    /// `headers_hash()` returns the sha256 of the headers sorted by name, so the order in which
+    /// they are written in the document does not matter. Secret headers are left out, see
+    /// `is_secret()`.
+    fn headers_hash(&self) -> String {
+        use sha2::Digest;
+
+        let mut headers = self
+            .headers
+            .iter()
+            .filter(|(k, _)| !is_secret(k))
+            .collect::<Vec<_>>();
+        headers.sort();
+        let mut hasher = sha2::Sha256::new();
+        for (k, v) in headers {
+            hasher.update(format!("{}: {}\n", k, v).as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    fn file_name(&self) -> String {
+        use sha2::Digest;
+
+        let hash = sha2::Sha256::digest(
+            format!(
+                "{}\n{}\n{}\n{}",
+                self.method,
+                self.url,
+                self.headers_hash(),
+                self.body.as_deref().unwrap_or_default()
+            )
+            .as_bytes(),
+        );
+        format!("{:x}.json", hash)
+    }
+}
+
+/// `is_secret()` tells if the header `name` carries credentials.
+fn is_secret(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name == "authorization"
+        || name == "proxy-authorization"
+        || name == "cookie"
+        || name.contains("token")
+        || name.contains("secret")
+        || name.contains("api-key")
+        || name.contains("apikey")
+}
+
+fn cache_dir(config: &fpm::Config) -> camino::Utf8PathBuf {
+    config.root.join(".cache").join("http")
+}
+
+fn fixtures_dir(config: &fpm::Config) -> camino::Utf8PathBuf {
+    config.root.join("FPM").join("http")
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .map(|v| v.as_secs())
+        .unwrap_or(0)
+}
+
+fn read(path: &camino::Utf8Path) -> Option<Entry> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|v| serde_json::from_str(v.as_str()).ok())
+}
+
+/// `get()` returns the stored response for the request, if it should not be fetched. In
+/// `Replay` mode it is an error if there is no fixture for the request.
+pub(crate) fn get(config: &fpm::Config, key: &Key, ttl: u64) -> fpm::Result<Option<String>> {
+    match config.http_cache {
+        HttpCacheMode::Refresh | HttpCacheMode::Record => Ok(None),
+        HttpCacheMode::Replay => match read(&fixtures_dir(config).join(key.file_name())) {
+            Some(entry) => Ok(Some(entry.response)),
+            None => Err(fpm::Error::UsageError {
+                message: format!(
+                    "no recorded response for {} {}, run `fpm build --http-fixtures record`",
+                    key.method, key.url
+                ),
+            }),
+        },
+        HttpCacheMode::Default => Ok(read(&cache_dir(config).join(key.file_name()))
+            .filter(|entry| now().saturating_sub(entry.fetched_on) < ttl)
+            .map(|entry| entry.response)),
+    }
+}
+
+/// `put()` stores a fetched response in the cache, and as a fixture in `Record` mode.
+pub(crate) fn put(config: &fpm::Config, key: &Key, ttl: u64, response: &str) -> fpm::Result<()> {
+    let entry = serde_json::to_string_pretty(&Entry {
+        method: key.method.to_string(),
+        url: key.url.to_string(),
+        headers: key.headers_hash(),
+        body: key.body.clone(),
+        fetched_on: now(),
+        response: response.to_string(),
+    })?;
+
+    let mut dirs = vec![];
+    if ttl > 0 {
+        dirs.push(cache_dir(config));
+    }
+    if config.http_cache == HttpCacheMode::Record {
+        dirs.push(fixtures_dir(config));
+    }
+    for dir in dirs {
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join(key.file_name()), entry.as_str())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    fn new_key(token: &str) -> super::Key {
+        super::Key {
+            method: "GET".to_string(),
+            url: "https://api.example.com/repos".to_string(),
+            headers: vec![
+                ("authorization".to_string(), format!("Bearer {}", token)),
+                ("accept".to_string(), "application/json".to_string()),
+            ],
+            body: None,
+        }
+    }
+
+    fn config(name: &str) -> fpm::Config {
+        let root =
+            std::env::temp_dir().join(format!("fpm-http-cache-{}-{}", name, std::process::id()));
+        fpm::Config::for_test(camino::Utf8PathBuf::from_path_buf(root).unwrap())
+    }
+
+    #[test]
+    fn from_flags() {
+        use super::HttpCacheMode;
+
+        assert_eq!(
+            HttpCacheMode::from_flags(false, None).unwrap(),
+            HttpCacheMode::Default
+        );
+        assert_eq!(
+            HttpCacheMode::from_flags(true, None).unwrap(),
+            HttpCacheMode::Refresh
+        );
+        assert_eq!(
+            HttpCacheMode::from_flags(true, Some("record")).unwrap(),
+            HttpCacheMode::Record
+        );
+        assert_eq!(
+            HttpCacheMode::from_flags(false, Some("replay")).unwrap(),
+            HttpCacheMode::Replay
+        );
+        assert!(HttpCacheMode::from_flags(true, Some("replay")).is_err());
+        assert!(HttpCacheMode::from_flags(false, Some("rewind")).is_err());
+    }
+
+    #[test]
+    fn key() {
+        let mut reordered = new_key("secret");
+        reordered.headers.reverse();
+        assert_eq!(new_key("secret").file_name(), reordered.file_name());
+        // the token is not part of the key
+        assert_eq!(new_key("secret").file_name(), new_key("other").file_name());
+        let mut without_token = new_key("secret");
+        without_token.headers.retain(|(k, _)| k != "authorization");
+        assert_eq!(new_key("secret").file_name(), without_token.file_name());
+        // other headers are
+        let mut other_accept = new_key("secret");
+        other_accept.headers[1].1 = "text/csv".to_string();
+        assert_ne!(new_key("secret").file_name(), other_accept.file_name());
+        assert!(super::is_secret("X-Api-Key"));
+        assert!(super::is_secret("x-auth-token"));
+        assert!(!super::is_secret("accept"));
+    }
+
+    #[test]
+    fn get_and_put() {
+        use super::HttpCacheMode;
+
+        let mut config = config("get-and-put");
+        let key = new_key("secret");
+
+        assert_eq!(super::get(&config, &key, 60).unwrap(), None);
+        super::put(&config, &key, 60, "[1]").unwrap();
+        assert_eq!(
+            super::get(&config, &key, 60).unwrap(),
+            Some("[1]".to_string())
+        );
+        // expired
+        assert_eq!(super::get(&config, &key, 0).unwrap(), None);
+        // secrets are not stored, only the hash of the other headers
+        let stored =
+            std::fs::read_to_string(super::cache_dir(&config).join(key.file_name())).unwrap();
+        assert!(!stored.contains("secret"));
+        assert!(stored.contains(key.headers_hash().as_str()));
+
+        config.http_cache = HttpCacheMode::Refresh;
+        assert_eq!(super::get(&config, &key, 60).unwrap(), None);
+
+        // `cache-ttl: 0` is not cached
+        config.http_cache = HttpCacheMode::Default;
+        let mut uncached = new_key("uncached");
+        uncached.url = "https://api.example.com/uncached".to_string();
+        super::put(&config, &uncached, 0, "[2]").unwrap();
+        assert!(!super::cache_dir(&config)
+            .join(uncached.file_name())
+            .exists());
+
+        config.http_cache = HttpCacheMode::Replay;
+        assert!(super::get(&config, &key, 60).is_err());
+        config.http_cache = HttpCacheMode::Record;
+        assert_eq!(super::get(&config, &key, 60).unwrap(), None);
+        super::put(&config, &key, 60, "[3]").unwrap();
+        config.http_cache = HttpCacheMode::Replay;
+        assert_eq!(
+            super::get(&config, &key, 0).unwrap(),
+            Some("[3]".to_string())
+        );
+        assert_eq!(
+            super::get(&config, &new_key("other"), 0).unwrap(),
+            Some("[3]".to_string())
+        );
+
+        std::fs::remove_dir_all(&config.root).unwrap();
+    }
+}
@@ -4,8 +4,13 @@ type Bundle = fluent::bundle::FluentBundle<
     fluent::FluentResource,
     intl_memoizer::concurrent::IntlLangMemoizer,
 >;
-type Map = std::collections::HashMap<realm_lang::Language, Option<Bundle>>;
-pub type Base = std::sync::Arc<antidote::Mutex<(Bundle, Map)>>;
+/// `Map` caches the bundle of each package root and language, `None` if there are no resources
+/// for the language
+type Map = std::collections::HashMap<String, Option<Bundle>>;
+pub type Base = std::sync::Arc<antidote::Mutex<Map>>;
+
+/// names of the resources loaded in every bundle, along with the one looked up
+const COMMON_RESOURCES: &[&str] = &["terms"];
 
 #[derive(serde::Serialize)]
 #[allow(clippy::upper_case_acronyms)]
@@ -25,27 +30,53 @@ struct Float {
     localised: String,
 }
 
-fn new_bundle(lang: &realm_lang::Language, res: String) -> Bundle {
-    let i = issue(lang, res.as_str(), None);
+/// `new_bundle()` returns the bundle of `resources`, a later resource overrides the messages of
+/// the earlier ones.
+fn new_bundle(lang: &str, resources: Vec<(camino::Utf8PathBuf, String)>) -> Bundle {
+    use colorize::AnsiColor;
 
-    let mut b = fluent::bundle::FluentBundle::new_concurrent(vec![lang
-        .to_2_letter_code()
-        .parse()
-        .unwrap_or_else(|_| panic!("{}", i))]);
-    b.add_resource(fluent::FluentResource::try_new(res).unwrap_or_else(|_| panic!("{}", i)))
-        .unwrap_or_else(|_| panic!("{}", i));
+    let langid = match lang.parse() {
+        Ok(v) => v,
+        Err(_) => {
+            warning!(format!("invalid language `{}`, using `en`", lang));
+            "en".parse()
+                .unwrap_or_else(|_| panic!("{}", issue(lang, "", None)))
+        }
+    };
+    let mut b = fluent::bundle::FluentBundle::new_concurrent(vec![langid]);
+    for (path, res) in resources {
+        let res = match fluent::FluentResource::try_new(res) {
+            Ok(v) => v,
+            Err((v, errors)) => {
+                warning!(format!("errors in {}: {:?}", path, errors));
+                v
+            }
+        };
+        b.add_resource_overriding(res);
+    }
     b
 }
 
-pub fn new_base(id: &'static str) -> Base {
-    let default = realm_lang::Language::English;
-    std::sync::Arc::new(antidote::Mutex::new((
-        new_bundle(
-            &default,
-            read_file(&default, id).unwrap_or_else(|| panic!("cant read english resource: {}", id)),
-        ),
-        std::collections::HashMap::new(),
-    )))
+pub fn new_base() -> Base {
+    std::sync::Arc::new(antidote::Mutex::new(std::collections::HashMap::new()))
+}
+
+/// `fallback_chain()` returns the languages whose resources are looked up, in order, for `lang`:
+/// `pt-BR` falls back to `pt`, and every language falls back to English.
+pub(crate) fn fallback_chain(lang: &str) -> Vec<String> {
+    let mut chain = vec![];
+    let mut lang = lang.trim().replace('_', "-");
+    while !lang.is_empty() {
+        chain.push(lang.to_string());
+        lang = match lang.rsplit_once('-') {
+            Some((v, _)) => v.to_string(),
+            None => "".to_string(),
+        };
+    }
+    if !chain.iter().any(|v| v == "en") {
+        chain.push("en".to_string());
+    }
+    chain
 }
 
 // fn bundle<'a, 'b>(
@@ -70,8 +101,8 @@ pub fn new_base(id: &'static str) -> Base {
 //     (lock, b)
 // }
 
-fn issue(lang: &realm_lang::Language, res: &str, id: Option<&str>) -> String {
-    format!("issue with {}/{}/{:?}", lang.to_2_letter_code(), res, id)
+fn issue(lang: &str, res: &str, id: Option<&str>) -> String {
+    format!("issue with {}/{}/{:?}", lang, res, id)
 }
 
 /*pub fn html(base: &Base, lang: &realm_lang::Language, res: &'static str, id: &'static str) -> HTML {
@@ -104,70 +135,285 @@ pub fn attribute(
 
 // message_with_args
 
+/// `lookup()` returns the message `id` of the resource `res`, in `lang` or else in the first
+/// language of its fallback chain which has it.
 pub fn lookup(
     base: &Base,
-    lang: &realm_lang::Language,
+    config: &fpm::Config,
+    lang: &str,
     res: &'static str,
     id: &'static str,
     attribute: Option<&'static str>,
     args: Option<&fluent::FluentArgs>,
 ) -> String {
-    use std::ops::DerefMut;
-
     let i = issue(lang, res, Some(id));
 
     let mut lock = base.lock();
-    let (en, ref mut m) = lock.deref_mut();
-    if m.get(lang).is_none() {
-        match read_file(lang, res) {
-            Some(v) => {
-                m.insert(*lang, Some(new_bundle(lang, v)));
-            }
-            None => {
-                m.insert(*lang, None);
+    for lang in fallback_chain(lang) {
+        let key = format!("{}/{}/{}", config.root, lang, res);
+        if !lock.contains_key(&key) {
+            let resources = resources(config, lang.as_str(), res);
+            let bundle = if resources.is_empty() {
+                None
+            } else {
+                Some(new_bundle(lang.as_str(), resources))
+            };
+            lock.insert(key.clone(), bundle);
+        }
+        let b = match lock.get(&key) {
+            Some(Some(v)) => v,
+            _ => continue,
+        };
+        let pattern = match (b.get_message(id), attribute) {
+            (Some(msg), Some(key)) => msg.get_attribute(key).map(|v| v.value()),
+            (Some(msg), None) => msg.value(),
+            (None, _) => None,
+        };
+        let pattern = match pattern {
+            Some(v) => v,
+            None => continue,
+        };
+
+        let mut errors = vec![];
+        let s = b.format_pattern(pattern, args, &mut errors);
+        if !errors.is_empty() {
+            panic!("errors found in {}: {:?}", i, errors)
+        }
+        return s.into();
+    }
+    panic!("{}", i)
+}
+
+/// `resources()` returns the content of the Fluent resources of `lang`: the one bundled with fpm
+/// first, then the ones shipped by the dependencies of the package, like its theme, and then the
+/// package's own, in `FPM/i18n/<lang>/<res>.ftl`.
+fn resources(config: &fpm::Config, lang: &str, res: &str) -> Vec<(camino::Utf8PathBuf, String)> {
+    COMMON_RESOURCES
+        .iter()
+        .filter(|v| **v != res)
+        .chain(std::iter::once(&res))
+        .flat_map(|name| resource_files(config, lang, name))
+        .collect()
+}
+
+/// `resource_files()` returns the content of the resource `res` of `lang`, without the common
+/// resources, in the order they override each other.
+fn resource_files(
+    config: &fpm::Config,
+    lang: &str,
+    res: &str,
+) -> Vec<(camino::Utf8PathBuf, String)> {
+    let mut resources = vec![];
+    if let Some(v) = read_file(lang, res) {
+        resources.push((
+            camino::Utf8PathBuf::from(format!("i18n/{}/{}.ftl", lang, res)),
+            v.to_string(),
+        ));
+    }
+    for root in package_roots(config) {
+        let path = root
+            .join("FPM")
+            .join("i18n")
+            .join(lang)
+            .join(format!("{}.ftl", res));
+        if let Ok(v) = std::fs::read_to_string(&path) {
+            resources.push((path, v));
+        }
+    }
+    resources
+}
+
+/// `package_roots()` returns the roots of the dependencies of the package, and then of the
+/// package itself.
+fn package_roots(config: &fpm::Config) -> Vec<camino::Utf8PathBuf> {
+    let mut roots = config
+        .package
+        .dependencies
+        .iter()
+        .map(|v| config.packages_root.join(v.package.name.as_str()))
+        .collect::<Vec<camino::Utf8PathBuf>>();
+    roots.push(config.root.clone());
+    roots
+}
+
+/// `languages()` returns the languages the package, or its dependencies, ship resources for.
+pub(crate) fn languages(config: &fpm::Config) -> std::collections::BTreeSet<String> {
+    let mut languages = std::collections::BTreeSet::new();
+    for root in package_roots(config) {
+        let dir = match std::fs::read_dir(root.join("FPM").join("i18n")) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        for entry in dir.flatten() {
+            if entry.path().is_dir() {
+                languages.insert(entry.file_name().to_string_lossy().to_string());
             }
         }
-    };
+    }
+    languages
+}
+
+/// `missing_keys()` returns the messages of the English resource `res` which are not in `lang`,
+/// with the language of its fallback chain they fall back to, like `pt` for `pt-BR`.
+pub(crate) fn missing_keys(config: &fpm::Config, lang: &str, res: &str) -> Vec<(String, String)> {
+    let mut missing = vec![];
+    let chain = fallback_chain(lang);
+    if chain.len() < 2 {
+        return missing;
+    }
+    let keys = chain
+        .iter()
+        .map(|lang| {
+            resource_files(config, lang, res)
+                .iter()
+                .flat_map(|(_, v)| message_ids(v.as_str()))
+                .collect::<std::collections::BTreeSet<String>>()
+        })
+        .collect::<Vec<std::collections::BTreeSet<String>>>();
+    for key in keys[keys.len() - 1].iter() {
+        if keys[0].contains(key) {
+            continue;
+        }
+        // an intermediate language like `pt` for `pt-BR` may have it
+        let fallback = chain
+            .iter()
+            .zip(keys.iter())
+            .find(|(_, v)| v.contains(key))
+            .map(|(lang, _)| lang.to_string())
+            .unwrap_or_else(|| "en".to_string());
+        missing.push((key.to_string(), fallback));
+    }
+    missing
+}
 
-    let b: &Bundle = match m.get(lang) {
-        Some(Some(v)) => v,
-        Some(None) => en,
-        None => unreachable!(),
+/// `message_ids()` returns the ids of the messages and terms, like `-brand`, defined in the Fluent
+/// resource.
+fn message_ids(res: &str) -> Vec<String> {
+    let res = match fluent_syntax::parser::parse(res) {
+        Ok(v) => v,
+        // the entries which could be parsed
+        Err((v, _)) => v,
     };
+    res.body
+        .iter()
+        .filter_map(|entry| match entry {
+            fluent_syntax::ast::Entry::Message(v) => Some(v.id.name.to_string()),
+            fluent_syntax::ast::Entry::Term(v) => Some(format!("-{}", v.id.name)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `read_file()` returns the resource `res` of `lang` bundled with fpm, if any.
+fn read_file(lang: &str, res: &str) -> Option<&'static str> {
+    match (lang, res) {
+        ("hi", "translation") => Some(include_str!("../../i18n/hi/translation.ftl")),
+        ("hi", "terms") => Some(include_str!("../../i18n/hi/terms.ftl")),
+        ("en", "translation") => Some(include_str!("../../i18n/en/translation.ftl")),
+        ("en", "terms") => Some(include_str!("../../i18n/en/terms.ftl")),
+        _ => None,
+    }
+}
 
-    let msg = b
-        .get_message(id)
-        .or_else(|| en.get_message(id))
-        .unwrap_or_else(|| panic!("{}", i));
+#[cfg(test)]
+mod test {
+    #[test]
+    fn fallback_chain() {
+        assert_eq!(super::fallback_chain("pt-BR"), vec!["pt-BR", "pt", "en"]);
+        assert_eq!(super::fallback_chain("hi"), vec!["hi", "en"]);
+        assert_eq!(super::fallback_chain("en-GB"), vec!["en-GB", "en"]);
+    }
 
-    let mut errors = vec![];
+    fn config(name: &str) -> fpm::Config {
+        let root = camino::Utf8PathBuf::from_path_buf(std::env::temp_dir().join(format!(
+            "fpm-i18n-{}-{}",
+            name,
+            std::process::id()
+        )))
+        .unwrap();
+        let mut config = fpm::Config::for_test(root);
+        config.package.dependencies.push(fpm::Dependency {
+            package: fpm::Package::new("theme.example.com"),
+            version: None,
+            notes: None,
+            alias: None,
+            implements: vec![],
+        });
+        config
+    }
 
-    let pattern = match attribute {
-        Some(key) => msg
-            .get_attribute(key)
-            .unwrap_or_else(|| panic!("{}", i))
-            .value(),
-        None => msg.value().unwrap_or_else(|| panic!("{}", i)),
-    };
+    fn write(root: &camino::Utf8Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
 
-    let s = b.format_pattern(pattern, args, &mut errors);
+    #[test]
+    fn lookup() {
+        let config = config("lookup");
+        write(
+            &config.packages_root,
+            "theme.example.com/FPM/i18n/hi/translation.ftl",
+            "never-synced = थीम\ncurrent-language = थीम\n",
+        );
+        write(
+            &config.root,
+            "FPM/i18n/hi/translation.ftl",
+            "current-language = पैकेज\n",
+        );
 
-    if !errors.is_empty() {
-        panic!("errors found in {}: {:?}", i, errors)
+        let paths = super::resources(&config, "hi", "translation")
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<camino::Utf8PathBuf>>();
+        let base = super::new_base();
+        let lookup = |id| super::lookup(&base, &config, "hi", "translation", id, None, None);
+        let values = (
+            lookup("last-modified-on"),
+            lookup("never-synced"),
+            lookup("current-language"),
+        );
+        std::fs::remove_dir_all(&config.root).unwrap();
+
+        assert_eq!(
+            paths,
+            vec![
+                camino::Utf8PathBuf::from("i18n/hi/terms.ftl"),
+                camino::Utf8PathBuf::from("i18n/hi/translation.ftl"),
+                config
+                    .packages_root
+                    .join("theme.example.com/FPM/i18n/hi/translation.ftl"),
+                config.root.join("FPM/i18n/hi/translation.ftl"),
+            ]
+        );
+        // bundled, overridden by the dependency, overridden by the package
+        assert_eq!(values.0, "अंतिम संशोधित तिथि :");
+        assert_eq!(values.1, "थीम");
+        assert_eq!(values.2, "पैकेज");
     }
 
-    s.into()
-}
+    #[test]
+    fn missing_keys() {
+        let config = config("missing");
+        write(
+            &config.root,
+            "FPM/i18n/pt/translation.ftl",
+            "last-modified-on = Modificado em:\n",
+        );
+        write(
+            &config.packages_root,
+            "theme.example.com/FPM/i18n/pt-BR/translation.ftl",
+            "# a comment = not a message\nnever-synced =\n    Nunca sincronizado\n",
+        );
+        let translation = super::missing_keys(&config, "pt-BR", "translation");
+        let terms = super::missing_keys(&config, "pt-BR", "terms");
+        std::fs::remove_dir_all(&config.root).unwrap();
 
-fn read_file(lang: &realm_lang::Language, res: &'static str) -> Option<String> {
-    let string = match (lang, res) {
-        (&realm_lang::Language::Hindi, "translation") => {
-            include_str!("../../i18n/hi/translation.ftl")
-        }
-        (_, "translation") => {
-            include_str!("../../i18n/en/translation.ftl")
-        }
-        _ => panic!(),
-    };
-    Some(string.to_string())
+        let english = super::message_ids(include_str!("../../i18n/en/translation.ftl"));
+        assert_eq!(translation.len(), english.len() - 1);
+        assert!(!translation.iter().any(|(key, _)| key == "never-synced"));
+        assert!(translation.contains(&("last-modified-on".to_string(), "pt".to_string())));
+        assert!(translation.contains(&("current-language".to_string(), "en".to_string())));
+        assert_eq!(terms, vec![("-brand".to_string(), "en".to_string())]);
+    }
 }
@@ -1,26 +1,27 @@
 const RES: &str = "translation";
 
 lazy_static! {
-    pub static ref TRANSLATION: fpm::i18n::Base = fpm::i18n::new_base(RES);
+    pub static ref TRANSLATION: fpm::i18n::Base = fpm::i18n::new_base();
 }
 
 pub fn search(
-    lang: &realm_lang::Language,
-    primary_lang: &realm_lang::Language,
+    config: &fpm::Config,
+    lang: &str,
+    primary_lang: &str,
     key: &'static str,
     last_modified_on: &Option<String>,
 ) -> String {
     let mut args = fluent::FluentArgs::new();
     args.set(
         "primary-lang",
-        fluent::FluentValue::from(primary_lang.human()),
+        fluent::FluentValue::from(fpm::utils::language_to_human(primary_lang)),
     );
+    args.set("primary-lang-code", fluent::FluentValue::from(primary_lang));
     args.set(
-        "primary-lang-code",
-        fluent::FluentValue::from(primary_lang.id()),
+        "lang",
+        fluent::FluentValue::from(fpm::utils::language_to_human(lang)),
     );
-    args.set("lang", fluent::FluentValue::from(lang.human()));
-    args.set("lang-code", fluent::FluentValue::from(lang.id()));
+    args.set("lang-code", fluent::FluentValue::from(lang));
     let last_modified_on = if let Some(last_modified_on) = last_modified_on {
         last_modified_on.to_string()
     } else {
@@ -30,5 +31,5 @@ pub fn search(
         "last-modified-on",
         fluent::FluentValue::from(last_modified_on.as_str()),
     );
-    fpm::i18n::lookup(&TRANSLATION, lang, RES, key, None, Some(&args))
+    fpm::i18n::lookup(&TRANSLATION, config, lang, RES, key, None, Some(&args))
 }
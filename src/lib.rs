@@ -7,6 +7,7 @@ extern crate lazy_static;
 pub(crate) mod utils;
 
 // Temp comment
+mod artifacts;
 mod auto_import;
 mod commands;
 mod config;
@@ -14,28 +15,40 @@ mod dependency;
 mod doc;
 mod file;
 mod font;
+mod glossary;
+mod highlight;
+mod http_cache;
 mod i18n;
 mod library;
+mod machine_translation;
+mod meta;
+mod optimize;
+mod redirects;
 mod render;
+mod search;
+mod segment;
 mod sitemap;
 mod snapshot;
 mod tracker;
+mod transclude;
 mod translation;
 mod version;
 
 pub(crate) use auto_import::AutoImport;
 pub(crate) use commands::build::process_file;
 pub use commands::{
-    build::build, diff::diff, mark_upto_date::mark_upto_date, serve::serve,
+    build::build, check::check, diff::diff, mark_upto_date::mark_upto_date, serve::serve,
     start_project::start_project, start_tracking::start_tracking, status::status,
-    stop_tracking::stop_tracking, sync::sync, translation_status::translation_status,
-    update::update,
+    stop_tracking::stop_tracking, sync::sync, translate::translate,
+    translation_export::translation_export, translation_import::translation_import,
+    translation_lint::translation_lint, translation_status::translation_status, update::update,
 };
 pub use config::Config;
 pub(crate) use config::Package;
 pub(crate) use dependency::Dependency;
 pub(crate) use file::{get_file, paths_to_files, Document, File, Static};
 pub(crate) use font::Font;
+pub use http_cache::HttpCacheMode;
 pub(crate) use library::{FPMLibrary, Library};
 pub use render::render;
 pub(crate) use snapshot::Snapshot;
@@ -146,6 +159,12 @@ fn package_info_image(
     })
 }
 
+/// `package_info_code()` returns the ftd page of a code file. `FPM/code.ftd` of the package
+/// overrides it, else the `code-page` of the package-info package is used.
+///
+/// When the code is syntax highlighted, the `code-page` of package-info does not show it, so fpm
+/// renders the highlighted html in its own page instead. A theme which wants to show it can
+/// override the page with `FPM/code.ftd` and use `fpm.highlighted-code`.
 fn package_info_code(
     config: &fpm::Config,
     file_name: &str,
@@ -155,6 +174,21 @@ fn package_info_code(
     let path = config.root.join("FPM").join("code.ftd");
     Ok(if path.is_file() {
         std::fs::read_to_string(path)?
+    } else if let Some(ref highlighted_code) = config.highlighted_code {
+        // the highlighted html is a single line, so it is kept as a raw html block of the body
+        indoc::formatdoc! {"
+            {body_prefix}
+
+            -- ftd.text: {file_name}
+
+            -- ftd.text:
+
+            {highlighted_code}
+        ",
+        body_prefix = config.package.generate_prefix_string(false).unwrap_or_default(),
+        file_name = file_name,
+        highlighted_code = highlighted_code,
+        }
     } else {
         let package_info_package = match config
             .package
@@ -331,6 +365,110 @@ fn translation_package_status(config: &fpm::Config) -> fpm::Result<String> {
     })
 }
 
+/// `translation_matrix()` returns the `/-/translation-matrix/` page of an original package, with
+/// the translation status of each document in each language. `FPM/translation/matrix.ftd` can replace
+/// the heading of the page, it must end with a container with id `matrix` for the rows.
+async fn translation_matrix(config: &fpm::Config) -> fpm::Result<String> {
+    let path = config
+        .root
+        .join("FPM")
+        .join("translation")
+        .join("matrix.ftd");
+    let mut page = if path.is_file() {
+        std::fs::read_to_string(path)?
+    } else {
+        include_str!("../ftd/translation/matrix.ftd").to_string()
+    };
+
+    let snapshots = fpm::snapshot::get_latest_snapshots(&config.root).await?;
+    let mut statuses = vec![];
+    for translation in config.package.translations.iter() {
+        let root = config.packages_root.join(translation.name.as_str());
+        statuses.push(if root.join("FPM.ftd").exists() {
+            Some(fpm::commands::translation_status::get_translation_status(
+                &snapshots,
+                &root,
+                &config.root,
+            )?)
+        } else {
+            None
+        });
+    }
+
+    let width = 100 / (config.package.translations.len() + 1);
+    let mut header = vec![("$fpm.i18n.document".to_string(), None)];
+    for translation in config.package.translations.iter() {
+        header.push((
+            translation
+                .language
+                .as_deref()
+                .map(fpm::utils::language_to_human)
+                .unwrap_or_else(|| translation.name.to_string()),
+            Some(format!(
+                "{}-/translation-status/",
+                fpm::artifacts::base_url(translation)
+            )),
+        ));
+    }
+    page.push_str(matrix_row(&header, width).as_str());
+
+    for file in snapshots.keys() {
+        let mut cells = vec![(file.to_string(), None)];
+        for (translation, status) in config.package.translations.iter().zip(statuses.iter()) {
+            let status = match status.as_ref().and_then(|v| v.get(file)) {
+                Some(fpm::commands::translation_status::TranslationStatus::Missing) => {
+                    "$fpm.i18n.missing"
+                }
+                Some(fpm::commands::translation_status::TranslationStatus::NeverMarked) => {
+                    "$fpm.i18n.never-marked"
+                }
+                Some(fpm::commands::translation_status::TranslationStatus::Outdated { .. }) => {
+                    "$fpm.i18n.out-dated"
+                }
+                Some(fpm::commands::translation_status::TranslationStatus::UptoDate) => {
+                    "$fpm.i18n.upto-date"
+                }
+                None => "-",
+            };
+            cells.push((
+                status.to_string(),
+                Some(fpm::artifacts::document_url(translation, file)),
+            ));
+        }
+        page.push_str(matrix_row(&cells, width).as_str());
+    }
+    return Ok(page);
+
+    /// `matrix_row()` returns a row of cells, each with a text and an optional link
+    fn matrix_row(cells: &[(String, Option<String>)], width: usize) -> String {
+        let mut row = indoc::indoc! {"
+
+
+            -- ftd.row:
+            width: fill
+            padding: 10
+            border-top: 1
+        "}
+        .to_string();
+        for (text, link) in cells {
+            row = format!(
+                indoc::indoc! {"
+                    {row}
+                    --- ftd.text: {text}
+                    width: percent {width}
+                "},
+                row = row,
+                text = text,
+                width = width
+            );
+            if let Some(link) = link {
+                row = format!("{}link: {}\n", row, link);
+            }
+        }
+        format!("{}\n-- container: matrix\n", row)
+    }
+}
+
 fn get_messages(status: &fpm::TranslatedDocument, config: &fpm::Config) -> fpm::Result<String> {
     Ok(match status {
         TranslatedDocument::Missing { .. } => {
@@ -433,4 +571,65 @@ mod tests {
             panic!("test failed")
         }
     }
+
+    #[test]
+    fn translation_matrix() {
+        let root = camino::Utf8PathBuf::from_path_buf(
+            std::env::temp_dir().join(format!("fpm-matrix-{}", std::process::id())),
+        )
+        .unwrap();
+        std::fs::create_dir_all(root.join(".history")).unwrap();
+        std::fs::write(
+            root.join(".history/.latest.ftd"),
+            "-- import: fpm\n\n-- fpm.snapshot: index.ftd\ntimestamp: 1\n\n\
+            -- fpm.snapshot: guide/intro.ftd\ntimestamp: 2\n",
+        )
+        .unwrap();
+
+        let mut config = fpm::Config::for_test(root.clone());
+        let mut hindi = fpm::Package::new("hi.example.com");
+        hindi.canonical_url = Some("https://example.com/hi".to_string());
+        config.package.translations = vec![hindi, fpm::Package::new("fr.example.com")];
+
+        let page = tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(super::translation_matrix(&config))
+            .unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(page.starts_with(include_str!("../ftd/translation/matrix.ftd")));
+        assert!(page.contains(
+            "--- ftd.text: hi.example.com\nwidth: percent 33\n\
+            link: https://example.com/hi/-/translation-status/\n"
+        ));
+        assert!(page.contains(
+            "--- ftd.text: fr.example.com\nwidth: percent 33\n\
+            link: https://fr.example.com/-/translation-status/\n"
+        ));
+        // neither translation is downloaded, so the status of its documents is not known
+        assert!(page.contains(
+            "--- ftd.text: guide/intro.ftd\nwidth: percent 33\n\n\
+            --- ftd.text: -\nwidth: percent 33\nlink: https://example.com/hi/guide/intro/\n\n\
+            --- ftd.text: -\nwidth: percent 33\nlink: https://fr.example.com/guide/intro/\n"
+        ));
+        assert!(page.contains("link: https://example.com/hi/\n"));
+    }
+
+    #[test]
+    fn package_info_code() {
+        let mut config = fpm::Config::for_test(camino::Utf8PathBuf::from("/tmp/fpm-code"));
+        let code = "fn main() {\n    println!(\"Hello\");\n}\n";
+        let page = super::package_info_code(&config, "hello.rs.ftd", code, "rs").unwrap();
+        assert!(page.contains("-- pi.code-page: hello.rs.ftd\nlang: rs\n"));
+
+        // highlighted code is rendered by fpm, not by the `code-page` of package-info
+        config.package.highlight_code = true;
+        let html = fpm::highlight::to_html(&config, code, "rs", &Default::default()).unwrap();
+        assert!(!html.contains('\n'));
+        config.highlighted_code = Some(html.clone());
+        let page = super::package_info_code(&config, "hello.rs.ftd", code, "rs").unwrap();
+        assert!(!page.contains("pi.code-page"));
+        assert!(page.contains(format!("-- ftd.text:\n\n{}\n", html).as_str()));
+        fpm::doc::parse_ftd("hello.rs.ftd", page.as_str(), &fpm::FPMLibrary::default()).unwrap();
+    }
 }
@@ -0,0 +1,143 @@
+/// `$processor$: env` reads an environment variable, like the git sha or release version set by
+/// CI:
+///
+/// ```ftd
+/// -- string sha:
+/// $processor$: env
+/// name: GIT_SHA
+/// default: dev
+/// ```
+///
+/// Only the variables declared using `fpm.env` in `FPM.ftd` can be read, so secrets in the
+/// environment do not end up in the published pages:
+///
+/// ```ftd
+/// -- fpm.env: GIT_SHA
+/// default: unknown
+/// ```
+///
+/// `name` defaults to the name of the ftd variable. If the environment variable is not set, the
+/// `default` of the section is used, then the `default` in `FPM.ftd`. The value is parsed as per
+/// the kind of the ftd variable, like `get-data` does.
+pub fn processor(
+    section: &ftd::p1::Section,
+    doc: &ftd::p2::TDoc,
+    config: &fpm::Config,
+) -> ftd::p1::Result<ftd::Value> {
+    let value = value(section, doc.name, &config.package)?;
+    fpm::library::get_data::from_string(section, doc, value.as_str())
+}
+
+/// `value()` returns the value of the environment variable the section reads, or its default.
+fn value(
+    section: &ftd::p1::Section,
+    doc_id: &str,
+    package: &fpm::Package,
+) -> ftd::p1::Result<String> {
+    let name = match section
+        .header
+        .string_optional(doc_id, section.line_number, "name")?
+    {
+        Some(name) => name,
+        None => match section.name.rsplit_once(' ') {
+            Some((_, name)) => name.to_string(),
+            None => section.name.to_string(),
+        },
+    };
+
+    let allowed = match package.env.iter().find(|v| v.name == name) {
+        Some(v) => v,
+        None => {
+            return ftd::e2(
+                format!(
+                    "environment variable `{}` is not allowed, add `-- fpm.env: {}` to FPM.ftd",
+                    name, name
+                ),
+                doc_id,
+                section.line_number,
+            )
+        }
+    };
+
+    Ok(match std::env::var(name.as_str()) {
+        Ok(v) => v,
+        Err(_) => match section
+            .header
+            .string_optional(doc_id, section.line_number, "default")?
+            .or_else(|| allowed.default.clone())
+        {
+            Some(v) => v,
+            None => {
+                return ftd::e2(
+                    format!(
+                        "environment variable `{}` is not set and has no default",
+                        name
+                    ),
+                    doc_id,
+                    section.line_number,
+                )
+            }
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    fn value(source: &str) -> ftd::p1::Result<String> {
+        let mut package = fpm::Package::new("foo");
+        package.env = vec![
+            fpm::config::EnvVariable {
+                name: "FPM_ENV_TEST_SHA".to_string(),
+                default: Some("unknown".to_string()),
+            },
+            fpm::config::EnvVariable {
+                name: "FPM_ENV_TEST_RELEASE".to_string(),
+                default: Some("unknown".to_string()),
+            },
+            fpm::config::EnvVariable {
+                name: "FPM_ENV_TEST_UNSET".to_string(),
+                default: None,
+            },
+        ];
+        let sections = ftd::p1::parse(source, "foo").unwrap();
+        super::value(&sections[0], "foo", &package)
+    }
+
+    #[test]
+    fn allowed() {
+        std::env::set_var("FPM_ENV_TEST_SHA", "abc123");
+        std::env::set_var("FPM_ENV_TEST_SECRET", "hunter2");
+        assert_eq!(
+            value("-- string sha:\n$processor$: env\nname: FPM_ENV_TEST_SHA\n").unwrap(),
+            "abc123"
+        );
+        // `name` defaults to the name of the variable
+        assert_eq!(
+            value("-- string FPM_ENV_TEST_SHA:\n$processor$: env\n").unwrap(),
+            "abc123"
+        );
+        assert!(value("-- string secret:\n$processor$: env\nname: FPM_ENV_TEST_SECRET\n").is_err());
+    }
+
+    #[test]
+    fn default() {
+        std::env::remove_var("FPM_ENV_TEST_RELEASE");
+        std::env::remove_var("FPM_ENV_TEST_UNSET");
+        // the default of the section is used before the one in FPM.ftd
+        assert_eq!(
+            value("-- string v:\n$processor$: env\nname: FPM_ENV_TEST_RELEASE\ndefault: dev\n")
+                .unwrap(),
+            "dev"
+        );
+        assert_eq!(
+            value("-- string v:\n$processor$: env\nname: FPM_ENV_TEST_RELEASE\n").unwrap(),
+            "unknown"
+        );
+        assert_eq!(
+            value("-- string v:\n$processor$: env\nname: FPM_ENV_TEST_UNSET\ndefault: dev\n")
+                .unwrap(),
+            "dev"
+        );
+        assert!(value("-- string v:\n$processor$: env\nname: FPM_ENV_TEST_UNSET\n").is_err());
+    }
+}
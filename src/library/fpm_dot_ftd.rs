@@ -1,20 +1,16 @@
 use crate::utils::HasElements;
 
 fn i18n_data(lib: &fpm::Library) -> String {
-    let lang = match lib.config.package.language {
-        Some(ref lang) => {
-            realm_lang::Language::from_2_letter_code(lang).unwrap_or(realm_lang::Language::English)
-        }
-        None => realm_lang::Language::English,
-    };
+    let lang = lib
+        .config
+        .package
+        .language
+        .clone()
+        .unwrap_or_else(|| "en".to_string());
 
     let primary_lang = match lib.config.package.translation_of.as_ref() {
-        Some(ref package) => match package.language {
-            Some(ref lang) => realm_lang::Language::from_2_letter_code(lang)
-                .unwrap_or(realm_lang::Language::English),
-            None => lang,
-        },
-        None => lang,
+        Some(ref package) => package.language.clone().unwrap_or_else(|| lang.clone()),
+        None => lang.clone(),
     };
 
     let current_document_last_modified_on = futures::executor::block_on(
@@ -37,6 +33,7 @@ fn i18n_data(lib: &fpm::Library) -> String {
             out-dated-body: {out_dated_body}
             out-dated-heading: {out_dated_heading}
             out-dated: {out_dated}
+            search: {search}
             show-latest-version: {show_latest_version}
             show-outdated-version: {show_outdated_version}
             show-translation-status: {show_translation_status}
@@ -50,144 +47,175 @@ fn i18n_data(lib: &fpm::Library) -> String {
             welcome-fpm-page: {welcome_fpm_page}
         "},
         current_language = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "current-language",
             &current_document_last_modified_on
         ),
         document = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "document",
             &current_document_last_modified_on
         ),
         language = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "language",
             &current_document_last_modified_on
         ),
         language_detail_page = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "language-detail-page",
             &current_document_last_modified_on
         ),
         language_detail_page_body = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "language-detail-page-body",
             &current_document_last_modified_on
         ),
         last_modified_on = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "last-modified-on",
             &current_document_last_modified_on
         ),
         never_synced = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "never-synced",
             &current_document_last_modified_on
         ),
         missing = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "missing",
             &current_document_last_modified_on
         ),
         never_marked = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "never-marked",
             &current_document_last_modified_on
         ),
         other_available_languages = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "other-available-languages",
             &current_document_last_modified_on
         ),
         out_dated = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "out-dated",
             &current_document_last_modified_on
         ),
         out_dated_body = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "out-dated-body",
             &current_document_last_modified_on
         ),
         out_dated_heading = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "out-dated-heading",
             &current_document_last_modified_on
         ),
+        search = fpm::i18n::translation::search(
+            &lib.config,
+            &lang,
+            &primary_lang,
+            "search",
+            &current_document_last_modified_on
+        ),
         show_latest_version = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "show-latest-version",
             &current_document_last_modified_on
         ),
         show_outdated_version = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "show-outdated-version",
             &current_document_last_modified_on
         ),
         show_translation_status = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "show-translation-status",
             &current_document_last_modified_on
         ),
         show_unapproved_version = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "show-unapproved-version",
             &current_document_last_modified_on
         ),
         status = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "status",
             &current_document_last_modified_on
         ),
         total_number_of_documents = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "total-number-of-documents",
             &current_document_last_modified_on
         ),
         translation_not_available = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "translation-not-available",
             &current_document_last_modified_on
         ),
         unapproved_heading = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "unapproved-heading",
             &current_document_last_modified_on
         ),
         upto_date = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "upto-date",
             &current_document_last_modified_on
         ),
         welcome_fpm_page = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "welcome-fpm-page",
             &current_document_last_modified_on
         ),
         welcome_fpm_page_subtitle = fpm::i18n::translation::search(
+            &lib.config,
             &lang,
             &primary_lang,
             "welcome-fpm-page-subtitle",
@@ -312,6 +340,34 @@ pub(crate) fn get(lib: &fpm::Library) -> String {
         );
     }
 
+    for section in lib.translated_data.outdated_sections.iter() {
+        fpm_base = format!(
+            indoc::indoc! {"
+                {fpm_base}
+
+                -- outdated-sections: {id}
+                change: {change}
+                line: {line}
+
+                {diff}
+            "},
+            fpm_base = fpm_base,
+            id = section.id,
+            change = section.change.as_str(),
+            line = section.line,
+            diff = section
+                .diff
+                .lines()
+                .map(|l| if l.starts_with("--") {
+                    format!("\\{}", l)
+                } else {
+                    l.to_string()
+                })
+                .collect::<Vec<String>>()
+                .join("\n"),
+        );
+    }
+
     if let Some(ref status) = lib.translated_data.status {
         fpm_base = format!(
             indoc::indoc! {"
@@ -382,6 +438,20 @@ pub(crate) fn get(lib: &fpm::Library) -> String {
         );
     }
 
+    if let Some(ref highlighted_code) = lib.config.highlighted_code {
+        fpm_base = format!(
+            indoc::indoc! {"
+                {fpm_base}
+
+                -- highlighted-code:
+
+                {highlighted_code}
+            "},
+            fpm_base = fpm_base,
+            highlighted_code = highlighted_code,
+        );
+    }
+
     if let Some(ref language) = lib.config.package.language {
         fpm_base = format!(
             indoc::indoc! {"
@@ -392,6 +462,18 @@ pub(crate) fn get(lib: &fpm::Library) -> String {
             fpm_base = fpm_base,
             language = fpm::utils::language_to_human(language),
         );
+        if fpm::utils::language_direction(language) == "rtl" {
+            fpm_base = format!(
+                indoc::indoc! {"
+                    {fpm_base}
+
+                    -- direction: rtl
+
+                    -- is-rtl: true
+                "},
+                fpm_base = fpm_base,
+            );
+        }
     }
 
     if let Some(ref last_marked_on) = lib.translated_data.last_marked_on {
@@ -471,6 +553,7 @@ pub(crate) fn get(lib: &fpm::Library) -> String {
                 fpm::commands::translation_status::get_translation_status(
                     &original_snapshots,
                     &lib.config.root,
+                    &original_path,
                 )
             {
                 let mut never_marked_files = "".to_string();
@@ -546,7 +629,9 @@ pub(crate) fn get(lib: &fpm::Library) -> String {
                                 static_attrs = static_attrs,
                             );
                         }
-                        fpm::commands::translation_status::TranslationStatus::Outdated => {
+                        fpm::commands::translation_status::TranslationStatus::Outdated {
+                            ..
+                        } => {
                             outdated_files = format!(
                                 indoc::indoc! {"
                                     {list}
@@ -638,6 +723,15 @@ pub(crate) fn get(lib: &fpm::Library) -> String {
                             out_dated = status.out_dated,
                             upto_date = status.upto_date
                         );
+                        if let Some(out_dated_sections) = status.out_dated_sections {
+                            status_data = format!(
+                                indoc::indoc! {"
+                                    {status}out-dated-sections: {out_dated_sections}
+                                "},
+                                status = status_data,
+                                out_dated_sections = out_dated_sections
+                            );
+                        }
                         if let Some(ref last_modified_on) = status.last_modified_on {
                             status_data = format!(
                                 indoc::indoc! {"
@@ -23,7 +23,11 @@ pub fn processor(
     }
 
     if let Some(data) = config.extra_data.get(name.as_str()) {
-        return doc.from_json(data, section);
+        let data = match fpm::library::json::transform(section, doc.name, data.to_owned()) {
+            Ok(v) => v,
+            Err(e) => return ftd::e2(e.to_string(), doc.name, section.line_number),
+        };
+        return doc.from_json(&data, section);
     }
 
     if let Some(ref sitemap) = config.sitemap {
@@ -41,46 +45,21 @@ pub fn processor(
 
         if let Some(extra_data) = sitemap.get_extra_data_by_id(doc_id.as_str()) {
             if let Some(data) = extra_data.get(name.as_str()) {
-                let kind = doc.get_variable_kind(section)?;
-                return match kind {
-                    ftd::p2::Kind::Integer { .. } => {
-                        let value =
-                            data.parse::<i64>()
-                                .map_err(|e| ftd::p1::Error::ParseError {
-                                    message: e.to_string(),
-                                    doc_id: doc.name.to_string(),
-                                    line_number: section.line_number,
-                                })?;
-                        doc.from_json(&value, section)
-                    }
-                    ftd::p2::Kind::Decimal { .. } => {
-                        let value =
-                            data.parse::<f64>()
-                                .map_err(|e| ftd::p1::Error::ParseError {
-                                    message: e.to_string(),
-                                    doc_id: doc.name.to_string(),
-                                    line_number: section.line_number,
-                                })?;
-                        doc.from_json(&value, section)
-                    }
-                    ftd::p2::Kind::Boolean { .. } => {
-                        let value =
-                            data.parse::<bool>()
-                                .map_err(|e| ftd::p1::Error::ParseError {
-                                    message: e.to_string(),
-                                    doc_id: doc.name.to_string(),
-                                    line_number: section.line_number,
-                                })?;
-                        doc.from_json(&value, section)
-                    }
-                    _ => doc.from_json(data, section),
-                };
+                return from_string(section, doc, data.as_str());
             }
         }
     }
 
     if let Some((_, ref b)) = section.body {
-        return doc.from_json(&serde_json::from_str::<serde_json::Value>(b)?, section);
+        let data = match fpm::library::json::transform(
+            section,
+            doc.name,
+            serde_json::from_str::<serde_json::Value>(b)?,
+        ) {
+            Ok(v) => v,
+            Err(e) => return ftd::e2(e.to_string(), doc.name, section.line_number),
+        };
+        return doc.from_json(&data, section);
     }
 
     let caption = match section.caption {
@@ -108,3 +87,46 @@ pub fn processor(
 
     doc.from_json(&serde_json::json!(caption), section)
 }
+
+/// `from_string()` converts `data` to the ftd variable of the `section`, parsing it as integer,
+/// decimal or boolean if that is the kind of the variable.
+pub(crate) fn from_string(
+    section: &ftd::p1::Section,
+    doc: &ftd::p2::TDoc,
+    data: &str,
+) -> ftd::p1::Result<ftd::Value> {
+    let kind = doc.get_variable_kind(section)?;
+    match kind {
+        ftd::p2::Kind::Integer { .. } => {
+            let value = data
+                .parse::<i64>()
+                .map_err(|e| ftd::p1::Error::ParseError {
+                    message: e.to_string(),
+                    doc_id: doc.name.to_string(),
+                    line_number: section.line_number,
+                })?;
+            doc.from_json(&value, section)
+        }
+        ftd::p2::Kind::Decimal { .. } => {
+            let value = data
+                .parse::<f64>()
+                .map_err(|e| ftd::p1::Error::ParseError {
+                    message: e.to_string(),
+                    doc_id: doc.name.to_string(),
+                    line_number: section.line_number,
+                })?;
+            doc.from_json(&value, section)
+        }
+        ftd::p2::Kind::Boolean { .. } => {
+            let value = data
+                .parse::<bool>()
+                .map_err(|e| ftd::p1::Error::ParseError {
+                    message: e.to_string(),
+                    doc_id: doc.name.to_string(),
+                    line_number: section.line_number,
+                })?;
+            doc.from_json(&value, section)
+        }
+        _ => doc.from_json(&data, section),
+    }
+}
@@ -1,31 +1,83 @@
+/// `$processor$: http` fetches JSON from `url` and converts it to the ftd variable:
+///
+/// ```ftd
+/// -- record repo-data:
+/// string name:
+///
+/// -- repo-data repo:
+/// $processor$: http
+/// url: https://api.example.com/repos
+/// method: post
+/// body-type: form
+/// timeout: 10
+/// header-Accept: application/json
+/// bearer-token-env: EXAMPLE_API_TOKEN
+/// name: fpm
+/// ```
+///
+/// `method` can be `get` (default), `post` or `put`. Headers starting with `header-` are sent as
+/// request headers. The bearer token is read from the environment variable named by
+/// `bearer-token-env`, so secrets never end up in documents. Fixtures are replayed without the
+/// token, so the variable need not be set for `fpm build --http-fixtures replay`.
+///
+/// All other headers are sent as query string params for `get`, and as the request body for
+/// `post` and `put`, json encoded (default) or form encoded as per `body-type`. Instead, the body
+/// of the section, if present, is sent as is.
+///
+/// The response can be reshaped using `path`, `map-` and `rename-` headers, see
+/// `fpm::library::json::transform()`.
+///
+/// Successful responses are cached for `cache-ttl` seconds, see `fpm::http_cache`.
 pub async fn processor<'a>(
     section: &ftd::p1::Section,
     doc: &ftd::p2::TDoc<'a>,
+    config: &fpm::Config,
 ) -> ftd::p1::Result<ftd::Value> {
-    {
-        let method = section
-            .header
-            .str_with_default(doc.name, section.line_number, "method", "GET")?
-            .to_lowercase();
+    let (request, ttl) = request(section, doc.name, &config.http_cache)?;
+    let json = fetch(request, config, ttl, doc.name, section.line_number).await?;
+    let json = match fpm::library::json::transform(section, doc.name, json) {
+        Ok(v) => v,
+        Err(e) => return ftd::e2(e.to_string(), doc.name, section.line_number),
+    };
+    doc.from_json(&json, section)
+}
 
-        if method != "get" {
+/// `request()` builds the request described by the section headers and body, and returns it
+/// along with the number of seconds the response is cached for.
+fn request(
+    section: &ftd::p1::Section,
+    doc_id: &str,
+    mode: &fpm::HttpCacheMode,
+) -> ftd::p1::Result<(Request, u64)> {
+    let method = section
+        .header
+        .str_with_default(doc_id, section.line_number, "method", "GET")?
+        .to_lowercase();
+    let method = match method.as_str() {
+        "get" => reqwest::Method::GET,
+        "post" => reqwest::Method::POST,
+        "put" => reqwest::Method::PUT,
+        _ => {
             return ftd::e2(
-                format!("only GET method is allowed, found: {}", method),
-                doc.name,
+                format!(
+                    "only GET, POST and PUT methods are allowed, found: {}",
+                    method
+                ),
+                doc_id,
                 section.line_number,
-            );
+            )
         }
-    }
+    };
 
     let url = match section
         .header
-        .string_optional(doc.name, section.line_number, "url")?
+        .string_optional(doc_id, section.line_number, "url")?
     {
         Some(v) => v,
         None => {
             return ftd::e2(
                 "'url' key is required when using `$processor$: http`",
-                doc.name,
+                doc_id,
                 section.line_number,
             )
         }
@@ -33,39 +85,256 @@ pub async fn processor<'a>(
 
     let mut url = match url::Url::parse(url.as_str()) {
         Ok(v) => v,
-        Err(e) => {
+        Err(e) => return ftd::e2(format!("invalid url: {:?}", e), doc_id, section.line_number),
+    };
+
+    let body_type = section
+        .header
+        .str_with_default(doc_id, section.line_number, "body-type", "json")?
+        .to_lowercase();
+    if body_type != "json" && body_type != "form" {
+        return ftd::e2(
+            format!("body-type can be `json` or `form`, found: {}", body_type),
+            doc_id,
+            section.line_number,
+        );
+    }
+
+    let timeout = match section
+        .header
+        .i64_optional(doc_id, section.line_number, "timeout")?
+    {
+        Some(v) if v > 0 => Some(std::time::Duration::from_secs(v as u64)),
+        Some(v) => {
             return ftd::e2(
-                format!("invalid url: {:?}", e),
-                doc.name,
+                format!("timeout must be positive seconds, found: {}", v),
+                doc_id,
                 section.line_number,
             )
         }
+        None => None,
     };
 
+    let ttl = match section
+        .header
+        .i64_optional(doc_id, section.line_number, "cache-ttl")?
+    {
+        Some(v) if v >= 0 => v as u64,
+        Some(v) => {
+            return ftd::e2(
+                format!("cache-ttl must not be negative, found: {}", v),
+                doc_id,
+                section.line_number,
+            )
+        }
+        None => fpm::http_cache::DEFAULT_TTL,
+    };
+
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(env) = section
+        .header
+        .string_optional(doc_id, section.line_number, "bearer-token-env")?
+        .filter(|_| *mode != fpm::HttpCacheMode::Replay)
+    {
+        let token = match std::env::var(env.as_str()) {
+            Ok(v) => v,
+            Err(_) => {
+                return ftd::e2(
+                    format!("environment variable `{}` is not set", env),
+                    doc_id,
+                    section.line_number,
+                )
+            }
+        };
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            header_value(
+                format!("Bearer {}", token).as_str(),
+                doc_id,
+                section.line_number,
+            )?,
+        );
+    }
+
+    let mut params = vec![];
     for (_, k, v) in section.header.0.iter() {
-        if k == "$processor$" || k == "url" || k == "method" {
+        if k == "$processor$"
+            || k == "url"
+            || k == "method"
+            || k == "body-type"
+            || k == "timeout"
+            || k == "cache-ttl"
+            || k == "bearer-token-env"
+            || fpm::library::json::is_transform_header(k)
+        {
             continue;
         }
-        url.query_pairs_mut().append_pair(k, v);
+        if let Some(name) = k.strip_prefix("header-") {
+            let name = match reqwest::header::HeaderName::from_bytes(name.as_bytes()) {
+                Ok(v) => v,
+                Err(e) => {
+                    return ftd::e2(
+                        format!("invalid header name `{}`: {:?}", name, e),
+                        doc_id,
+                        section.line_number,
+                    )
+                }
+            };
+            headers.insert(name, header_value(v.as_str(), doc_id, section.line_number)?);
+            continue;
+        }
+        params.push((k.to_string(), v.to_string()));
     }
 
-    let json = get(url, doc.name, section.line_number).await?;
-    doc.from_json(&json, section)
+    let body = if method == reqwest::Method::GET {
+        for (k, v) in params {
+            url.query_pairs_mut().append_pair(k.as_str(), v.as_str());
+        }
+        None
+    } else if let Some((_, ref body)) = section.body {
+        if body_type == "json" && !headers.contains_key(reqwest::header::CONTENT_TYPE) {
+            headers.insert(
+                reqwest::header::CONTENT_TYPE,
+                reqwest::header::HeaderValue::from_static("application/json"),
+            );
+        }
+        Some(Body::Raw(body.to_string()))
+    } else if body_type == "form" {
+        Some(Body::Form(params))
+    } else {
+        Some(Body::Json(serde_json::Value::Object(
+            params
+                .into_iter()
+                .map(|(k, v)| {
+                    // `count: 10` is sent as a number and `draft: false` as a boolean
+                    let value = match serde_json::from_str::<serde_json::Value>(v.as_str()) {
+                        Ok(value) if value.is_number() || value.is_boolean() => value,
+                        _ => serde_json::Value::String(v),
+                    };
+                    (k, value)
+                })
+                .collect(),
+        )))
+    };
+
+    Ok((
+        Request {
+            method,
+            url,
+            headers,
+            body,
+            timeout,
+        },
+        ttl,
+    ))
+}
+
+fn header_value(
+    value: &str,
+    doc_id: &str,
+    line_number: usize,
+) -> ftd::p1::Result<reqwest::header::HeaderValue> {
+    match reqwest::header::HeaderValue::from_str(value) {
+        Ok(v) => Ok(v),
+        Err(e) => ftd::e2(
+            format!("invalid header value: {:?}", e),
+            doc_id,
+            line_number,
+        ),
+    }
+}
+
+#[derive(Debug)]
+enum Body {
+    Json(serde_json::Value),
+    Form(Vec<(String, String)>),
+    Raw(String),
 }
 
-async fn get(
+#[derive(Debug)]
+struct Request {
+    method: reqwest::Method,
     url: url::Url,
+    headers: reqwest::header::HeaderMap,
+    body: Option<Body>,
+    timeout: Option<std::time::Duration>,
+}
+
+impl Request {
+    fn cache_key(&self) -> fpm::http_cache::Key {
+        fpm::http_cache::Key {
+            method: self.method.to_string(),
+            url: self.url.to_string(),
+            headers: self
+                .headers
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        k.as_str().to_string(),
+                        String::from_utf8_lossy(v.as_bytes()).to_string(),
+                    )
+                })
+                .collect(),
+            body: self.body.as_ref().map(|body| match body {
+                Body::Json(v) => v.to_string(),
+                Body::Form(v) => url::form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(v.iter())
+                    .finish(),
+                Body::Raw(v) => v.to_string(),
+            }),
+        }
+    }
+}
+
+async fn fetch(
+    request: Request,
+    config: &fpm::Config,
+    ttl: u64,
     doc_id: &str,
     line_number: usize,
 ) -> ftd::p1::Result<serde_json::Value> {
-    let t = match _get(url).await {
+    let description = format!("{} {}", request.method, request.url);
+    let key = request.cache_key();
+    let cached = match fpm::http_cache::get(config, &key, ttl) {
         Ok(v) => v,
-        Err(e) => {
-            return ftd::e2(
-                format!("failed to fetch data: {:?}", e),
-                doc_id,
-                line_number,
-            )
+        Err(e) => return ftd::e2(e.to_string(), doc_id, line_number),
+    };
+
+    let t = match cached {
+        Some(t) => t,
+        None => {
+            let (status, t) = match _fetch(request).await {
+                Ok(v) => v,
+                Err(e) => {
+                    return ftd::e2(
+                        format!("failed to fetch data from {}: {:?}", description, e),
+                        doc_id,
+                        line_number,
+                    )
+                }
+            };
+
+            if !status.is_success() {
+                return ftd::e2(
+                    format!(
+                        "{} failed with status {}: {}",
+                        description,
+                        status,
+                        t.chars().take(200).collect::<String>()
+                    ),
+                    doc_id,
+                    line_number,
+                );
+            }
+
+            if let Err(e) = fpm::http_cache::put(config, &key, ttl, t.as_str()) {
+                return ftd::e2(
+                    format!("failed to store response of {}: {}", description, e),
+                    doc_id,
+                    line_number,
+                );
+            }
+            t
         }
     };
 
@@ -78,14 +347,255 @@ async fn get(
     }
 }
 
-async fn _get(url: url::Url) -> reqwest::Result<String> {
+async fn _fetch(request: Request) -> reqwest::Result<(reqwest::StatusCode, String)> {
     let mut headers = reqwest::header::HeaderMap::new();
     headers.insert(
         reqwest::header::USER_AGENT,
         reqwest::header::HeaderValue::from_static("fpm"),
     );
-    let c = reqwest::Client::builder()
-        .default_headers(headers)
-        .build()?;
-    c.get(url.to_string().as_str()).send()?.text()
+    let mut c = reqwest::Client::builder().default_headers(headers);
+    if let Some(timeout) = request.timeout {
+        c = c.timeout(timeout);
+    }
+    let c = c.build()?;
+    let mut r = c
+        .request(request.method, request.url.to_string().as_str())
+        .headers(request.headers);
+    r = match request.body {
+        Some(Body::Json(v)) => r.json(&v),
+        Some(Body::Form(v)) => r.form(&v),
+        Some(Body::Raw(v)) => r.body(v),
+        None => r,
+    };
+    let mut response = r.send()?;
+    Ok((response.status(), response.text()?))
+}
+
+#[cfg(test)]
+mod test {
+    fn request(source: &str) -> ftd::p1::Result<(super::Request, u64)> {
+        request_in(source, &fpm::HttpCacheMode::Default)
+    }
+
+    fn request_in(
+        source: &str,
+        mode: &fpm::HttpCacheMode,
+    ) -> ftd::p1::Result<(super::Request, u64)> {
+        let sections = ftd::p1::parse(source, "foo").unwrap();
+        super::request(&sections[0], "foo", mode)
+    }
+
+    #[test]
+    fn get() {
+        let (request, ttl) = request(indoc::indoc! {"
+            -- string name:
+            $processor$: http
+            url: https://api.example.com/repos
+            q: fpm
+            count: 10
+            path: /items
+        "})
+        .unwrap();
+        assert_eq!(request.method, reqwest::Method::GET);
+        assert_eq!(
+            request.url.as_str(),
+            "https://api.example.com/repos?q=fpm&count=10"
+        );
+        assert!(request.body.is_none());
+        assert!(request.timeout.is_none());
+        assert!(request.headers.is_empty());
+        assert_eq!(ttl, fpm::http_cache::DEFAULT_TTL);
+    }
+
+    #[test]
+    fn body_type() {
+        let (request, _) = request(indoc::indoc! {"
+            -- string name:
+            $processor$: http
+            url: https://api.example.com/repos
+            method: POST
+            name: fpm
+            count: 10
+            draft: false
+        "})
+        .unwrap();
+        assert_eq!(request.method, reqwest::Method::POST);
+        assert_eq!(request.url.as_str(), "https://api.example.com/repos");
+        match request.body {
+            Some(super::Body::Json(v)) => assert_eq!(
+                v,
+                serde_json::json!({"name": "fpm", "count": 10, "draft": false})
+            ),
+            v => panic!("expected json body, found: {:?}", v),
+        }
+
+        let (request, _) = request(indoc::indoc! {"
+            -- string name:
+            $processor$: http
+            url: https://api.example.com/repos
+            method: put
+            body-type: form
+            name: fpm
+            count: 10
+        "})
+        .unwrap();
+        assert_eq!(request.method, reqwest::Method::PUT);
+        match request.body {
+            Some(super::Body::Form(v)) => assert_eq!(
+                v,
+                vec![
+                    ("name".to_string(), "fpm".to_string()),
+                    ("count".to_string(), "10".to_string())
+                ]
+            ),
+            v => panic!("expected form body, found: {:?}", v),
+        }
+
+        let (request, _) = request(indoc::indoc! {r#"
+            -- string name:
+            $processor$: http
+            url: https://api.example.com/repos
+            method: post
+
+            {"name": "fpm"}
+        "#})
+        .unwrap();
+        assert_eq!(
+            request.headers.get(reqwest::header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        match request.body {
+            Some(super::Body::Raw(v)) => assert_eq!(v, r#"{"name": "fpm"}"#),
+            v => panic!("expected raw body, found: {:?}", v),
+        }
+    }
+
+    #[test]
+    fn timeout_and_cache_ttl() {
+        let (request, ttl) = request(indoc::indoc! {"
+            -- string name:
+            $processor$: http
+            url: https://api.example.com/repos
+            timeout: 10
+            cache-ttl: 0
+        "})
+        .unwrap();
+        assert_eq!(request.timeout, Some(std::time::Duration::from_secs(10)));
+        assert_eq!(ttl, 0);
+        assert_eq!(request.url.as_str(), "https://api.example.com/repos");
+    }
+
+    #[test]
+    fn headers() {
+        std::env::set_var("FPM_HTTP_TEST_TOKEN", "secret");
+        let (request, _) = request(indoc::indoc! {"
+            -- string name:
+            $processor$: http
+            url: https://api.example.com/repos
+            bearer-token-env: FPM_HTTP_TEST_TOKEN
+            header-Accept: application/json
+            header-X-Request-Id: 42
+        "})
+        .unwrap();
+        assert_eq!(
+            request.headers.get(reqwest::header::AUTHORIZATION).unwrap(),
+            "Bearer secret"
+        );
+        assert_eq!(
+            request.headers.get(reqwest::header::ACCEPT).unwrap(),
+            "application/json"
+        );
+        assert_eq!(request.headers.get("x-request-id").unwrap(), "42");
+        // headers are not sent as query string params
+        assert_eq!(request.url.as_str(), "https://api.example.com/repos");
+    }
+
+    #[test]
+    fn errors() {
+        let invalid = |headers: &str| {
+            request(format!("-- string name:\n$processor$: http\n{}\n", headers).as_str()).is_err()
+        };
+        assert!(invalid("method: delete\nurl: https://api.example.com"));
+        assert!(invalid("method: get"));
+        assert!(invalid("url: api.example.com"));
+        assert!(invalid("url: https://api.example.com\nbody-type: xml"));
+        assert!(invalid("url: https://api.example.com\ntimeout: 0"));
+        assert!(invalid("url: https://api.example.com\ncache-ttl: -1"));
+        assert!(invalid(
+            "url: https://api.example.com\nbearer-token-env: FPM_HTTP_TEST_UNSET_TOKEN"
+        ));
+        assert!(invalid("url: https://api.example.com\nheader-Bad Name: 1"));
+        // the token is not needed to replay a fixture
+        let (request, _) = request_in(
+            "-- string name:\n$processor$: http\nurl: https://api.example.com\n\
+             bearer-token-env: FPM_HTTP_TEST_UNSET_TOKEN\n",
+            &fpm::HttpCacheMode::Replay,
+        )
+        .unwrap();
+        assert!(request.headers.is_empty());
+    }
+
+    #[test]
+    fn record_and_replay() {
+        use std::io::{Read, Write};
+
+        // the stand-in server answers only one request, replay must not hit the network
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/repos", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4096];
+            assert!(stream.read(&mut buf).unwrap() > 0);
+            let body = r#"{"name": "fpm"}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+
+        let root = std::env::temp_dir().join(format!("fpm-http-{}", std::process::id()));
+        let mut config = fpm::Config::for_test(camino::Utf8PathBuf::from_path_buf(root).unwrap());
+        let source = format!(
+            "-- string name:\n$processor$: http\nurl: {}\n\
+             bearer-token-env: FPM_HTTP_TEST_RECORD_TOKEN\n",
+            url
+        );
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let fetch = |config: &fpm::Config| {
+            let (request, ttl) = request_in(source.as_str(), &config.http_cache)?;
+            runtime.block_on(super::fetch(request, config, ttl, "foo", 1))
+        };
+
+        std::env::set_var("FPM_HTTP_TEST_RECORD_TOKEN", "secret");
+        config.http_cache = fpm::HttpCacheMode::Record;
+        assert_eq!(fetch(&config).unwrap(), serde_json::json!({"name": "fpm"}));
+        server.join().unwrap();
+        assert_eq!(
+            std::fs::read_dir(config.root.join("FPM").join("http"))
+                .unwrap()
+                .count(),
+            1
+        );
+
+        let fixture = std::fs::read_dir(config.root.join("FPM").join("http"))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        assert!(!std::fs::read_to_string(fixture).unwrap().contains("secret"));
+
+        // a different token, or none at all, replays the same fixture
+        config.http_cache = fpm::HttpCacheMode::Replay;
+        std::env::set_var("FPM_HTTP_TEST_RECORD_TOKEN", "other");
+        assert_eq!(fetch(&config).unwrap(), serde_json::json!({"name": "fpm"}));
+        std::env::remove_var("FPM_HTTP_TEST_RECORD_TOKEN");
+        assert_eq!(fetch(&config).unwrap(), serde_json::json!({"name": "fpm"}));
+
+        std::fs::remove_dir_all(&config.root).unwrap();
+    }
 }
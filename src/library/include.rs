@@ -1,3 +1,23 @@
+/// `$processor$: include` includes the content of a file, or parts of it:
+///
+/// ```ftd
+/// -- ftd.code:
+/// $processor$: include
+/// path: src/main.rs:MAIN,20:25
+/// dedent: true
+/// ```
+///
+/// `path` is relative to the package root, and can not be outside of it. Files of dependencies
+/// are included using `-/<package>/path`, and remote files using their `http(s)://` url, these
+/// are cached like the responses of `http` processor.
+///
+/// After the path, a comma separated list of anchors and line ranges, `4`, `2:5`, `2:` or `:5`,
+/// selects the lines to include. `dedent: true` removes the indentation common to all lines.
+/// `line-numbers: true` also sets `line-number` to the number of the first included line.
+///
+/// When `highlight-code` is enabled for the package, `highlighted` is set to the syntax
+/// highlighted html of the code, with line numbers if `line-numbers` is set, and the lines in
+/// `highlight-lines`, like `2-4,7`, emphasised. `highlight` header overrides the package setting.
 pub fn processor(
     section: &ftd::p1::Section,
     doc: &ftd::p2::TDoc,
@@ -24,9 +44,43 @@ pub fn processor(
             }
         }
     };
+    let dedent =
+        section
+            .header
+            .bool_with_default(doc.name, section.line_number, "dedent", false)?;
+    let line_numbers =
+        section
+            .header
+            .bool_with_default(doc.name, section.line_number, "line-numbers", false)?;
+    let highlight = section.header.bool_with_default(
+        doc.name,
+        section.line_number,
+        "highlight",
+        config.package.highlight_code,
+    )?;
+    let highlighted_lines =
+        match section
+            .header
+            .str_optional(doc.name, section.line_number, "highlight-lines")?
+        {
+            Some(v) => match fpm::highlight::Highlight::parse_lines(v) {
+                Ok(v) => v,
+                Err(e) => return ftd::e2(e, doc.name, section.line_number),
+            },
+            None => vec![],
+        };
     let mut v: std::collections::BTreeMap<String, ftd::PropertyValue> = Default::default();
 
-    let code_item = IncludeCode::parse(doc_path, config).unwrap();
+    let code_item = match IncludeCode::parse(doc_path, config, dedent) {
+        Ok(v) => v,
+        Err(e) => {
+            return ftd::e2(
+                format!("failed to include `{}`: {}", doc_path, e),
+                doc.name,
+                section.line_number,
+            )
+        }
+    };
 
     v.insert(
         "$body$".to_string(),
@@ -46,6 +100,40 @@ pub fn processor(
             },
         },
     );
+    if highlight {
+        let html = match fpm::highlight::to_html(
+            config,
+            code_item.code.as_str(),
+            code_item.extension.as_str(),
+            &fpm::highlight::Highlight {
+                line_numbers,
+                start_line: code_item.line_number,
+                highlighted_lines,
+            },
+        ) {
+            Ok(v) => v,
+            Err(e) => return ftd::e2(e.to_string(), doc.name, section.line_number),
+        };
+        v.insert(
+            "highlighted".to_string(),
+            ftd::PropertyValue::Value {
+                value: ftd::Value::String {
+                    text: html,
+                    source: ftd::TextSource::Header,
+                },
+            },
+        );
+    }
+    if line_numbers {
+        v.insert(
+            "line-number".to_string(),
+            ftd::PropertyValue::Value {
+                value: ftd::Value::Integer {
+                    value: code_item.line_number as i64,
+                },
+            },
+        );
+    }
     Ok(ftd::Value::Object { values: v })
 }
 
@@ -53,6 +141,10 @@ pub fn processor(
 pub struct IncludeCode {
     pub extension: String,
     pub body: String,
+    /// `code` is the included content, `body` is the same escaped for ftd
+    pub code: String,
+    /// `line_number` is the number of the first included line in the file
+    pub line_number: usize,
 }
 
 #[derive(PartialEq, Debug, Clone, serde::Serialize)]
@@ -74,58 +166,66 @@ enum LineRange {
 #[derive(PartialEq, Debug, Clone, serde::Serialize)]
 pub struct IncludeDocument {
     path: String,
-    roa: RangeOrAnchor,
+    roa: Vec<RangeOrAnchor>,
 }
 
 impl IncludeDocument {
     pub fn parse(s: &str) -> Result<Self, ParseError> {
-        match s.split_once(":") {
+        // the `:` of `https://` does not separate the path from the ranges
+        let skip = s.find("://").map(|i| i + 3).unwrap_or(0);
+        match s[skip..].split_once(":") {
             None => {
                 // No `:` found. Include full file
                 Ok(IncludeDocument {
                     path: s.to_string(),
-                    roa: RangeOrAnchor::Range(LineRange::RangeFull),
+                    roa: vec![RangeOrAnchor::Range(LineRange::RangeFull)],
                 })
             }
-            Some((doc_path, range_or_anchor)) => {
-                match range_or_anchor.split_once(":") {
-                    None => {
-                        // Can either be an anchor or an individual line
-                        let test = range_or_anchor.parse::<i32>();
-                        match test {
-                            Ok(ok) => Ok(IncludeDocument {
-                                path: doc_path.to_string(),
-                                roa: RangeOrAnchor::Range(LineRange::SingleLine(ok)),
-                            }),
-                            Err(_e) => Ok(IncludeDocument {
-                                path: doc_path.to_string(),
-                                roa: RangeOrAnchor::Anchor(range_or_anchor.to_string()),
-                            }),
-                        }
-                    }
-                    Some((start, end)) => match (start, end) {
-                        (k, "") => Ok(IncludeDocument {
-                            path: doc_path.to_string(),
-                            roa: RangeOrAnchor::Range(LineRange::RangeFrom(k.parse::<i32>()?)),
-                        }),
-                        ("", l) => Ok(IncludeDocument {
-                            path: doc_path.to_string(),
-                            roa: RangeOrAnchor::Range(LineRange::RangeTo(l.parse::<i32>()?)),
-                        }),
-                        (k, l) => {
-                            let start = k.parse::<i32>()?;
-                            let end = l.parse::<i32>()?;
-                            if end < start {
-                                panic!("End can't be higher than start")
-                            }
-                            Ok(IncludeDocument {
-                                path: doc_path.to_string(),
-                                roa: RangeOrAnchor::Range(LineRange::Range((start, end))),
-                            })
-                        }
-                    },
+            Some((doc_path, range_or_anchor)) => Ok(IncludeDocument {
+                path: s[..skip + doc_path.len()].to_string(),
+                roa: range_or_anchor
+                    .split(',')
+                    .map(|v| RangeOrAnchor::parse(v.trim()))
+                    .collect::<Result<Vec<RangeOrAnchor>, ParseError>>()?,
+            }),
+        }
+    }
+}
+
+impl RangeOrAnchor {
+    fn parse(s: &str) -> Result<Self, ParseError> {
+        let line = |v: &str| -> Result<i32, ParseError> {
+            match v.parse::<i32>()? {
+                v if v < 1 => Err(ParseError::InvalidRange(format!(
+                    "line numbers start from 1, found: {}",
+                    v
+                ))),
+                v => Ok(v),
+            }
+        };
+        match s.split_once(":") {
+            None => {
+                // Can either be an anchor or an individual line
+                match s.parse::<i32>() {
+                    Ok(_) => Ok(RangeOrAnchor::Range(LineRange::SingleLine(line(s)?))),
+                    Err(_e) => Ok(RangeOrAnchor::Anchor(s.to_string())),
                 }
             }
+            Some((start, end)) => match (start, end) {
+                (k, "") => Ok(RangeOrAnchor::Range(LineRange::RangeFrom(line(k)?))),
+                ("", l) => Ok(RangeOrAnchor::Range(LineRange::RangeTo(line(l)?))),
+                (k, l) => {
+                    let start = line(k)?;
+                    let end = line(l)?;
+                    if end < start {
+                        return Err(ParseError::InvalidRange(format!(
+                            "end can't be before start: {}",
+                            s
+                        )));
+                    }
+                    Ok(RangeOrAnchor::Range(LineRange::Range((start, end))))
+                }
+            },
         }
     }
 }
@@ -178,55 +278,191 @@ pub fn sanitize_anchored_lines(s: &str) -> String {
 }
 
 impl IncludeCode {
-    pub fn parse(s: &str, config: &fpm::Config) -> Result<Self, ParseError> {
+    pub fn parse(s: &str, config: &fpm::Config, should_dedent: bool) -> Result<Self, ParseError> {
         let doc = IncludeDocument::parse(s)?;
         let extension = match &doc.path.rsplit_once(".") {
             Some((_, ex)) => ex,
             None => "txt",
         };
-        let file_path = config.root.join(
-            doc.path
-                .replace('/', std::path::MAIN_SEPARATOR.to_string().as_str()),
-        );
-        let file_content = std::fs::read_to_string(file_path)?
-            .lines()
-            .into_iter()
-            .fold(String::new(), |accumulator, instance| {
-                let should_escape = instance.starts_with('$') || instance.starts_with('/');
-                format!(
-                    "{}{}{}\n",
-                    accumulator.as_str(),
-                    if should_escape { "\\" } else { "" },
-                    instance
-                )
-            });
-        let output = match (file_content, doc.roa) {
-            (fc, RangeOrAnchor::Anchor(anchor_name)) => take_anchored_lines(&fc, &anchor_name),
-            (fc, RangeOrAnchor::Range(LineRange::RangeFull)) => fc,
-            (fc, RangeOrAnchor::Range(r)) => {
-                let lines = fc.lines();
-                let len = lines.clone().count();
-                let (start, end) = match r {
-                    LineRange::Range((s, e)) => (s - 1, e),
-                    LineRange::RangeFrom(s) => (s - 1, len as i32),
-                    LineRange::RangeTo(e) => (0, e),
-                    LineRange::SingleLine(e) => (e - 1, e),
-                    LineRange::RangeFull => (0, len as i32),
-                };
-                lines
-                    .skip(start as usize)
-                    .take(std::cmp::min(end as usize, len))
-                    .collect::<Vec<&str>>()
-                    .join("\n")
-            }
-        };
+        let (output, line_number) = doc.read(config, should_dedent)?;
         Ok(IncludeCode {
             extension: extension.to_string(),
-            body: sanitize_anchored_lines(output.as_str()),
+            body: escape(output.as_str()),
+            code: output,
+            line_number,
         })
     }
 }
 
+impl IncludeDocument {
+    pub(crate) fn path(&self) -> &str {
+        self.path.as_str()
+    }
+
+    /// `read()` returns the selected lines of the document, without the anchor lines, and the
+    /// number of the first of them.
+    pub(crate) fn read(
+        &self,
+        config: &fpm::Config,
+        should_dedent: bool,
+    ) -> Result<(String, usize), ParseError> {
+        let file_content = read(self.path.as_str(), config)?;
+        let lines = file_content.lines().collect::<Vec<&str>>();
+
+        let mut output = vec![];
+        let mut line_number = None;
+        for roa in self.roa.iter() {
+            match roa {
+                RangeOrAnchor::Anchor(anchor_name) => {
+                    let start = match lines.iter().position(|l| {
+                        ANCHOR_START
+                            .captures(l)
+                            .map(|cap| &cap["anchor_name"] == anchor_name)
+                            .unwrap_or(false)
+                    }) {
+                        Some(v) => v,
+                        None => {
+                            return Err(ParseError::InvalidRange(format!(
+                                "anchor `{}` not found",
+                                anchor_name
+                            )))
+                        }
+                    };
+                    line_number.get_or_insert(start + 2);
+                    output.push(take_anchored_lines(&file_content, anchor_name));
+                }
+                RangeOrAnchor::Range(r) => {
+                    let len = lines.len();
+                    let (start, end) = match *r {
+                        LineRange::Range((s, e)) => (s - 1, e),
+                        LineRange::RangeFrom(s) => (s - 1, len as i32),
+                        LineRange::RangeTo(e) => (0, e),
+                        LineRange::SingleLine(e) => (e - 1, e),
+                        LineRange::RangeFull => (0, len as i32),
+                    };
+                    let (start, end) = (start as usize, std::cmp::min(end as usize, len));
+                    if start >= len && len > 0 {
+                        return Err(ParseError::InvalidRange(format!(
+                            "line {} is after the end of the file, it has {} lines",
+                            start + 1,
+                            len
+                        )));
+                    }
+                    line_number.get_or_insert(start + 1);
+                    output.push(lines[start.min(end)..end].join("\n"));
+                }
+            }
+        }
+
+        let output = sanitize_anchored_lines(output.join("\n").as_str());
+        let output = if should_dedent {
+            dedent(output.as_str())
+        } else {
+            output
+        };
+        Ok((output, line_number.unwrap_or(1)))
+    }
+}
+
+/// `escape()` escapes the lines which ftd would otherwise read as comments or references.
+pub(crate) fn escape(s: &str) -> String {
+    s.lines()
+        .map(|l| {
+            let should_escape = l.starts_with('$') || l.starts_with('/');
+            format!("{}{}", if should_escape { "\\" } else { "" }, l)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// `dedent()` removes the leading whitespace common to all non empty lines.
+fn dedent(s: &str) -> String {
+    let indent = s
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    s.lines()
+        .map(|l| l.get(indent..).unwrap_or_else(|| l.trim_start()))
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+/// `read()` reads the file at `path` in the package, in a dependency for `-/<package>/path`, or
+/// fetches it for `http(s)://` urls. Like the `file` of `$processor$: read-data`, `path` can not
+/// contain `..`, and the file, after following symlinks, must be inside its package.
+fn read(path: &str, config: &fpm::Config) -> Result<String, ParseError> {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        return fetch(path, config);
+    }
+
+    let (root, file) = match path.strip_prefix("-/") {
+        Some(rest) => {
+            match config
+                .package
+                .get_flattened_dependencies()
+                .into_iter()
+                .find_map(|dep| {
+                    rest.strip_prefix(format!("{}/", dep.package.name).as_str())
+                        .map(|v| (config.get_root_for_package(&dep.package), v))
+                }) {
+                Some(v) => v,
+                None => return Err(ParseError::PackageNotFound(rest.to_string())),
+            }
+        }
+        None => (config.root.clone(), path),
+    };
+    let file = file.trim_start_matches('/');
+    let file_path = root.join(file);
+    if camino::Utf8Path::new(file).components().any(|c| {
+        !matches!(
+            c,
+            camino::Utf8Component::Normal(_) | camino::Utf8Component::CurDir
+        )
+    }) || (file_path.exists() && fpm::library::read_data::within(&root, file).is_none())
+    {
+        return Err(ParseError::OutsidePackage(path.to_string()));
+    }
+    let file_path = file_path
+        .as_str()
+        .replace('/', std::path::MAIN_SEPARATOR.to_string().as_str());
+    std::fs::read_to_string(file_path.as_str())
+        .map_err(|e| ParseError::ReadError(file_path.to_string(), e))
+}
+
+fn fetch(url: &str, config: &fpm::Config) -> Result<String, ParseError> {
+    let key = fpm::http_cache::Key {
+        method: "GET".to_string(),
+        url: url.to_string(),
+        headers: vec![],
+        body: None,
+    };
+    let ttl = fpm::http_cache::DEFAULT_TTL;
+    if let Some(v) = fpm::http_cache::get(config, &key, ttl)
+        .map_err(|e| ParseError::FetchError(e.to_string()))?
+    {
+        return Ok(v);
+    }
+
+    let (status, body) = _fetch(url).map_err(|e| ParseError::FetchError(e.to_string()))?;
+    if !status.is_success() {
+        return Err(ParseError::FetchError(format!(
+            "{} failed with status {}",
+            url, status
+        )));
+    }
+
+    fpm::http_cache::put(config, &key, ttl, body.as_str())
+        .map_err(|e| ParseError::FetchError(e.to_string()))?;
+    Ok(body)
+}
+
+fn _fetch(url: &str) -> reqwest::Result<(reqwest::StatusCode, String)> {
+    let mut response = reqwest::get(url)?;
+    Ok((response.status(), response.text()?))
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ParseError {
     #[error("Integer Parsing Error: {}", _0)]
@@ -235,6 +471,21 @@ pub enum ParseError {
     #[error("File Not Found Error: {}", _0)]
     FileNotFound(#[from] std::io::Error),
 
+    #[error("Can not read `{}`: {}", _0, _1)]
+    ReadError(String, std::io::Error),
+
+    #[error("Package `{}` is not a dependency", _0)]
+    PackageNotFound(String),
+
+    #[error("`{}` is outside of the package", _0)]
+    OutsidePackage(String),
+
+    #[error("Fetch Error: {}", _0)]
+    FetchError(String),
+
+    #[error("Invalid Range: {}", _0)]
+    InvalidRange(String),
+
     #[error("{}", _0)]
     FTDError(#[from] ftd::p1::Error),
 }
@@ -263,7 +514,7 @@ mod test {
             &indoc!("code.rs"),
             super::IncludeDocument {
                 path: "code.rs".to_string(),
-                roa: super::RangeOrAnchor::Range(super::LineRange::RangeFull)
+                roa: vec![super::RangeOrAnchor::Range(super::LineRange::RangeFull)]
             }
         );
         // Line Import
@@ -271,7 +522,7 @@ mod test {
             &indoc!("code.rs:4"),
             super::IncludeDocument {
                 path: "code.rs".to_string(),
-                roa: super::RangeOrAnchor::Range(super::LineRange::SingleLine(4))
+                roa: vec![super::RangeOrAnchor::Range(super::LineRange::SingleLine(4))]
             }
         );
         // Anchor Import
@@ -279,7 +530,7 @@ mod test {
             &indoc!("code.rs:MAIN_CODE"),
             super::IncludeDocument {
                 path: "code.rs".to_string(),
-                roa: super::RangeOrAnchor::Anchor("MAIN_CODE".to_string())
+                roa: vec![super::RangeOrAnchor::Anchor("MAIN_CODE".to_string())]
             }
         );
         // Range From Import
@@ -287,7 +538,7 @@ mod test {
             &indoc!("code.rs:2:"),
             super::IncludeDocument {
                 path: "code.rs".to_string(),
-                roa: super::RangeOrAnchor::Range(super::LineRange::RangeFrom(2))
+                roa: vec![super::RangeOrAnchor::Range(super::LineRange::RangeFrom(2))]
             }
         );
         // Range To Import
@@ -295,8 +546,167 @@ mod test {
             &indoc!("code.rs::2"),
             super::IncludeDocument {
                 path: "code.rs".to_string(),
-                roa: super::RangeOrAnchor::Range(super::LineRange::RangeTo(2))
+                roa: vec![super::RangeOrAnchor::Range(super::LineRange::RangeTo(2))]
+            }
+        );
+        // Multiple Ranges and Anchors Import
+        p!(
+            &indoc!("-/fifthtry.github.io/code.rs:1:3,MAIN_CODE"),
+            super::IncludeDocument {
+                path: "-/fifthtry.github.io/code.rs".to_string(),
+                roa: vec![
+                    super::RangeOrAnchor::Range(super::LineRange::Range((1, 3))),
+                    super::RangeOrAnchor::Anchor("MAIN_CODE".to_string())
+                ]
+            }
+        );
+        // Remote File Import
+        p!(
+            &indoc!("https://example.com/code.rs:4"),
+            super::IncludeDocument {
+                path: "https://example.com/code.rs".to_string(),
+                roa: vec![super::RangeOrAnchor::Range(super::LineRange::SingleLine(4))]
             }
         );
+        // Bad Ranges
+        assert!(super::IncludeDocument::parse("code.rs:5:2").is_err());
+        assert!(super::IncludeDocument::parse("code.rs:0").is_err());
+    }
+
+    fn config(name: &str) -> fpm::Config {
+        let root = camino::Utf8PathBuf::from_path_buf(std::env::temp_dir().join(format!(
+            "fpm-include-{}-{}",
+            name,
+            std::process::id()
+        )))
+        .unwrap();
+        let mut config = fpm::Config::for_test(root.join("package"));
+        config.package.dependencies.push(fpm::Dependency {
+            package: fpm::Package::new("fifthtry.github.io"),
+            version: None,
+            notes: None,
+            alias: None,
+            implements: vec![],
+        });
+        let code = "fn main() {\n    // ANCHOR: MAIN\n    run();\n    // ANCHOR_END: MAIN\n}\n";
+        std::fs::create_dir_all(config.root.join("src")).unwrap();
+        std::fs::write(config.root.join("src/main.rs"), code).unwrap();
+        let dependency = config.packages_root.join("fifthtry.github.io");
+        std::fs::create_dir_all(&dependency).unwrap();
+        std::fs::write(dependency.join("code.rs"), "fn dependency() {}\n").unwrap();
+        std::fs::write(root.join("secret.rs"), "fn secret() {}\n").unwrap();
+        config
+    }
+
+    #[test]
+    fn read() {
+        let config = config("read");
+        let read = |s: &str| {
+            super::IncludeDocument::parse(s)
+                .unwrap()
+                .read(&config, false)
+        };
+
+        assert_eq!(
+            read("src/main.rs:MAIN").unwrap(),
+            ("    run();".to_string(), 3)
+        );
+        assert_eq!(
+            read("src/main.rs:MAIN")
+                .map(|(v, _)| super::dedent(v.as_str()))
+                .unwrap(),
+            "run();"
+        );
+        assert_eq!(
+            read("/src/main.rs:4:").unwrap(),
+            ("}".to_string(), 4),
+            "anchor lines are left out"
+        );
+        assert_eq!(
+            read("src/main.rs:1,5").unwrap(),
+            ("fn main() {\n}".to_string(), 1)
+        );
+        assert_eq!(
+            read("-/fifthtry.github.io/code.rs").unwrap(),
+            ("fn dependency() {}".to_string(), 1)
+        );
+
+        assert!(matches!(
+            read("src/missing.rs"),
+            Err(super::ParseError::ReadError(..))
+        ));
+        assert!(matches!(
+            read("-/example.com/code.rs"),
+            Err(super::ParseError::PackageNotFound(..))
+        ));
+        assert!(matches!(
+            read("src/main.rs:9"),
+            Err(super::ParseError::InvalidRange(..))
+        ));
+        assert!(matches!(
+            read("src/main.rs:MISSING"),
+            Err(super::ParseError::InvalidRange(..))
+        ));
+        assert!(matches!(
+            read("../secret.rs"),
+            Err(super::ParseError::OutsidePackage(..))
+        ));
+        assert!(matches!(
+            read("-/fifthtry.github.io/../../../secret.rs"),
+            Err(super::ParseError::OutsidePackage(..))
+        ));
+
+        std::fs::remove_dir_all(config.root.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn line_number() {
+        let config = config("line-number");
+        let code = super::IncludeCode::parse("src/main.rs:2:3", &config, true).unwrap();
+        assert_eq!(code.line_number, 2);
+        assert_eq!(code.code, "run();");
+        assert_eq!(code.extension, "rs");
+        std::fs::remove_dir_all(config.root.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn fetch() {
+        let root = camino::Utf8PathBuf::from_path_buf(
+            std::env::temp_dir().join(format!("fpm-include-fetch-{}", std::process::id())),
+        )
+        .unwrap();
+        let mut config = fpm::Config::for_test(root.clone());
+        let url = "https://fpm-test.invalid/code.rs";
+        let key = fpm::http_cache::Key {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: vec![],
+            body: None,
+        };
+        // the url is served from the cache, without network access
+        fpm::http_cache::put(&config, &key, fpm::http_cache::DEFAULT_TTL, "a\nb\nc").unwrap();
+        let read = |s: &str, config: &fpm::Config| {
+            super::IncludeDocument::parse(s)
+                .unwrap()
+                .read(config, false)
+        };
+        assert_eq!(
+            read("https://fpm-test.invalid/code.rs:2", &config).unwrap(),
+            ("b".to_string(), 2)
+        );
+        config.http_cache = fpm::HttpCacheMode::Replay;
+        assert!(matches!(
+            read(url, &config),
+            Err(super::ParseError::FetchError(..))
+        ));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn dedent() {
+        assert_eq!(
+            super::dedent("    fn main() {\n\n        println!();\n    }"),
+            "fn main() {\n\n    println!();\n}"
+        );
     }
 }
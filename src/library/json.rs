@@ -0,0 +1,258 @@
+/// `transform()` reshapes the JSON `value` fetched by `http` or `get-data` processors, before it
+/// is converted to the ftd variable, using these headers of the section:
+///
+/// - `path`: selects a subtree. It is either a JSON Pointer, `/data/items`, or a JSONPath,
+///   `$.data.items[0]`. `[*]` selects every element of a list, `$.items[*].author`.
+/// - `map-<field>`: sets `<field>` to the value at the given JSON Pointer, relative to the object.
+///   `map-author: /author/login` flattens a nested value.
+/// - `rename-<field>`: renames `<field>` of the object, `rename-full_name: name`.
+///
+/// If the selected value is a list, `map-` and `rename-` headers are applied on each object in
+/// it. A `path` which is not found, or is not valid, and a `map-` value which is not a JSON
+/// Pointer are `fpm::Error::UsageError`s.
+pub(crate) fn transform(
+    section: &ftd::p1::Section,
+    doc_id: &str,
+    value: serde_json::Value,
+) -> fpm::Result<serde_json::Value> {
+    let mut value = match section
+        .header
+        .string_optional(doc_id, section.line_number, "path")?
+    {
+        Some(path) => match select(&value, path.trim()) {
+            Ok(Some(v)) => v,
+            Ok(None) => return fpm::usage_error(format!("`{}` not found in the data", path)),
+            Err(e) => return fpm::usage_error(e),
+        },
+        None => value,
+    };
+
+    let mut maps = vec![];
+    let mut renames = vec![];
+    for (_, k, v) in section.header.0.iter() {
+        if let Some(field) = k.strip_prefix("map-") {
+            if !v.trim().starts_with('/') {
+                return fpm::usage_error(format!(
+                    "`{}` must be a JSON Pointer, eg `/author/login`, found: {}",
+                    k, v
+                ));
+            }
+            maps.push((field.to_string(), v.trim().to_string()));
+        } else if let Some(field) = k.strip_prefix("rename-") {
+            renames.push((field.to_string(), v.trim().to_string()));
+        }
+    }
+    if maps.is_empty() && renames.is_empty() {
+        return Ok(value);
+    }
+
+    let apply = |object: &mut serde_json::Value| {
+        let mapped = maps
+            .iter()
+            .map(|(field, pointer)| {
+                (
+                    field.to_string(),
+                    object
+                        .pointer(pointer.as_str())
+                        .cloned()
+                        .unwrap_or(serde_json::Value::Null),
+                )
+            })
+            .collect::<Vec<(String, serde_json::Value)>>();
+        if let serde_json::Value::Object(object) = object {
+            object.extend(mapped);
+            for (from, to) in renames.iter() {
+                if let Some(v) = object.remove(from.as_str()) {
+                    object.insert(to.to_string(), v);
+                }
+            }
+        }
+    };
+    match value {
+        serde_json::Value::Array(ref mut list) => list.iter_mut().for_each(apply),
+        ref mut object => apply(object),
+    }
+    Ok(value)
+}
+
+/// `is_transform_header()` tells if header `k` is used by `transform()`.
+pub(crate) fn is_transform_header(k: &str) -> bool {
+    k == "path" || k.starts_with("map-") || k.starts_with("rename-")
+}
+
+fn select(value: &serde_json::Value, path: &str) -> Result<Option<serde_json::Value>, String> {
+    if path.is_empty() || path.starts_with('/') {
+        return Ok(value.pointer(path).cloned());
+    }
+    let rest = match path.strip_prefix('$') {
+        Some(rest) => rest,
+        None => {
+            return Err(format!(
+                "path must be a JSON Pointer, `/a/b`, or a JSONPath, `$.a.b`, found: {}",
+                path
+            ))
+        }
+    };
+    return Ok(select_(value, parse(rest)?.as_slice()));
+
+    #[derive(Debug)]
+    enum Segment {
+        Key(String),
+        Index(usize),
+        All,
+    }
+
+    fn parse(mut path: &str) -> Result<Vec<Segment>, String> {
+        let mut segments = vec![];
+        while !path.is_empty() {
+            if let Some(rest) = path.strip_prefix('.') {
+                let end = rest.find(|c| c == '.' || c == '[').unwrap_or(rest.len());
+                if end == 0 {
+                    return Err(format!("empty key in JSONPath at `{}`", path));
+                }
+                segments.push(Segment::Key(rest[..end].to_string()));
+                path = &rest[end..];
+            } else if let Some(rest) = path.strip_prefix('[') {
+                let end = match rest.find(']') {
+                    Some(end) => end,
+                    None => return Err(format!("missing `]` in JSONPath at `{}`", path)),
+                };
+                let inner = rest[..end].trim();
+                segments.push(if inner == "*" {
+                    Segment::All
+                } else if let Ok(index) = inner.parse::<usize>() {
+                    Segment::Index(index)
+                } else {
+                    Segment::Key(inner.trim_matches(|c| c == '\'' || c == '"').to_string())
+                });
+                path = &rest[end + 1..];
+            } else {
+                return Err(format!("expected `.` or `[` in JSONPath at `{}`", path));
+            }
+        }
+        Ok(segments)
+    }
+
+    fn select_(value: &serde_json::Value, segments: &[Segment]) -> Option<serde_json::Value> {
+        let (segment, rest) = match segments.split_first() {
+            Some(v) => v,
+            None => return Some(value.clone()),
+        };
+        match segment {
+            Segment::Key(key) => value.get(key.as_str()).and_then(|v| select_(v, rest)),
+            Segment::Index(index) => value.get(index).and_then(|v| select_(v, rest)),
+            Segment::All => value.as_array().map(|list| {
+                serde_json::Value::Array(list.iter().filter_map(|v| select_(v, rest)).collect())
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn select() {
+        let value = serde_json::json!({
+            "data": {
+                "items": [
+                    {"name": "fpm", "owner": {"login": "fifthtry"}},
+                    {"name": "ftd", "owner": {"login": "amitu"}}
+                ]
+            }
+        });
+        assert_eq!(
+            super::select(&value, "/data/items/1/name"),
+            Ok(Some(serde_json::json!("ftd")))
+        );
+        assert_eq!(
+            super::select(&value, "$.data.items[0]['name']"),
+            Ok(Some(serde_json::json!("fpm")))
+        );
+        assert_eq!(
+            super::select(&value, "$.data.items[*].owner.login"),
+            Ok(Some(serde_json::json!(["fifthtry", "amitu"])))
+        );
+        assert_eq!(super::select(&value, "$.data.missing"), Ok(None));
+        assert!(super::select(&value, "data.items").is_err());
+    }
+
+    fn transform(source: &str, value: serde_json::Value) -> fpm::Result<serde_json::Value> {
+        let sections = ftd::p1::parse(source, "foo").unwrap();
+        super::transform(&sections[0], "foo", value)
+    }
+
+    #[test]
+    fn map_and_rename() {
+        let repo = serde_json::json!({
+            "full_name": "fifthtry/fpm",
+            "owner": {"login": "fifthtry"},
+            "stars": 10
+        });
+        assert_eq!(
+            transform(
+                indoc::indoc! {"
+                    -- repo-data repo:
+                    $processor$: http
+                    map-author: /owner/login
+                    map-license: /license/name
+                    rename-full_name: name
+                "},
+                repo.clone()
+            )
+            .unwrap(),
+            serde_json::json!({
+                "name": "fifthtry/fpm",
+                "owner": {"login": "fifthtry"},
+                "stars": 10,
+                "author": "fifthtry",
+                "license": null
+            })
+        );
+
+        // applied on each object of the selected list
+        assert_eq!(
+            transform(
+                indoc::indoc! {"
+                    -- repo-data list repos:
+                    $processor$: http
+                    path: /items
+                    map-author: /owner/login
+                    rename-full_name: name
+                "},
+                serde_json::json!({"items": [repo.clone(), {"full_name": "fifthtry/ftd"}]})
+            )
+            .unwrap(),
+            serde_json::json!([
+                {"name": "fifthtry/fpm", "owner": {"login": "fifthtry"}, "stars": 10,
+                 "author": "fifthtry"},
+                {"name": "fifthtry/ftd", "author": null}
+            ])
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let value = serde_json::json!({"items": []});
+        match transform(
+            "-- repo-data repo:\n$processor$: http\nmap-author: owner/login\n",
+            value.clone(),
+        ) {
+            Err(fpm::Error::UsageError { message }) => assert!(message.contains("map-author")),
+            v => panic!("expected usage error, found: {:?}", v),
+        }
+        assert!(matches!(
+            transform(
+                "-- repo-data repo:\n$processor$: http\npath: /missing\n",
+                value.clone()
+            ),
+            Err(fpm::Error::UsageError { .. })
+        ));
+        assert!(matches!(
+            transform(
+                "-- repo-data repo:\n$processor$: http\npath: items\n",
+                value
+            ),
+            Err(fpm::Error::UsageError { .. })
+        ));
+    }
+}
@@ -1,8 +1,11 @@
+mod env;
 mod fpm_dot_ftd;
 mod get_data;
 mod get_version_data;
 mod http;
-mod include;
+pub(crate) mod include;
+mod json;
+mod read_data;
 mod sitemap;
 mod sqlite;
 mod toc;
@@ -212,11 +215,13 @@ impl Library {
             .str(doc.name, section.line_number, "$processor$")?
         {
             // "toc" => fpm::library::toc::processor(section, doc),
-            "http" => fpm::library::http::processor(section, doc).await,
+            "http" => fpm::library::http::processor(section, doc, &self.config).await,
             "package-query" => fpm::library::sqlite::processor(section, doc, &self.config).await,
             "toc" => fpm::library::toc::processor(section, doc, &self.config),
             "include" => fpm::library::include::processor(section, doc, &self.config),
             "get-data" => fpm::library::get_data::processor(section, doc, &self.config),
+            "read-data" => fpm::library::read_data::processor(section, doc, &self.config),
+            "env" => fpm::library::env::processor(section, doc, &self.config),
             "sitemap" => fpm::library::sitemap::processor(section, doc, &self.config),
             "get-version-data" => {
                 fpm::library::get_version_data::processor(
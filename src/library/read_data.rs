@@ -0,0 +1,231 @@
+/// `$processor$: read-data` loads a data file and converts it to the ftd variable:
+///
+/// ```ftd
+/// -- product list products:
+/// $processor$: read-data
+/// file: data/products.csv
+/// ```
+///
+/// `file` is relative to the package root, and can not be outside of it. Files of dependencies
+/// are read by prefixing the package name, `file: fifthtry.github.io/data/changelog.yaml`.
+///
+/// JSON, YAML, TOML and CSV files are supported, the format is guessed from the extension, or can
+/// be set using `format` header. Each row of a CSV file becomes an object keyed by the column
+/// names. The data can be reshaped using `path`, `map-` and `rename-` headers, see
+/// `fpm::library::json::transform()`.
+pub fn processor(
+    section: &ftd::p1::Section,
+    doc: &ftd::p2::TDoc,
+    config: &fpm::Config,
+) -> ftd::p1::Result<ftd::Value> {
+    let file = match section
+        .header
+        .str_optional(doc.name, section.line_number, "file")?
+    {
+        Some(v) => v.trim(),
+        None => {
+            return ftd::e2(
+                "`file` is not specified".to_string(),
+                doc.name,
+                section.line_number,
+            )
+        }
+    };
+
+    let path = match resolve(file, config) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            return ftd::e2(
+                format!("`{}` not found", file),
+                doc.name,
+                section.line_number,
+            )
+        }
+        Err(e) => return ftd::e2(e, doc.name, section.line_number),
+    };
+
+    let format = match section
+        .header
+        .str_optional(doc.name, section.line_number, "format")?
+    {
+        Some(v) => v.to_lowercase(),
+        None => path.extension().unwrap_or_default().to_lowercase(),
+    };
+
+    let content = match std::fs::read_to_string(&path) {
+        Ok(v) => v,
+        Err(e) => {
+            return ftd::e2(
+                format!("failed to read `{}`: {:?}", path, e),
+                doc.name,
+                section.line_number,
+            )
+        }
+    };
+
+    let data = match parse(content.as_str(), format.as_str()) {
+        Ok(v) => v,
+        Err(e) => {
+            return ftd::e2(
+                format!("failed to parse `{}`: {}", path, e),
+                doc.name,
+                section.line_number,
+            )
+        }
+    };
+
+    let data = match fpm::library::json::transform(section, doc.name, data) {
+        Ok(v) => v,
+        Err(e) => return ftd::e2(e.to_string(), doc.name, section.line_number),
+    };
+    doc.from_json(&data, section)
+}
+
+/// `resolve()` finds `file` in the dependency whose name it starts with, else in the package,
+/// and then in the original package for translation packages. `file` can not contain `..`, and
+/// the file it resolves to, after following symlinks, must be inside that package.
+pub(crate) fn resolve(
+    file: &str,
+    config: &fpm::Config,
+) -> Result<Option<camino::Utf8PathBuf>, String> {
+    let file = file.trim_start_matches('/');
+    if camino::Utf8Path::new(file).components().any(|c| {
+        !matches!(
+            c,
+            camino::Utf8Component::Normal(_) | camino::Utf8Component::CurDir
+        )
+    }) {
+        return Err(format!("`{}` is outside of the package", file));
+    }
+
+    for dep in config.package.get_flattened_dependencies() {
+        if let Some(rest) = file.strip_prefix(format!("{}/", dep.package.name).as_str()) {
+            if let Some(path) = within(&config.get_root_for_package(&dep.package), rest) {
+                return Ok(Some(path));
+            }
+        }
+    }
+
+    if let Some(path) = within(&config.root, file) {
+        return Ok(Some(path));
+    }
+
+    Ok(config.original_path().ok().and_then(|v| within(&v, file)))
+}
+
+/// `within()` returns `root/file` if it exists and is inside `root`.
+pub(crate) fn within(root: &camino::Utf8Path, file: &str) -> Option<camino::Utf8PathBuf> {
+    let path = root.join(file);
+    let canonical = path.canonicalize().ok()?;
+    if canonical.starts_with(root.canonicalize().ok()?) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+fn parse(content: &str, format: &str) -> Result<serde_json::Value, String> {
+    match format {
+        "json" => serde_json::from_str(content).map_err(|e| e.to_string()),
+        "yaml" | "yml" => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+        "toml" => toml::from_str(content).map_err(|e| e.to_string()),
+        "csv" => parse_csv(content),
+        _ => Err(format!(
+            "format can be `json`, `yaml`, `toml` or `csv`, found: `{}`",
+            format
+        )),
+    }
+}
+
+fn parse_csv(content: &str) -> Result<serde_json::Value, String> {
+    let (columns, rows) = csv_rows(content)?;
+    Ok(serde_json::Value::Array(
+        rows.into_iter()
+            .map(|row| serde_json::Value::Object(columns.iter().cloned().zip(row).collect()))
+            .collect(),
+    ))
+}
+
+/// `csv_rows()` returns the column names, from the first row, and the rows of a CSV file. Empty
+/// values are `null`, `10` is read as a number and `true` as a boolean, other values are strings.
+pub(crate) fn csv_rows(
+    content: &str,
+) -> Result<(Vec<String>, Vec<Vec<serde_json::Value>>), String> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    let columns = reader
+        .headers()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .map(|v| v.trim().to_string())
+        .collect::<Vec<String>>();
+
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        rows.push(
+            record
+                .iter()
+                .map(|v| match v.trim() {
+                    "" => serde_json::Value::Null,
+                    v => match serde_json::from_str::<serde_json::Value>(v) {
+                        Ok(value) if value.is_number() || value.is_boolean() => value,
+                        _ => serde_json::Value::String(v.to_string()),
+                    },
+                })
+                .collect(),
+        );
+    }
+    Ok((columns, rows))
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn parse() {
+        assert_eq!(
+            super::parse("name, price\nfpm, 10\nftd,\n", "csv"),
+            Ok(serde_json::json!([
+                {"name": "fpm", "price": 10},
+                {"name": "ftd", "price": null}
+            ]))
+        );
+        assert_eq!(
+            super::parse("name: fpm\ntags: [rust, ftd]\n", "yaml"),
+            Ok(serde_json::json!({"name": "fpm", "tags": ["rust", "ftd"]}))
+        );
+        assert_eq!(
+            super::parse("name = \"fpm\"\n[owner]\nlogin = \"fifthtry\"\n", "toml"),
+            Ok(serde_json::json!({"name": "fpm", "owner": {"login": "fifthtry"}}))
+        );
+        assert!(super::parse("", "xml").is_err());
+    }
+
+    #[test]
+    fn resolve() {
+        let dir = std::env::temp_dir().join(format!("fpm-read-data-{}", std::process::id()));
+        let root = camino::Utf8PathBuf::from_path_buf(dir.join("package")).unwrap();
+        std::fs::create_dir_all(root.join("data")).unwrap();
+        std::fs::write(root.join("data").join("products.json"), "[]").unwrap();
+        std::fs::write(dir.join("secrets.json"), "{}").unwrap();
+        let config = fpm::Config::for_test(root.clone());
+
+        assert_eq!(
+            super::resolve("data/products.json", &config),
+            Ok(Some(root.join("data/products.json")))
+        );
+        assert_eq!(
+            super::resolve("/data/products.json", &config),
+            Ok(Some(root.join("data/products.json")))
+        );
+        assert_eq!(super::resolve("data/missing.json", &config), Ok(None));
+        assert!(super::resolve("../secrets.json", &config).is_err());
+        assert!(super::resolve("data/../../secrets.json", &config).is_err());
+        // absolute paths are relative to the package root
+        assert_eq!(
+            super::resolve(dir.join("secrets.json").to_str().unwrap(), &config),
+            Ok(None)
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
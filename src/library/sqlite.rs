@@ -1,3 +1,31 @@
+/// `$processor$: package-query` runs the sql query in the section body:
+///
+/// ```ftd
+/// -- product list products:
+/// $processor$: package-query
+/// db: data/products.csv
+/// category: $current-category
+/// min-price: 10
+///
+/// SELECT name, price FROM products WHERE category = :category AND price >= :min_price
+/// ```
+///
+/// `db` is a SQLite database, or a CSV or JSON file which is loaded as a table named after the
+/// file (`products` above, use `table` header to change it). The JSON file must contain a list of
+/// objects. A `.sql` file is a `pg_dump` of a Postgres database, which is loaded locally, so no
+/// Postgres server is needed to build the package. `backend: sqlite | csv | json | postgres`
+/// overrides the backend guessed from the extension, see `Backend`. The query is run by SQLite
+/// whatever the backend is, so it is written in the SQLite dialect.
+///
+/// `db` is relative to the package root, and can not be outside of it, like the `file` of
+/// `$processor$: read-data`.
+///
+/// Other headers are bound to the named parameters of the query, with `-` replaced by `_`, so
+/// `min-price` header is bound to `:min_price` above. A header value starting with `$` is the
+/// value of that ftd variable.
+///
+/// BLOB columns are returned base64 encoded, or with `blob: asset`, written in `.build/-/blobs/`
+/// and returned as the url of that file.
 pub async fn processor<'a>(
     section: &ftd::p1::Section,
     doc: &ftd::p2::TDoc<'a>,
@@ -6,6 +34,9 @@ pub async fn processor<'a>(
     tokio::task::block_in_place(move || processor_(section, doc, config))
 }
 
+/// Headers of `package-query` which are not query parameters.
+const RESERVED_HEADERS: &[&str] = &["$processor$", "db", "backend", "table", "blob"];
+
 pub fn processor_(
     section: &ftd::p1::Section,
     doc: &ftd::p2::TDoc,
@@ -25,39 +56,24 @@ pub fn processor_(
         }
     };
     let query = section.body(section.line_number, doc.name)?;
-
-    let conn =
-        match rusqlite::Connection::open_with_flags(db, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+    let blob_as_asset =
+        match section
+            .header
+            .str_with_default(doc.name, section.line_number, "blob", "base64")?
         {
-            Ok(conn) => conn,
-            Err(e) => {
-                let original_path = match config.original_path() {
-                    Ok(original_path) => original_path,
-                    _ => {
-                        return ftd::e2(
-                            format!("Failed to open `{}`: {:?}", db, e),
-                            doc.name,
-                            section.line_number,
-                        );
-                    }
-                };
-                let db_path = original_path.join(db);
-                match rusqlite::Connection::open_with_flags(
-                    &db_path,
-                    rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
-                ) {
-                    Ok(conn) => conn,
-                    Err(e) => {
-                        return ftd::e2(
-                            format!("Failed to open `{}`: {:?}", db_path.as_str(), e),
-                            doc.name,
-                            section.line_number,
-                        )
-                    }
-                }
+            "base64" => false,
+            "asset" => true,
+            v => {
+                return ftd::e2(
+                    format!("`blob` can be `base64` or `asset`, found: {}", v),
+                    doc.name,
+                    section.line_number,
+                )
             }
         };
 
+    let conn = connect(db, section, doc, config)?;
+
     let mut stmt = match conn.prepare(query.as_str()) {
         Ok(v) => v,
         Err(e) => {
@@ -71,7 +87,23 @@ pub fn processor_(
 
     let count = stmt.column_count();
 
-    let mut rows = match stmt.query([]) {
+    let mut params: Vec<(String, rusqlite::types::Value)> = vec![];
+    for (name, v) in bind(&stmt, section.header.0.as_slice()) {
+        let variable = |name: &str| {
+            doc.get_value(section.line_number, name)
+                .map_err(|e| e.to_string())
+        };
+        match param_value(v, variable) {
+            Ok(value) => params.push((name, value)),
+            Err(e) => return ftd::e2(e, doc.name, section.line_number),
+        }
+    }
+    let params = params
+        .iter()
+        .map(|(k, v)| (k.as_str(), v as &dyn rusqlite::ToSql))
+        .collect::<Vec<(&str, &dyn rusqlite::ToSql)>>();
+
+    let mut rows = match stmt.query(params.as_slice()) {
         Ok(v) => v,
         Err(e) => {
             return ftd::e2(
@@ -87,9 +119,10 @@ pub fn processor_(
         loop {
             match rows.next() {
                 Ok(None) => break,
-                Ok(Some(r)) => {
-                    result.push(row_to_json(r, section, doc, count)?);
-                }
+                Ok(Some(r)) => match row_to_json(r, config, count, blob_as_asset) {
+                    Ok(row) => result.push(row),
+                    Err(e) => return ftd::e2(e, doc.name, section.line_number),
+                },
                 Err(e) => {
                     return ftd::e2(
                         format!("Failed to execute query: {:?}", e),
@@ -102,7 +135,10 @@ pub fn processor_(
         doc.from_json_rows(section, &result)
     } else {
         let json = match rows.next() {
-            Ok(Some(r)) => row_to_json(r, section, doc, count)?,
+            Ok(Some(r)) => match row_to_json(r, config, count, blob_as_asset) {
+                Ok(row) => row,
+                Err(e) => return ftd::e2(e, doc.name, section.line_number),
+            },
             Ok(None) => {
                 return ftd::e2(
                     "Query returned no result, expected one row".to_string(),
@@ -122,12 +158,566 @@ pub fn processor_(
     }
 }
 
-fn row_to_json(
-    r: &rusqlite::Row,
+/// `connect()` opens the `db` with its `Backend`. `db` is looked up in the package, and then in
+/// the original package for translation packages, see `fpm::library::read_data::resolve()`.
+fn connect(
+    db: &str,
     section: &ftd::p1::Section,
     doc: &ftd::p2::TDoc,
+    config: &fpm::Config,
+) -> ftd::p1::Result<rusqlite::Connection> {
+    let path = match fpm::library::read_data::resolve(db.trim(), config) {
+        Ok(Some(v)) => v,
+        Ok(None) => return ftd::e2(format!("`{}` not found", db), doc.name, section.line_number),
+        Err(e) => return ftd::e2(e, doc.name, section.line_number),
+    };
+
+    let backend: &dyn Backend =
+        match section
+            .header
+            .str_optional(doc.name, section.line_number, "backend")?
+        {
+            Some(v) => match v.to_lowercase().as_str() {
+                "sqlite" => &Sqlite,
+                "csv" => &Csv,
+                "json" => &Json,
+                "postgres" => &Postgres,
+                v => {
+                    return ftd::e2(
+                        format!(
+                            "`backend` can be `sqlite`, `csv`, `json` or `postgres`, found: {}",
+                            v
+                        ),
+                        doc.name,
+                        section.line_number,
+                    )
+                }
+            },
+            None => match path.extension().map(|v| v.to_lowercase()).as_deref() {
+                Some("csv") => &Csv,
+                Some("json") => &Json,
+                Some("sql") => &Postgres,
+                _ => &Sqlite,
+            },
+        };
+
+    let table = match section
+        .header
+        .str_optional(doc.name, section.line_number, "table")?
+    {
+        Some(v) => v.to_string(),
+        None => path
+            .file_stem()
+            .unwrap_or("data")
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect(),
+    };
+
+    match backend.connect(&path, table.as_str()) {
+        Ok(conn) => Ok(conn),
+        Err(e) => ftd::e2(
+            format!("Failed to open `{}`: {}", path.as_str(), e),
+            doc.name,
+            section.line_number,
+        ),
+    }
+}
+
+/// `Backend` gives a SQLite connection for a `db`, so the query is written in the same SQL
+/// dialect whatever the format of the data is.
+trait Backend {
+    /// `connect()` opens the database at `path`. Backends that load a file as a single table
+    /// name it `table`.
+    fn connect(&self, path: &camino::Utf8Path, table: &str)
+        -> Result<rusqlite::Connection, String>;
+}
+
+/// SQLite databases are opened read only.
+struct Sqlite;
+
+impl Backend for Sqlite {
+    fn connect(
+        &self,
+        path: &camino::Utf8Path,
+        _table: &str,
+    ) -> Result<rusqlite::Connection, String> {
+        rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| format!("{:?}", e))
+    }
+}
+
+/// CSV files are loaded in an in memory database, the first row has the column names.
+struct Csv;
+
+impl Backend for Csv {
+    fn connect(
+        &self,
+        path: &camino::Utf8Path,
+        table: &str,
+    ) -> Result<rusqlite::Connection, String> {
+        let (columns, rows) = csv_rows(path)?;
+        load_table(table, columns.as_slice(), rows).map_err(|e| format!("{:?}", e))
+    }
+}
+
+/// JSON files, a list of objects, are loaded in an in memory database. The columns are the keys
+/// of all the objects.
+struct Json;
+
+impl Backend for Json {
+    fn connect(
+        &self,
+        path: &camino::Utf8Path,
+        table: &str,
+    ) -> Result<rusqlite::Connection, String> {
+        let (columns, rows) = json_rows(path)?;
+        load_table(table, columns.as_slice(), rows).map_err(|e| format!("{:?}", e))
+    }
+}
+
+/// `pg_dump` files, in plain SQL format, are loaded in an in memory database, so the data of a
+/// Postgres database can be queried without a server. The tables, views and rows are loaded,
+/// from either `INSERT` statements (`pg_dump --inserts`) or `COPY` blocks, other statements, like
+/// `SET`, sequences, constraints and indexes, are skipped.
+struct Postgres;
+
+impl Backend for Postgres {
+    fn connect(
+        &self,
+        path: &camino::Utf8Path,
+        _table: &str,
+    ) -> Result<rusqlite::Connection, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let conn = rusqlite::Connection::open_in_memory().map_err(|e| format!("{:?}", e))?;
+        for statement in pg_dump_statements(content.as_str())? {
+            match statement {
+                Statement::Sql(sql) => {
+                    if let Some(sql) = pg_to_sqlite(sql.as_str()) {
+                        conn.execute_batch(sql.as_str()).map_err(|e| {
+                            format!(
+                                "failed to load `{}`: {:?}",
+                                sql.lines().next().unwrap_or_default(),
+                                e
+                            )
+                        })?;
+                    }
+                }
+                Statement::Copy { head, rows } => copy(&conn, head.as_str(), rows)?,
+            }
+        }
+        Ok(conn)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Statement {
+    Sql(String),
+    /// `COPY <table> (<columns>) FROM stdin;` and its rows, `None` is `\N`, ie `NULL`.
+    Copy {
+        head: String,
+        rows: Vec<Vec<Option<String>>>,
+    },
+}
+
+/// `pg_dump_statements()` splits a plain SQL dump in statements. `;` in strings, quoted names
+/// and dollar quoted bodies do not end a statement, and comments and psql commands, like
+/// `\connect`, are dropped.
+fn pg_dump_statements(content: &str) -> Result<Vec<Statement>, String> {
+    let mut statements = vec![];
+    let mut current = String::new();
+    let mut copy: Option<(String, Vec<Vec<Option<String>>>)> = None;
+    // the closing quote of the string, quoted name or dollar quoted body we are in
+    let mut quote: Option<String> = None;
+
+    for line in content.lines() {
+        if let Some((head, mut rows)) = copy.take() {
+            if line == "\\." {
+                statements.push(Statement::Copy { head, rows });
+            } else {
+                rows.push(line.split('\t').map(copy_value).collect());
+                copy = Some((head, rows));
+            }
+            continue;
+        }
+        if quote.is_none() && current.trim().is_empty() && line.starts_with('\\') {
+            continue;
+        }
+
+        let mut rest = line;
+        while !rest.is_empty() {
+            if let Some(end) = quote.clone() {
+                match rest.find(end.as_str()) {
+                    Some(i) => {
+                        current.push_str(&rest[..i + end.len()]);
+                        rest = &rest[i + end.len()..];
+                        quote = None;
+                    }
+                    None => {
+                        current.push_str(rest);
+                        rest = "";
+                    }
+                }
+                continue;
+            }
+            let c = rest.chars().next().unwrap();
+            if rest.starts_with("--") {
+                break;
+            } else if c == '\'' || c == '"' {
+                quote = Some(c.to_string());
+            } else if c == '$' {
+                if let Some(tag) = dollar_tag(rest) {
+                    current.push_str(tag);
+                    rest = &rest[tag.len()..];
+                    quote = Some(tag.to_string());
+                    continue;
+                }
+            } else if c == ';' {
+                let statement = current.trim().to_string();
+                current.clear();
+                if statement.to_uppercase().starts_with("COPY ")
+                    && statement.to_lowercase().ends_with("from stdin")
+                {
+                    copy = Some((statement, vec![]));
+                    if !rest[1..].trim().is_empty() {
+                        return Err(format!("unexpected `{}` after COPY", &rest[1..]));
+                    }
+                    break;
+                } else if !statement.is_empty() {
+                    statements.push(Statement::Sql(statement));
+                }
+                rest = &rest[1..];
+                continue;
+            }
+            current.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        current.push('\n');
+    }
+
+    if copy.is_some() {
+        return Err("COPY data does not end with `\\.`".to_string());
+    }
+    if !current.trim().is_empty() {
+        statements.push(Statement::Sql(current.trim().to_string()));
+    }
+    Ok(statements)
+}
+
+/// `dollar_tag()` returns the `$tag$` that starts `s`, if any.
+fn dollar_tag(s: &str) -> Option<&str> {
+    let end = s[1..].find('$')? + 1;
+    let tag = &s[1..end];
+    if tag.chars().next().map_or(true, |c| !c.is_ascii_digit())
+        && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Some(&s[..=end])
+    } else {
+        None
+    }
+}
+
+/// `copy_value()` unescapes a value of a `COPY` row.
+fn copy_value(value: &str) -> Option<String> {
+    if value == "\\N" {
+        return None;
+    }
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('b') => unescaped.push('\u{8}'),
+            Some('f') => unescaped.push('\u{c}'),
+            Some('v') => unescaped.push('\u{b}'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    Some(unescaped)
+}
+
+lazy_static! {
+    static ref PUBLIC_SCHEMA: regex::Regex =
+        regex::Regex::new(r#"(?:\bpublic|"public")\."#).unwrap();
+    static ref COLUMN_DEFAULT: regex::Regex =
+        regex::Regex::new(r"(?m)\s+DEFAULT\s+.*?(,?)$").unwrap();
+    static ref CAST: regex::Regex =
+        regex::Regex::new(r"::[A-Za-z_]+(?: varying| precision)?(?:\[\])?").unwrap();
+    static ref BYTEA: regex::Regex = regex::Regex::new(r"'\\x([0-9A-Fa-f]*)'").unwrap();
+    static ref COPY_HEAD: regex::Regex = regex::Regex::new(
+        r#"(?is)^COPY\s+(?:public\.|"public"\.)?(\S+?)\s*(?:\((.*)\))?\s+FROM\s+stdin$"#
+    )
+    .unwrap();
+}
+
+/// `pg_to_sqlite()` returns the statement in SQLite dialect, or `None` if it is not needed to
+/// read the data.
+fn pg_to_sqlite(sql: &str) -> Option<String> {
+    let upper = sql.to_uppercase();
+    if upper.starts_with("CREATE TABLE ") {
+        let sql = COLUMN_DEFAULT.replace_all(sql, "$1");
+        let sql = CAST.replace_all(&sql, "");
+        Some(PUBLIC_SCHEMA.replace_all(&sql, "").replace("[]", ""))
+    } else if upper.starts_with("CREATE VIEW ") {
+        let sql = CAST.replace_all(sql, "");
+        Some(PUBLIC_SCHEMA.replace_all(&sql, "").to_string())
+    } else if upper.starts_with("INSERT INTO ") {
+        // only the table name, the values may contain `public.`
+        let (head, values) = sql.split_at(sql.find(" VALUES").unwrap_or(sql.len()));
+        Some(format!(
+            "{}{}",
+            PUBLIC_SCHEMA.replace(head, ""),
+            BYTEA.replace_all(values, "X'$1'")
+        ))
+    } else {
+        None
+    }
+}
+
+/// `copy()` inserts the rows of a `COPY` statement. `boolean` and `bytea` values are converted,
+/// others are stored as text, which SQLite converts to numbers as per the column type.
+fn copy(
+    conn: &rusqlite::Connection,
+    head: &str,
+    rows: Vec<Vec<Option<String>>>,
+) -> Result<(), String> {
+    let captures = match COPY_HEAD.captures(head) {
+        Some(v) => v,
+        None => return Err(format!("unsupported `{}`", head)),
+    };
+    let table = captures[1].to_string();
+    let types = conn
+        .prepare(format!("PRAGMA table_info({})", table).as_str())
+        .and_then(|mut stmt| {
+            stmt.query_map([], |r| Ok((r.get::<_, String>(1)?, r.get::<_, String>(2)?)))?
+                .collect::<rusqlite::Result<Vec<(String, String)>>>()
+        })
+        .map_err(|e| format!("{:?}", e))?;
+    let columns = match captures.get(2) {
+        Some(v) => v
+            .as_str()
+            .split(',')
+            .map(|v| v.trim().to_string())
+            .collect::<Vec<String>>(),
+        None => types.iter().map(|(name, _)| name.to_string()).collect(),
+    };
+    let types = columns
+        .iter()
+        .map(|column| {
+            types
+                .iter()
+                .find(|(name, _)| name.eq(column.trim_matches('"')))
+                .map(|(_, kind)| kind.to_lowercase())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<String>>();
+
+    let mut insert = conn
+        .prepare(
+            format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                table,
+                columns.join(", "),
+                vec!["?"; columns.len()].join(", ")
+            )
+            .as_str(),
+        )
+        .map_err(|e| format!("{:?}", e))?;
+    for row in rows {
+        let values = row
+            .into_iter()
+            .zip(types.iter())
+            .map(|(value, kind)| match value {
+                None => rusqlite::types::Value::Null,
+                Some(v) if kind == "boolean" => rusqlite::types::Value::Integer((v == "t") as i64),
+                Some(v) if kind == "bytea" => match v.strip_prefix("\\x").and_then(decode_hex) {
+                    Some(v) => rusqlite::types::Value::Blob(v),
+                    None => rusqlite::types::Value::Text(v),
+                },
+                Some(v) => rusqlite::types::Value::Text(v),
+            })
+            .collect::<Vec<rusqlite::types::Value>>();
+        insert
+            .execute(rusqlite::params_from_iter(values))
+            .map_err(|e| format!("{:?}", e))?;
+    }
+    Ok(())
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| s.get(i..i + 2).and_then(|v| u8::from_str_radix(v, 16).ok()))
+        .collect()
+}
+
+type Rows = (Vec<String>, Vec<Vec<rusqlite::types::Value>>);
+
+fn csv_rows(path: &camino::Utf8Path) -> Result<Rows, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let (columns, rows) = fpm::library::read_data::csv_rows(content.as_str())?;
+    Ok((
+        columns,
+        rows.iter()
+            .map(|row| row.iter().map(sqlite_value).collect())
+            .collect(),
+    ))
+}
+
+fn json_rows(path: &camino::Utf8Path) -> Result<Rows, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let list = match serde_json::from_str::<serde_json::Value>(content.as_str()) {
+        Ok(serde_json::Value::Array(list)) => list,
+        Ok(_) => return Err("expected a list of objects".to_string()),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let mut columns: Vec<String> = vec![];
+    for item in list.iter() {
+        let object = match item.as_object() {
+            Some(v) => v,
+            None => return Err("expected a list of objects".to_string()),
+        };
+        for key in object.keys() {
+            if !columns.contains(key) {
+                columns.push(key.to_string());
+            }
+        }
+    }
+
+    let rows = list
+        .iter()
+        .map(|item| {
+            columns
+                .iter()
+                .map(|column| {
+                    item.get(column)
+                        .map(sqlite_value)
+                        .unwrap_or(rusqlite::types::Value::Null)
+                })
+                .collect()
+        })
+        .collect();
+    Ok((columns, rows))
+}
+
+/// `sqlite_value()` stores booleans as integers, and lists and objects as JSON text.
+fn sqlite_value(value: &serde_json::Value) -> rusqlite::types::Value {
+    match value {
+        serde_json::Value::Null => rusqlite::types::Value::Null,
+        serde_json::Value::Bool(v) => rusqlite::types::Value::Integer(*v as i64),
+        serde_json::Value::Number(v) => match v.as_i64() {
+            Some(v) => rusqlite::types::Value::Integer(v),
+            None => rusqlite::types::Value::Real(v.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(v) => rusqlite::types::Value::Text(v.to_string()),
+        v => rusqlite::types::Value::Text(v.to_string()),
+    }
+}
+
+fn load_table(
+    table: &str,
+    columns: &[String],
+    rows: Vec<Vec<rusqlite::types::Value>>,
+) -> rusqlite::Result<rusqlite::Connection> {
+    let conn = rusqlite::Connection::open_in_memory()?;
+    let quote = |v: &str| format!("\"{}\"", v.replace('"', "\"\""));
+    conn.execute(
+        format!(
+            "CREATE TABLE {} ({})",
+            quote(table),
+            columns
+                .iter()
+                .map(|v| quote(v))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+        .as_str(),
+        [],
+    )?;
+    {
+        let mut insert = conn.prepare(
+            format!(
+                "INSERT INTO {} VALUES ({})",
+                quote(table),
+                vec!["?"; columns.len()].join(", ")
+            )
+            .as_str(),
+        )?;
+        for row in rows {
+            insert.execute(rusqlite::params_from_iter(row))?;
+        }
+    }
+    Ok(conn)
+}
+
+/// `bind()` returns the headers which are named parameters of the query, with the parameter
+/// name and the header value. `min-price` header is bound to `:min_price`.
+fn bind<'a>(
+    stmt: &rusqlite::Statement,
+    headers: &'a [(usize, String, String)],
+) -> Vec<(String, &'a str)> {
+    headers
+        .iter()
+        .filter(|(_, k, _)| !RESERVED_HEADERS.contains(&k.as_str()))
+        .filter_map(|(_, k, v)| {
+            let name = format!(":{}", k.replace('-', "_"));
+            match stmt.parameter_index(name.as_str()) {
+                Ok(Some(_)) => Some((name, v.as_str())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// `literal()` parses the value as integer or decimal if possible.
+fn literal(value: &str) -> rusqlite::types::Value {
+    if let Ok(v) = value.parse::<i64>() {
+        rusqlite::types::Value::Integer(v)
+    } else if let Ok(v) = value.parse::<f64>() {
+        rusqlite::types::Value::Real(v)
+    } else {
+        rusqlite::types::Value::Text(value.to_string())
+    }
+}
+
+/// `param_value()` returns the value of a query parameter header. `$foo` is the value of ftd
+/// variable `foo`, as returned by `variable`, other values are parsed as integer or decimal if
+/// possible.
+fn param_value(
+    value: &str,
+    variable: impl Fn(&str) -> Result<ftd::Value, String>,
+) -> Result<rusqlite::types::Value, String> {
+    let value = value.trim();
+    let name = match value.strip_prefix('$') {
+        Some(name) => name,
+        None => return Ok(literal(value)),
+    };
+    match variable(name)? {
+        ftd::Value::Integer { value } => Ok(rusqlite::types::Value::Integer(value)),
+        ftd::Value::Decimal { value } => Ok(rusqlite::types::Value::Real(value)),
+        ftd::Value::Boolean { value } => Ok(rusqlite::types::Value::Integer(value as i64)),
+        ftd::Value::String { text, .. } => Ok(rusqlite::types::Value::Text(text)),
+        v => Err(format!(
+            "`{}` can not be used as query parameter: {:?}",
+            name, v
+        )),
+    }
+}
+
+fn row_to_json(
+    r: &rusqlite::Row,
+    config: &fpm::Config,
     count: usize,
-) -> ftd::p1::Result<Vec<serde_json::Value>> {
+    blob_as_asset: bool,
+) -> Result<Vec<serde_json::Value>, String> {
     let mut row: Vec<serde_json::Value> = vec![];
     for i in 0..count {
         match r.get::<usize, rusqlite::types::Value>(i) {
@@ -137,28 +727,350 @@ fn row_to_json(
                 serde_json::Number::from_f64(i).unwrap(),
             )),
             Ok(rusqlite::types::Value::Text(i)) => row.push(serde_json::Value::String(i)),
-            Ok(rusqlite::types::Value::Blob(_)) => {
-                return ftd::e2(
-                    format!("Query returned blob for column: {}", i),
-                    doc.name,
-                    section.line_number,
-                );
+            Ok(rusqlite::types::Value::Blob(b)) if blob_as_asset => {
+                match write_blob(config, b.as_slice()) {
+                    Ok(url) => row.push(serde_json::Value::String(url)),
+                    Err(e) => return Err(format!("Failed to write blob of column {}: {:?}", i, e)),
+                }
             }
-            Err(e) => {
-                return ftd::e2(
-                    format!("Failed to read response: {:?}", e),
-                    doc.name,
-                    section.line_number,
-                );
+            Ok(rusqlite::types::Value::Blob(b)) => {
+                row.push(serde_json::Value::String(base64::encode(b)))
             }
+            Err(e) => return Err(format!("Failed to read response: {:?}", e)),
         }
     }
     Ok(row)
 }
 
+/// `write_blob()` writes the blob in `.build/-/blobs/`, named after its content hash, and
+/// returns its url.
+fn write_blob(config: &fpm::Config, blob: &[u8]) -> fpm::Result<String> {
+    use sha2::Digest;
+
+    let name = format!("{:x}", sha2::Sha256::digest(blob));
+    let dir = config.build_dir().join("-").join("blobs");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(name.as_str()), blob)?;
+    Ok(format!("-/blobs/{}", name))
+}
+
 fn is_list(section: &ftd::p1::Section, doc: &ftd::p2::TDoc) -> bool {
     matches!(
         doc.get_value(section.line_number, section.name.as_str()),
         Ok(ftd::Value::List { .. })
     )
 }
+
+#[cfg(test)]
+mod test {
+    use super::Backend;
+
+    const QUERY: &str = "SELECT name, price FROM products \
+        WHERE category = :category AND price >= :min_price ORDER BY name";
+
+    fn query(conn: &rusqlite::Connection) -> Vec<Vec<rusqlite::types::Value>> {
+        let headers = vec![
+            (1, "$processor$".to_string(), "package-query".to_string()),
+            (2, "db".to_string(), "products.csv".to_string()),
+            (3, "category".to_string(), "books".to_string()),
+            (4, "min-price".to_string(), "10".to_string()),
+            (5, "max-price".to_string(), "20".to_string()),
+        ];
+        let mut stmt = conn.prepare(QUERY).unwrap();
+        let params = super::bind(&stmt, headers.as_slice());
+        assert_eq!(
+            params,
+            vec![
+                (":category".to_string(), "books"),
+                (":min_price".to_string(), "10")
+            ]
+        );
+        let params = params
+            .into_iter()
+            .map(|(k, v)| (k, super::literal(v)))
+            .collect::<Vec<_>>();
+        let params = params
+            .iter()
+            .map(|(k, v)| (k.as_str(), v as &dyn rusqlite::ToSql))
+            .collect::<Vec<_>>();
+        stmt.query_map(params.as_slice(), |r| {
+            Ok(vec![
+                r.get::<usize, rusqlite::types::Value>(0)?,
+                r.get::<usize, rusqlite::types::Value>(1)?,
+            ])
+        })
+        .unwrap()
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .unwrap()
+    }
+
+    fn write(name: &str, content: &str) -> camino::Utf8PathBuf {
+        let dir = std::env::temp_dir().join(format!("fpm-sqlite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        camino::Utf8PathBuf::from_path_buf(path).unwrap()
+    }
+
+    #[test]
+    fn csv() {
+        let path = write(
+            "products.csv",
+            "name, category, price\nftd, books, 12\nfpm, books, 9.5\nrust, books, 10\nink, pens,\n",
+        );
+        assert_eq!(
+            super::csv_rows(&path).unwrap().1[3],
+            vec![
+                rusqlite::types::Value::Text("ink".to_string()),
+                rusqlite::types::Value::Text("pens".to_string()),
+                rusqlite::types::Value::Null
+            ]
+        );
+        let conn = super::Csv.connect(&path, "products").unwrap();
+        assert_eq!(
+            query(&conn),
+            vec![
+                vec![
+                    rusqlite::types::Value::Text("ftd".to_string()),
+                    rusqlite::types::Value::Integer(12)
+                ],
+                vec![
+                    rusqlite::types::Value::Text("rust".to_string()),
+                    rusqlite::types::Value::Integer(10)
+                ],
+            ]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn json() {
+        let path = write(
+            "products.json",
+            r#"[
+                {"name": "ftd", "category": "books", "price": 12.5},
+                {"name": "fpm", "category": "books", "price": 9},
+                {"name": "ink", "category": "pens", "price": 10, "refill": true}
+            ]"#,
+        );
+        let (columns, rows) = super::json_rows(&path).unwrap();
+        assert_eq!(columns, vec!["category", "name", "price", "refill"]);
+        assert_eq!(rows[0][3], rusqlite::types::Value::Null);
+        assert_eq!(rows[2][3], rusqlite::types::Value::Integer(1));
+
+        let conn = super::Json.connect(&path, "products").unwrap();
+        assert_eq!(
+            query(&conn),
+            vec![vec![
+                rusqlite::types::Value::Text("ftd".to_string()),
+                rusqlite::types::Value::Real(12.5)
+            ]]
+        );
+
+        std::fs::write(&path, r#"{"name": "ftd"}"#).unwrap();
+        assert!(super::Json.connect(&path, "products").is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn postgres() {
+        let path = write(
+            "products.sql",
+            indoc::indoc! {r#"
+                --
+                -- PostgreSQL database dump
+                --
+
+                SET standard_conforming_strings = on;
+                SELECT pg_catalog.set_config('search_path', '', false);
+
+                CREATE FUNCTION public.touch() RETURNS trigger
+                    LANGUAGE plpgsql
+                    AS $$
+                BEGIN
+                    NEW.updated := now(); -- a comment; with a semicolon
+                    RETURN NEW;
+                END;
+                $$;
+
+                CREATE TABLE public.products (
+                    name character varying(100) NOT NULL,
+                    category text DEFAULT 'misc'::text,
+                    price numeric(10,2),
+                    tags text[],
+                    in_stock boolean DEFAULT true NOT NULL,
+                    image bytea
+                );
+
+                ALTER TABLE public.products OWNER TO postgres;
+
+                CREATE VIEW public.books AS
+                 SELECT products.name,
+                    products.price
+                   FROM public.products
+                  WHERE (products.category = 'books'::text);
+
+                COPY public.products (name, category, price, tags, in_stock, image) FROM stdin;
+                ftd	books	12.00	{a,b}	t	\\x0102
+                fpm; the "manager"	books	9.50	\N	f	\N
+                rust	books	10	\N	t	\N
+                ink	pens	\N	\N	t	\N
+                \.
+
+                CREATE TABLE public.notes (body text, data bytea);
+                INSERT INTO public.notes VALUES ('it''s; public.notes', '\x0a0b');
+
+                CREATE INDEX products_category ON public.products USING btree (category);
+            "#},
+        );
+        let conn = super::Postgres.connect(&path, "products").unwrap();
+        assert_eq!(
+            query(&conn),
+            vec![
+                vec![
+                    rusqlite::types::Value::Text("ftd".to_string()),
+                    rusqlite::types::Value::Integer(12)
+                ],
+                vec![
+                    rusqlite::types::Value::Text("rust".to_string()),
+                    rusqlite::types::Value::Integer(10)
+                ],
+            ]
+        );
+        let row = |sql: &str| {
+            conn.query_row(sql, [], |r| {
+                Ok(vec![
+                    r.get::<usize, rusqlite::types::Value>(0)?,
+                    r.get::<usize, rusqlite::types::Value>(1)?,
+                ])
+            })
+            .unwrap()
+        };
+        assert_eq!(
+            row("SELECT in_stock, image FROM products WHERE name = 'ftd'"),
+            vec![
+                rusqlite::types::Value::Integer(1),
+                rusqlite::types::Value::Blob(vec![1, 2])
+            ]
+        );
+        assert_eq!(
+            row("SELECT name, price FROM books WHERE price < 10"),
+            vec![
+                rusqlite::types::Value::Text("fpm; the \"manager\"".to_string()),
+                rusqlite::types::Value::Real(9.5)
+            ]
+        );
+        assert_eq!(
+            row("SELECT body, data FROM notes"),
+            vec![
+                rusqlite::types::Value::Text("it's; public.notes".to_string()),
+                rusqlite::types::Value::Blob(vec![10, 11])
+            ]
+        );
+
+        std::fs::write(&path, "COPY public.products (name) FROM stdin;\nftd\n").unwrap();
+        assert!(super::Postgres.connect(&path, "products").is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn param_value() {
+        let variable = |name: &str| match name {
+            "current-category" => Ok(ftd::Value::String {
+                text: "books".to_string(),
+                source: ftd::TextSource::Header,
+            }),
+            "min-price" => Ok(ftd::Value::Integer { value: 10 }),
+            "in-stock" => Ok(ftd::Value::Boolean { value: true }),
+            v => Err(format!("`{}` not found", v)),
+        };
+        assert_eq!(
+            super::param_value("$current-category", variable),
+            Ok(rusqlite::types::Value::Text("books".to_string()))
+        );
+        assert_eq!(
+            super::param_value(" $min-price", variable),
+            Ok(rusqlite::types::Value::Integer(10))
+        );
+        assert_eq!(
+            super::param_value("$in-stock", variable),
+            Ok(rusqlite::types::Value::Integer(1))
+        );
+        assert_eq!(
+            super::param_value("9.5", variable),
+            Ok(rusqlite::types::Value::Real(9.5))
+        );
+        assert!(super::param_value("$missing", variable).is_err());
+
+        // `$current-category` is bound to `:category`
+        let conn = super::load_table(
+            "products",
+            &["name".to_string(), "category".to_string()],
+            vec![
+                vec![
+                    rusqlite::types::Value::Text("ftd".to_string()),
+                    rusqlite::types::Value::Text("books".to_string()),
+                ],
+                vec![
+                    rusqlite::types::Value::Text("ink".to_string()),
+                    rusqlite::types::Value::Text("pens".to_string()),
+                ],
+            ],
+        )
+        .unwrap();
+        let headers = vec![(1, "category".to_string(), "$current-category".to_string())];
+        let mut stmt = conn
+            .prepare("SELECT name FROM products WHERE category = :category")
+            .unwrap();
+        let params = super::bind(&stmt, headers.as_slice())
+            .into_iter()
+            .map(|(k, v)| (k, super::param_value(v, variable).unwrap()))
+            .collect::<Vec<_>>();
+        let params = params
+            .iter()
+            .map(|(k, v)| (k.as_str(), v as &dyn rusqlite::ToSql))
+            .collect::<Vec<_>>();
+        let names = stmt
+            .query_map(params.as_slice(), |r| r.get::<usize, String>(0))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(names, vec!["ftd".to_string()]);
+    }
+
+    #[test]
+    fn blob() {
+        let root = camino::Utf8PathBuf::from_path_buf(
+            std::env::temp_dir().join(format!("fpm-sqlite-blob-{}", std::process::id())),
+        )
+        .unwrap();
+        let config = fpm::Config::for_test(root.clone());
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let row = |blob_as_asset: bool| {
+            conn.query_row("SELECT X'666F6F', 'foo', 1, NULL", [], |r| {
+                Ok(super::row_to_json(r, &config, 4, blob_as_asset))
+            })
+            .unwrap()
+            .unwrap()
+        };
+
+        assert_eq!(
+            row(false),
+            vec![
+                serde_json::json!("Zm9v"),
+                serde_json::json!("foo"),
+                serde_json::json!(1),
+                serde_json::Value::Null
+            ]
+        );
+
+        // the sha256 of `foo`
+        let name = "2c26b46b68ffc68ff99b453c1d30413413422d706483bfa0f98a5e886266e7ae";
+        assert_eq!(row(true)[0], serde_json::json!(format!("-/blobs/{}", name)));
+        assert_eq!(
+            std::fs::read(config.build_dir().join("-").join("blobs").join(name)).unwrap(),
+            b"foo"
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
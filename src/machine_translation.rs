@@ -0,0 +1,302 @@
+/// `Provider` is a machine translation service, used by `fpm translate` to make a draft of a
+/// translation.
+pub(crate) trait Provider {
+    /// `translate()` returns the translation of each of `texts` from language `source` to
+    /// `target`, in the same order. The texts are ftd markdown, which should be kept.
+    fn translate(&self, texts: &[String], source: &str, target: &str) -> fpm::Result<Vec<String>>;
+}
+
+/// `HttpProvider` is a machine translation service behind an HTTP JSON endpoint, declared in
+/// `FPM.ftd`:
+///
+/// ```ftd
+/// -- fpm.translation-provider: our-mt
+/// url: https://mt.example.com/translate
+/// api-key-env: MT_API_KEY
+/// ```
+///
+/// It is sent a `POST` request with `{"source": "en", "target": "hi", "texts": [...]}` and must
+/// respond with `{"translations": [...]}`. If `api-key-env` is set, the value of the environment
+/// variable is sent as a bearer token.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct HttpProvider {
+    pub name: String,
+    pub url: String,
+    #[serde(rename = "api-key-env")]
+    pub api_key_env: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct HttpResponse {
+    translations: Vec<String>,
+}
+
+impl Provider for HttpProvider {
+    fn translate(&self, texts: &[String], source: &str, target: &str) -> fpm::Result<Vec<String>> {
+        let mut request = reqwest::Client::new()
+            .post(self.url.as_str())
+            .header(reqwest::header::USER_AGENT, "fpm")
+            .json(&serde_json::json!({
+                "source": source,
+                "target": target,
+                "texts": texts,
+            }));
+        if let Some(ref env) = self.api_key_env {
+            let key = std::env::var(env).map_err(|_| fpm::Error::UsageError {
+                message: format!(
+                    "translation provider `{}` needs the environment variable `{}`",
+                    self.name, env
+                ),
+            })?;
+            request = request.bearer_auth(key);
+        }
+        let mut response = request.send()?;
+        let body = response.text()?;
+        if !response.status().is_success() {
+            return Err(fpm::Error::UsageError {
+                message: format!(
+                    "translation provider `{}` failed with {}: {}",
+                    self.name,
+                    response.status(),
+                    body
+                ),
+            });
+        }
+        Ok(serde_json::from_str::<HttpResponse>(body.as_str())?.translations)
+    }
+}
+
+/// `MockProvider` does not translate, it prefixes each text with the target language, like
+/// `[hi] Hello World`. It is used by tests, and to try `fpm translate` out.
+pub(crate) struct MockProvider;
+
+impl Provider for MockProvider {
+    fn translate(&self, texts: &[String], _source: &str, target: &str) -> fpm::Result<Vec<String>> {
+        Ok(texts
+            .iter()
+            .map(|v| format!("[{}] {}", target, v))
+            .collect())
+    }
+}
+
+/// `provider()` returns the provider with `name`, `mock` or one declared in `FPM.ftd`.
+pub(crate) fn provider(config: &fpm::Config, name: &str) -> fpm::Result<Box<dyn Provider>> {
+    if name == "mock" {
+        return Ok(Box::new(MockProvider));
+    }
+    match config
+        .package
+        .translation_providers
+        .iter()
+        .find(|v| v.name == name)
+    {
+        Some(v) => Ok(Box::new(v.clone())),
+        None => Err(fpm::Error::UsageError {
+            message: format!(
+                "unknown translation provider `{}`, declare it using `-- fpm.translation-provider: {}` in FPM.ftd, or use `mock`",
+                name, name
+            ),
+        }),
+    }
+}
+
+/// `translate()` returns `source`, the content of the document `id`, with its text translated by
+/// `provider`. Section names, headers other than text ones, and code are kept as is.
+pub(crate) fn translate(
+    provider: &dyn Provider,
+    id: &str,
+    source: &str,
+    source_language: &str,
+    target_language: &str,
+) -> fpm::Result<(String, usize)> {
+    let segments = fpm::segment::segments(id, source);
+    let texts = segments
+        .iter()
+        .map(|v| v.text.to_string())
+        .collect::<Vec<String>>();
+    let translations = provider.translate(&texts, source_language, target_language)?;
+    if translations.len() != texts.len() {
+        return Err(fpm::Error::UsageError {
+            message: format!(
+                "expected {} translations, the provider returned {}",
+                texts.len(),
+                translations.len()
+            ),
+        });
+    }
+    let translations = segments
+        .iter()
+        .map(|v| v.id.to_string())
+        .zip(translations)
+        .collect::<std::collections::BTreeMap<String, String>>();
+    Ok((
+        fpm::segment::replace(id, source, |v| translations.get(&v.id).cloned()),
+        texts.len(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn translate() {
+        let source = indoc::indoc! {"
+            -- ftd.text: Hello World
+            color: red
+
+            -- ftd.code:
+            lang: rs
+
+            fn main() {}
+        "};
+        assert_eq!(
+            super::translate(&super::MockProvider, "index.ftd", source, "en", "hi").unwrap(),
+            (source.replace("Hello World", "[hi] Hello World"), 1)
+        );
+    }
+
+    /// `stub()` starts a stand-in translation service, which answers one request with `status` and
+    /// `body`, and returns its url and the request it got.
+    fn stub(status: &str, body: &str) -> (String, std::thread::JoinHandle<String>) {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/translate", listener.local_addr().unwrap());
+        let (status, body) = (status.to_string(), body.to_string());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buf = [0; 4096];
+            // the body follows the headers, and may come in a later read
+            loop {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text[..end]
+                        .lines()
+                        .find_map(|v| {
+                            let (name, value) = v.split_once(':')?;
+                            if name.eq_ignore_ascii_case("content-length") {
+                                value.trim().parse::<usize>().ok()
+                            } else {
+                                None
+                            }
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length || n == 0 {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+            write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (url, server)
+    }
+
+    fn http_provider(url: String, api_key_env: Option<&str>) -> super::HttpProvider {
+        super::HttpProvider {
+            name: "our-mt".to_string(),
+            url,
+            api_key_env: api_key_env.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn http() {
+        use super::Provider;
+
+        let (url, server) = stub("200 OK", r#"{"translations": ["नमस्ते", "दुनिया"]}"#);
+        std::env::set_var("FPM_MT_TEST_API_KEY", "secret");
+        let provider = http_provider(url, Some("FPM_MT_TEST_API_KEY"));
+        assert_eq!(
+            provider
+                .translate(&["Hello".to_string(), "World".to_string()], "en", "hi")
+                .unwrap(),
+            vec!["नमस्ते".to_string(), "दुनिया".to_string()]
+        );
+
+        let request = server.join().unwrap();
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("POST /translate "));
+        assert!(head
+            .lines()
+            .any(|v| v.eq_ignore_ascii_case("authorization: Bearer secret")));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(body).unwrap(),
+            serde_json::json!({"source": "en", "target": "hi", "texts": ["Hello", "World"]})
+        );
+    }
+
+    #[test]
+    fn http_errors() {
+        use super::Provider;
+
+        // the number of translations must match the number of texts
+        let (url, server) = stub("200 OK", r#"{"translations": []}"#);
+        let error = super::translate(
+            &http_provider(url, None),
+            "index.ftd",
+            "-- ftd.text: Hello World\n",
+            "en",
+            "hi",
+        )
+        .unwrap_err();
+        server.join().unwrap();
+        assert!(error
+            .to_string()
+            .contains("expected 1 translations, the provider returned 0"));
+
+        let (url, server) = stub("500 Internal Server Error", "overloaded");
+        let error = http_provider(url, None)
+            .translate(&["Hello".to_string()], "en", "hi")
+            .unwrap_err();
+        server.join().unwrap();
+        assert!(error.to_string().contains("overloaded"));
+
+        // the api key is checked before anything is sent
+        let provider = http_provider(
+            "http://127.0.0.1:1/translate".to_string(),
+            Some("FPM_MT_TEST_UNSET_API_KEY"),
+        );
+        assert!(provider
+            .translate(&["Hello".to_string()], "en", "hi")
+            .unwrap_err()
+            .to_string()
+            .contains("FPM_MT_TEST_UNSET_API_KEY"));
+    }
+
+    #[test]
+    fn provider() {
+        let mut config = fpm::Config::for_test(camino::Utf8PathBuf::from("/tmp/fpm-mt"));
+        config.package.translation_providers = vec![http_provider(
+            "https://mt.example.com/translate".to_string(),
+            None,
+        )];
+
+        let texts = vec!["Hello".to_string()];
+        let mock = super::provider(&config, "mock").ok().unwrap();
+        assert_eq!(
+            mock.translate(&texts, "en", "hi").unwrap(),
+            vec!["[hi] Hello".to_string()]
+        );
+        assert!(super::provider(&config, "our-mt").is_ok());
+        match super::provider(&config, "other") {
+            Err(fpm::Error::UsageError { message }) => {
+                assert!(message.starts_with("unknown translation provider `other`"))
+            }
+            _ => panic!("`other` is not declared"),
+        }
+    }
+}
@@ -21,6 +21,11 @@ async fn main() -> fpm::Result<()> {
     if let Some(build) = matches.subcommand_matches("build") {
         // Evaluate the aliases for the package
         config.package.aliases()?;
+        config.http_cache = fpm::HttpCacheMode::from_flags(
+            build.is_present("refresh-http"),
+            build.value_of("http-fixtures"),
+        )?;
+        attach_data(&mut config, build)?;
         if build.is_present("verbose") {
             println!("{}", fpm::debug_env_vars());
         }
@@ -29,6 +34,7 @@ async fn main() -> fpm::Result<()> {
             build.value_of("file"),
             build.value_of("base").unwrap(), // unwrap okay because base is required
             build.is_present("ignore-failed"),
+            build.is_present("optimize-assets"),
         )
         .await?;
     }
@@ -45,8 +51,57 @@ async fn main() -> fpm::Result<()> {
         let source = status.value_of("source");
         fpm::status(&config, source).await?;
     }
-    if matches.subcommand_matches("translation-status").is_some() {
-        fpm::translation_status(&config).await?;
+    if let Some(status) = matches.subcommand_matches("translation-status") {
+        let values = |name: &str| -> Vec<String> {
+            status
+                .values_of(name)
+                .map(|v| v.map(|v| v.to_string()).collect())
+                .unwrap_or_default()
+        };
+        fpm::translation_status(
+            &mut config,
+            status.value_of("format").unwrap(), // unwrap okay because format has a default
+            &values("status"),
+            &values("fail-on"),
+        )
+        .await?;
+    }
+    if let Some(translation) = matches.subcommand_matches("translation") {
+        if let Some(export) = translation.subcommand_matches("export") {
+            fpm::translation_export(
+                &config,
+                export.value_of("format").unwrap(), // unwrap okay because format is required
+                export.value_of("output"),
+            )
+            .await?;
+        }
+        if let Some(import) = translation.subcommand_matches("import") {
+            fpm::translation_import(
+                &config,
+                import.value_of("file").unwrap(), // unwrap okay because file is required
+                import.value_of("format"),
+            )
+            .await?;
+        }
+        if let Some(lint) = translation.subcommand_matches("lint") {
+            let files = lint
+                .values_of("file")
+                .map(|v| v.map(|v| v.to_string()).collect())
+                .unwrap_or_default();
+            fpm::translation_lint(&config, &files).await?;
+        }
+    }
+    if let Some(translate) = matches.subcommand_matches("translate") {
+        fpm::translate(
+            &config,
+            translate.value_of("file").unwrap(), // unwrap okay because file is required
+            translate.value_of("provider").unwrap(), // unwrap okay because provider is required
+            translate.is_present("force"),
+        )
+        .await?;
+    }
+    if matches.subcommand_matches("check").is_some() {
+        fpm::check(&config).await?;
     }
     if let Some(diff) = matches.subcommand_matches("diff") {
         let all = diff.is_present("all");
@@ -74,8 +129,11 @@ async fn main() -> fpm::Result<()> {
     }
     if let Some(mark) = matches.subcommand_matches("serve") {
         let port = mark.value_of("port").unwrap_or("8000").to_string();
+        attach_data(&mut config, mark)?;
+        let extra_data = config.extra_data.clone();
+        let negotiate_language = mark.is_present("negotiate-language");
         tokio::task::spawn_blocking(move || {
-            fpm::serve(port.as_str()).expect("http service error");
+            fpm::serve(port.as_str(), extra_data, negotiate_language).expect("http service error");
         })
         .await
         .expect("Thread spawn error");
@@ -83,6 +141,47 @@ async fn main() -> fpm::Result<()> {
     Ok(())
 }
 
+/// `attach_data()` adds the `--data` and `--data-file` values to the extra data of `config`, in
+/// the order they are passed.
+fn attach_data(config: &mut fpm::Config, matches: &clap::ArgMatches) -> fpm::Result<()> {
+    let mut data = vec![];
+    for name in ["data", "data-file"] {
+        if let (Some(indices), Some(values)) = (matches.indices_of(name), matches.values_of(name)) {
+            data.extend(indices.zip(values).map(|(i, v)| (i, name, v)));
+        }
+    }
+    data.sort_by_key(|(i, _, _)| *i);
+    for (_, name, value) in data {
+        if name == "data" {
+            config.attach_data_string(value)?;
+        } else {
+            config.attach_data_file(camino::Utf8Path::new(value))?;
+        }
+    }
+    Ok(())
+}
+
+/// `data_args()` returns the `--data` and `--data-file` arguments of `build` and `serve`, see
+/// `attach_data()`.
+fn data_args() -> [clap::Arg<'static, 'static>; 2] {
+    [
+        clap::Arg::with_name("data")
+            .long("data")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .required(false)
+            .help("JSON object whose keys are available to `get-data` processor, can be repeated"),
+        clap::Arg::with_name("data-file")
+            .long("data-file")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .required(false)
+            .help("Same as --data, but read from a JSON file, can be repeated"),
+    ]
+}
+
 fn app(authors: &'static str, version: &'static str) -> clap::App<'static, 'static> {
     clap::App::new("fpm: FTD Package Manager")
         .version(version)
@@ -141,6 +240,29 @@ fn app(authors: &'static str, version: &'static str) -> clap::App<'static, 'stat
                         .takes_value(false)
                         .required(false),
                 )
+                .arg(
+                    clap::Arg::with_name("optimize-assets")
+                        .long("optimize-assets")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Link content hashed js/css instead of inlining them and write .gz/.br files"),
+                )
+                .arg(
+                    clap::Arg::with_name("refresh-http")
+                        .long("refresh-http")
+                        .takes_value(false)
+                        .required(false)
+                        .help("Fetch the `http` processor urls again, ignoring the cached responses"),
+                )
+                .arg(
+                    clap::Arg::with_name("http-fixtures")
+                        .long("http-fixtures")
+                        .takes_value(true)
+                        .possible_values(&["record", "replay"])
+                        .required(false)
+                        .help("Record the `http` processor responses in FPM/http/, or replay them"),
+                )
+                .args(&data_args())
                 .arg(
                     clap::Arg::with_name("verbose")
                         .long("verbose")
@@ -169,9 +291,91 @@ fn app(authors: &'static str, version: &'static str) -> clap::App<'static, 'stat
         )
         .subcommand(
             clap::SubCommand::with_name("translation-status")
+                .arg(
+                    clap::Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json", "csv"])
+                        .default_value("text"),
+                )
+                .arg(
+                    clap::Arg::with_name("status")
+                        .long("status")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .possible_values(&["missing", "never-marked", "outdated", "upto-date"])
+                        .help("Only list the documents with this status, can be repeated"),
+                )
+                .arg(
+                    clap::Arg::with_name("fail-on")
+                        .long("fail-on")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .possible_values(&["missing", "never-marked", "outdated"])
+                        .help("Exit with an error if a document has this status, can be repeated"),
+                )
                 .about("Show the translation status of files in this fpm package")
                 .version(env!("CARGO_PKG_VERSION")),
         )
+        .subcommand(
+            clap::SubCommand::with_name("translation")
+                .about("Exchange translation work with CAT tools")
+                .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    clap::SubCommand::with_name("export")
+                        .arg(
+                            clap::Arg::with_name("format")
+                                .long("format")
+                                .takes_value(true)
+                                .possible_values(&["xliff", "po"])
+                                .required(true),
+                        )
+                        .arg(
+                            clap::Arg::with_name("output")
+                                .long("output")
+                                .short("o")
+                                .takes_value(true)
+                                .help("File to write to, printed if not given"),
+                        )
+                        .about("Export the translation units of the original documents"),
+                )
+                .subcommand(
+                    clap::SubCommand::with_name("import")
+                        .arg(clap::Arg::with_name("file").required(true))
+                        .arg(
+                            clap::Arg::with_name("format")
+                                .long("format")
+                                .takes_value(true)
+                                .possible_values(&["xliff", "po"])
+                                .help("Format of the file, guessed from its extension if not given"),
+                        )
+                        .about("Write the translated documents from an XLIFF or PO file"),
+                )
+                .subcommand(
+                    clap::SubCommand::with_name("lint")
+                        .arg(clap::Arg::with_name("file").multiple(true))
+                        .about("Check the translated documents against FPM/glossary.ftd"),
+                )
+                .version(env!("CARGO_PKG_VERSION")),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("translate")
+                .args(&[
+                    clap::Arg::with_name("file").required(true),
+                    clap::Arg::with_name("provider")
+                        .long("provider")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Name of a `fpm.translation-provider`, or `mock`"),
+                    clap::Arg::with_name("force")
+                        .long("force")
+                        .help("Overwrite the existing translation"),
+                ])
+                .about("Write a draft translation of a document using machine translation")
+                .version(env!("CARGO_PKG_VERSION")),
+        )
         .subcommand(
             clap::SubCommand::with_name("diff")
                 .args(&[
@@ -223,6 +427,12 @@ fn app(authors: &'static str, version: &'static str) -> clap::App<'static, 'stat
         .subcommand(
             clap::SubCommand::with_name("serve")
                 .arg(clap::Arg::with_name("port").required(false))
+                .args(&data_args())
+                .arg(
+                    clap::Arg::with_name("negotiate-language")
+                        .long("negotiate-language")
+                        .help("Redirect `/` to the translation, or the original, in the language the browser prefers"),
+                )
                 .about("Create an http server and serves static files")
                 .version(env!("CARGO_PKG_VERSION")),
         )
@@ -0,0 +1,364 @@
+/// `Meta` is the metadata of a document, used by `fpm::utils::replace_markers()` to generate the
+/// `<meta>`, Open Graph, Twitter card and JSON-LD tags of the page.
+///
+/// A document declares it using `fpm.document-meta`:
+///
+/// ```ftd
+/// -- fpm.document-meta:
+/// description: How to write your first fpm package
+/// image: /static/hello.png
+/// author: Amit Upadhyay
+/// published: 2022-01-17
+/// keywords: fpm, ftd, tutorial
+/// ```
+///
+/// Missing values are taken from the `extra_data` of the document in the sitemap, using the same
+/// keys, and the description falls back to the first paragraph of `package.about`. The fallback
+/// does not count as metadata of the document.
+#[derive(serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Meta {
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub author: Option<String>,
+    pub published: Option<String>,
+    pub updated: Option<String>,
+    pub keywords: Option<String>,
+    /// `about` is the first paragraph of `package.about`, the description of a document which
+    /// does not have one
+    #[serde(skip)]
+    pub about: Option<String>,
+}
+
+/// default territories of languages, for `og:locale` which needs the `en_US` form
+const LOCALE_TERRITORIES: &[(&str, &str)] = &[
+    ("ar", "AR"),
+    ("bn", "IN"),
+    ("de", "DE"),
+    ("en", "US"),
+    ("es", "ES"),
+    ("fr", "FR"),
+    ("gu", "IN"),
+    ("he", "IL"),
+    ("hi", "IN"),
+    ("it", "IT"),
+    ("ja", "JP"),
+    ("kn", "IN"),
+    ("ko", "KR"),
+    ("mr", "IN"),
+    ("nl", "NL"),
+    ("pa", "IN"),
+    ("pl", "PL"),
+    ("pt", "BR"),
+    ("ru", "RU"),
+    ("ta", "IN"),
+    ("te", "IN"),
+    ("tr", "TR"),
+    ("ur", "PK"),
+    ("zh", "CN"),
+];
+
+impl Meta {
+    /// `from_document()` returns the metadata of the document `id` of `package`, `doc` is the
+    /// parsed document.
+    pub(crate) fn from_document(
+        package: &fpm::Package,
+        sitemap: Option<&fpm::sitemap::Sitemap>,
+        id: &str,
+        doc: &ftd::p2::Document,
+    ) -> Meta {
+        let mut meta = doc
+            .get::<Option<Meta>>("fpm#document-meta")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        let path = fpm::utils::id_to_path(id).replace(std::path::MAIN_SEPARATOR, "/");
+        if let Some(extra_data) = sitemap.and_then(|v| v.get_extra_data_by_id(path.as_str())) {
+            let get = |key: &str| {
+                extra_data
+                    .get(key)
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+            };
+            meta.description = meta.description.or_else(|| get("description"));
+            meta.image = meta.image.or_else(|| get("image"));
+            meta.author = meta.author.or_else(|| get("author"));
+            meta.published = meta.published.or_else(|| get("published"));
+            meta.updated = meta.updated.or_else(|| get("updated"));
+            meta.keywords = meta.keywords.or_else(|| get("keywords"));
+        }
+
+        if meta.description.is_none() {
+            meta.about = package.about.as_ref().and_then(|v| {
+                v.trim()
+                    .split("\n\n")
+                    .next()
+                    .map(|v| v.split_whitespace().collect::<Vec<&str>>().join(" "))
+                    .filter(|v| !v.is_empty())
+            });
+        }
+        meta
+    }
+
+    /// `is_empty()` tells if the document has no metadata of its own.
+    fn is_empty(&self) -> bool {
+        Meta {
+            about: None,
+            ..self.clone()
+        }
+        .eq(&Meta::default())
+    }
+
+    /// `to_html()` returns the html that goes in the `__ftd_meta__` marker. The Open Graph and
+    /// Twitter card tags are generated for every document, using its title, the JSON-LD is
+    /// generated only for a document with metadata.
+    pub(crate) fn to_html(&self, package: &fpm::Package, main_id: &str, title: &str) -> String {
+        let escape = fpm::artifacts::escape;
+        let description = self.description.clone().or_else(|| self.about.clone());
+        // Open Graph and JSON-LD need absolute urls, which are only known with `canonical-url`
+        let url = package
+            .canonical_url
+            .as_ref()
+            .map(|_| fpm::artifacts::document_url(package, main_id));
+        let image = self.image.as_ref().map(|v| match package.canonical_url {
+            Some(_) => fpm::artifacts::join(fpm::artifacts::base_url(package).as_str(), v),
+            None => v.to_string(),
+        });
+        let published = self
+            .published
+            .as_ref()
+            .and_then(|v| fpm::artifacts::parse_date(v))
+            .map(|v| v.to_rfc3339());
+        let updated = self
+            .updated
+            .as_ref()
+            .and_then(|v| fpm::artifacts::parse_date(v))
+            .map(|v| v.to_rfc3339());
+
+        let mut tags = vec![];
+        let mut name = |key: &str, value: &Option<String>| {
+            if let Some(value) = value {
+                tags.push(format!(
+                    "<meta name=\"{}\" content=\"{}\">",
+                    key,
+                    escape(value)
+                ));
+            }
+        };
+        name("description", &description);
+        name("keywords", &self.keywords);
+        name("author", &self.author);
+        name(
+            "twitter:card",
+            &Some(
+                if image.is_some() {
+                    "summary_large_image"
+                } else {
+                    "summary"
+                }
+                .to_string(),
+            ),
+        );
+        name("twitter:title", &Some(title.to_string()));
+        name("twitter:description", &description);
+        name("twitter:image", &image);
+
+        let mut property = |key: &str, value: &Option<String>| {
+            if let Some(value) = value {
+                tags.push(format!(
+                    "<meta property=\"{}\" content=\"{}\">",
+                    key,
+                    escape(value)
+                ));
+            }
+        };
+        let is_article = published.is_some() || self.author.is_some();
+        property(
+            "og:type",
+            &Some(if is_article { "article" } else { "website" }.to_string()),
+        );
+        property("og:title", &Some(title.to_string()));
+        property("og:site_name", &Some(package.name.to_string()));
+        property("og:url", &url);
+        property("og:description", &description);
+        property("og:image", &image);
+        property(
+            "og:locale",
+            &package.language.as_deref().and_then(og_locale),
+        );
+        property("article:published_time", &published);
+        property("article:modified_time", &updated);
+        property("article:author", &self.author);
+
+        if self.is_empty() {
+            return tags.join("");
+        }
+
+        let mut json_ld = serde_json::json!({
+            "@context": "https://schema.org",
+            "@type": if is_article { "Article" } else { "WebPage" },
+            "headline": title,
+        });
+        let mut insert = |key: &str, value: Option<serde_json::Value>| {
+            if let Some(value) = value {
+                json_ld[key] = value;
+            }
+        };
+        insert("url", url.map(serde_json::Value::from));
+        insert("description", description.map(serde_json::Value::from));
+        insert("image", image.map(serde_json::Value::from));
+        insert(
+            "author",
+            self.author
+                .as_ref()
+                .map(|v| serde_json::json!({"@type": "Person", "name": v})),
+        );
+        insert("datePublished", published.map(serde_json::Value::from));
+        insert("dateModified", updated.map(serde_json::Value::from));
+        insert(
+            "keywords",
+            self.keywords.clone().map(serde_json::Value::from),
+        );
+        insert(
+            "inLanguage",
+            package.language.clone().map(serde_json::Value::from),
+        );
+        tags.push(format!(
+            "<script type=\"application/ld+json\">{}</script>",
+            json_ld.to_string().replace("</", "<\\/")
+        ));
+
+        tags.join("")
+    }
+}
+
+/// `og_locale()` returns the Open Graph locale of the language, `en_US` for `en` or `en-US`, or
+/// `None` for a language without a known territory.
+fn og_locale(language: &str) -> Option<String> {
+    let mut parts = language.trim().split(|c| c == '-' || c == '_');
+    let language = parts.next()?.to_lowercase();
+    let territory = match parts.next().filter(|v| v.len() == 2) {
+        Some(territory) => territory.to_uppercase(),
+        None => LOCALE_TERRITORIES
+            .iter()
+            .find(|(l, _)| *l == language.as_str())
+            .map(|(_, t)| t.to_string())?,
+    };
+    Some(format!("{}_{}", language, territory))
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn from_document() {
+        let mut package = fpm::Package::new("fifthtry.com");
+        package.about =
+            Some("FPM is the package manager\nfor ftd.\n\nIt builds sites.".to_string());
+        let lib = fpm::FPMLibrary::default();
+
+        let doc = fpm::doc::parse_ftd(
+            "index.ftd",
+            indoc::indoc! {"
+                -- import: fpm
+
+                -- fpm.document-meta:
+                description: How to write your first fpm package
+                author: Amit Upadhyay
+            "},
+            &lib,
+        )
+        .unwrap();
+        let meta = super::Meta::from_document(&package, None, "index.ftd", &doc);
+        assert_eq!(
+            meta.description.as_deref(),
+            Some("How to write your first fpm package")
+        );
+        assert_eq!(meta.author.as_deref(), Some("Amit Upadhyay"));
+
+        // the description falls back to the first paragraph of `package.about`
+        let doc = fpm::doc::parse_ftd("index.ftd", "-- ftd.text: Hello", &lib).unwrap();
+        assert_eq!(
+            super::Meta::from_document(&package, None, "index.ftd", &doc),
+            super::Meta {
+                about: Some("FPM is the package manager for ftd.".to_string()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn og_locale() {
+        assert_eq!(super::og_locale("en").as_deref(), Some("en_US"));
+        assert_eq!(super::og_locale("en-GB").as_deref(), Some("en_GB"));
+        assert_eq!(super::og_locale("pt_br").as_deref(), Some("pt_BR"));
+        assert_eq!(super::og_locale("hi").as_deref(), Some("hi_IN"));
+        assert_eq!(super::og_locale("xx"), None);
+    }
+
+    #[test]
+    fn to_html_with_about() {
+        // the description of the package is not metadata of the document, so there is no JSON-LD
+        let meta = super::Meta {
+            about: Some("FPM is the package manager for ftd.".to_string()),
+            ..Default::default()
+        };
+        let html = meta.to_html(&fpm::Package::new("fifthtry.com"), "/", "Hello");
+        assert!(html.contains(
+            "<meta name=\"description\" content=\"FPM is the package manager for ftd.\">"
+        ));
+        assert!(html.contains(
+            "<meta property=\"og:description\" content=\"FPM is the package manager for ftd.\">"
+        ));
+        assert!(!html.contains("ld+json"));
+    }
+
+    #[test]
+    fn to_html_without_metadata() {
+        assert_eq!(
+            super::Meta::default().to_html(&fpm::Package::new("fifthtry.com"), "/", "Hello & Bye"),
+            "<meta name=\"twitter:card\" content=\"summary\">\
+            <meta name=\"twitter:title\" content=\"Hello &amp; Bye\">\
+            <meta property=\"og:type\" content=\"website\">\
+            <meta property=\"og:title\" content=\"Hello &amp; Bye\">\
+            <meta property=\"og:site_name\" content=\"fifthtry.com\">"
+        );
+    }
+
+    #[test]
+    fn to_html() {
+        let mut package = fpm::Package::new("fifthtry.com");
+        package.canonical_url = Some("https://www.fifthtry.com".to_string());
+        package.language = Some("en".to_string());
+        let meta = super::Meta {
+            description: Some("How to write your first fpm package".to_string()),
+            image: Some("/static/hello.png".to_string()),
+            author: Some("Amit Upadhyay".to_string()),
+            published: Some("2022-01-17".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            meta.to_html(&package, "blog/hello/", "Hello"),
+            "<meta name=\"description\" content=\"How to write your first fpm package\">\
+            <meta name=\"author\" content=\"Amit Upadhyay\">\
+            <meta name=\"twitter:card\" content=\"summary_large_image\">\
+            <meta name=\"twitter:title\" content=\"Hello\">\
+            <meta name=\"twitter:description\" content=\"How to write your first fpm package\">\
+            <meta name=\"twitter:image\" content=\"https://www.fifthtry.com/static/hello.png\">\
+            <meta property=\"og:type\" content=\"article\">\
+            <meta property=\"og:title\" content=\"Hello\">\
+            <meta property=\"og:site_name\" content=\"fifthtry.com\">\
+            <meta property=\"og:url\" content=\"https://www.fifthtry.com/blog/hello/\">\
+            <meta property=\"og:description\" content=\"How to write your first fpm package\">\
+            <meta property=\"og:image\" content=\"https://www.fifthtry.com/static/hello.png\">\
+            <meta property=\"og:locale\" content=\"en_US\">\
+            <meta property=\"article:published_time\" content=\"2022-01-17T00:00:00+00:00\">\
+            <meta property=\"article:author\" content=\"Amit Upadhyay\">\
+            <script type=\"application/ld+json\">{\"@context\":\"https://schema.org\",\
+            \"@type\":\"Article\",\"author\":{\"@type\":\"Person\",\"name\":\"Amit Upadhyay\"},\
+            \"datePublished\":\"2022-01-17T00:00:00+00:00\",\
+            \"description\":\"How to write your first fpm package\",\"headline\":\"Hello\",\
+            \"image\":\"https://www.fifthtry.com/static/hello.png\",\"inLanguage\":\"en\",\
+            \"url\":\"https://www.fifthtry.com/blog/hello/\"}</script>"
+        );
+    }
+}
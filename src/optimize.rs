@@ -0,0 +1,169 @@
+/// `SharedAssets` keeps the urls of the javascript and css files that are otherwise inlined in
+/// every generated page.
+///
+/// When `fpm build --optimize-assets` is used, `ftd_js`, `ftd_css` and `fpm_js` are written once
+/// in `.build/-/fpm/` with the content hash in their file name, and `fpm::utils::replace_markers()`
+/// links to them instead of inlining them. As the file name changes whenever the content
+/// changes, these files can be served with long lived cache headers.
+#[derive(Debug, Clone)]
+pub struct SharedAssets {
+    pub ftd_js: String,
+    pub ftd_css: String,
+    pub fpm_js: String,
+}
+
+/// Extensions for which `precompress()` writes `.gz` and `.br` siblings.
+const COMPRESSIBLE_EXTENSIONS: &[&str] = &["html", "css", "js", "svg"];
+
+impl SharedAssets {
+    /// `write()` writes the content hashed shared assets in `.build/-/fpm/` and returns their
+    /// urls with respect to `base_url`.
+    pub(crate) fn write(config: &fpm::Config, base_url: &str) -> fpm::Result<SharedAssets> {
+        let dir = config.build_dir().join("-").join("fpm");
+        std::fs::create_dir_all(&dir)?;
+        let base_url = format!("{}/", base_url.trim_end_matches('/'));
+
+        let write_asset = |name: &str, ext: &str, content: &str| -> fpm::Result<String> {
+            let file_name = fingerprinted_name(name, ext, content.as_bytes());
+            std::fs::write(dir.join(file_name.as_str()), content)?;
+            Ok(format!("{}-/fpm/{}", base_url, file_name))
+        };
+
+        Ok(SharedAssets {
+            ftd_js: write_asset("ftd", "js", fpm::ftd_js().as_str())?,
+            ftd_css: write_asset("ftd", "css", fpm::ftd_css())?,
+            fpm_js: write_asset("fpm", "js", fpm::fpm_js())?,
+        })
+    }
+
+    /// `stylesheets()` returns the html that goes in `__ftd_stylesheets__` marker.
+    pub(crate) fn stylesheets(&self) -> String {
+        format!("<link rel=\"stylesheet\" href=\"{}\">", self.ftd_css)
+    }
+
+    /// `scripts()` returns the html that goes in `__ftd_scripts__` marker. `fpm_js` only
+    /// defines functions, so it is safe to load it before `window.ftd.init()` is called.
+    pub(crate) fn scripts(&self) -> String {
+        format!(
+            "<script src=\"{}\"></script><script src=\"{}\"></script>",
+            self.ftd_js, self.fpm_js
+        )
+    }
+}
+
+/// `fingerprinted_name()` returns `<name>.<first 16 hex digits of sha256>.<ext>`.
+fn fingerprinted_name(name: &str, ext: &str, content: &[u8]) -> String {
+    use sha2::Digest;
+
+    let hash = format!("{:x}", sha2::Sha256::digest(content));
+    format!("{}.{}.{}", name, &hash[..16], ext)
+}
+
+/// `precompress()` walks the build directory and writes a `.gz` and a `.br` sibling for every
+/// html, css, js and svg file in it, so a web server can serve the precompressed version
+/// directly.
+pub(crate) fn precompress(build_dir: &camino::Utf8Path) -> fpm::Result<()> {
+    use std::io::Write;
+
+    let files = ignore::WalkBuilder::new(build_dir)
+        .hidden(false)
+        .git_ignore(false)
+        .build()
+        .flatten()
+        .filter(|e| e.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .map(|e| e.into_path())
+        .collect::<Vec<std::path::PathBuf>>();
+
+    for path in files {
+        let compressible = path
+            .extension()
+            .and_then(|v| v.to_str())
+            .map(|v| COMPRESSIBLE_EXTENSIONS.contains(&v))
+            .unwrap_or(false);
+        if !compressible {
+            continue;
+        }
+        let content = std::fs::read(&path)?;
+
+        {
+            let mut encoder = flate2::write::GzEncoder::new(
+                std::fs::File::create(sibling(&path, "gz"))?,
+                flate2::Compression::best(),
+            );
+            encoder.write_all(&content)?;
+            encoder.finish()?;
+        }
+
+        // `brotli::CompressorWriter` finishes the stream when dropped, and ignores the errors
+        brotli::BrotliCompress(
+            &mut content.as_slice(),
+            &mut std::fs::File::create(sibling(&path, "br"))?,
+            &brotli::enc::BrotliEncoderParams {
+                quality: 11,
+                lgwin: 22,
+                ..Default::default()
+            },
+        )?;
+    }
+    return Ok(());
+
+    fn sibling(path: &std::path::Path, ext: &str) -> std::path::PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".");
+        name.push(ext);
+        std::path::PathBuf::from(name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn fingerprinted_name() {
+        let first = super::fingerprinted_name("ftd", "js", b"console.log(1)");
+        let second = super::fingerprinted_name("ftd", "js", b"console.log(2)");
+        assert!(first.starts_with("ftd.") && first.ends_with(".js"));
+        assert_eq!(first.len(), "ftd..js".len() + 16);
+        assert_ne!(first, second);
+        assert_eq!(
+            first,
+            super::fingerprinted_name("ftd", "js", b"console.log(1)")
+        );
+    }
+
+    #[test]
+    fn precompress() {
+        use std::io::Read;
+
+        let dir = std::env::temp_dir().join(format!("fpm-precompress-{}", std::process::id()));
+        let dir = camino::Utf8PathBuf::from_path_buf(dir).unwrap();
+        std::fs::create_dir_all(dir.join("-")).unwrap();
+        let html = "<html><body>Hello World</body></html>".repeat(100);
+        std::fs::write(dir.join("index.html"), html.as_str()).unwrap();
+        std::fs::write(dir.join("-").join("fpm.js"), "function f() {}").unwrap();
+        std::fs::write(dir.join("logo.png"), [0u8, 1, 2]).unwrap();
+
+        super::precompress(&dir).unwrap();
+
+        let mut gz = String::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(dir.join("index.html.gz")).unwrap())
+            .read_to_string(&mut gz)
+            .unwrap();
+        assert_eq!(gz, html);
+
+        let mut br = String::new();
+        brotli::Decompressor::new(
+            std::fs::File::open(dir.join("index.html.br")).unwrap(),
+            4096,
+        )
+        .read_to_string(&mut br)
+        .unwrap();
+        assert_eq!(br, html);
+
+        assert!(dir.join("-").join("fpm.js.gz").exists());
+        assert!(dir.join("-").join("fpm.js.br").exists());
+        assert!(!dir.join("logo.png.gz").exists());
+        assert!(!dir.join("logo.png.br").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
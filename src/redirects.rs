@@ -0,0 +1,194 @@
+/// `Redirect` is the `fpm.redirect` entry in `FPM.ftd`. It keeps the old url of a moved document
+/// working:
+///
+/// ```ftd
+/// -- fpm.redirect: guide/install/
+/// to: /docs/install/
+///
+/// -- fpm.redirect: old-blog/
+/// to: https://blog.example.com/
+/// permanent: false
+/// ```
+///
+/// `from` is a document id, eg `guide/install.ftd` or `guide/install/`. `to` is either an url, or
+/// a path relative to the package root. `fpm build` writes an html page with `<meta refresh>` at
+/// `from`, and `fpm serve` answers `from` with a `301` (or `302` if not `permanent`).
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct Redirect {
+    pub from: String,
+    pub to: String,
+    pub permanent: bool,
+}
+
+impl Redirect {
+    /// `path()` returns `from` as a path relative to the package root, eg `guide/install`.
+    pub(crate) fn path(&self) -> String {
+        normalize(self.from.trim())
+    }
+
+    /// `url()` returns the url to redirect to, `to` paths are resolved with respect to
+    /// `base_url`.
+    pub(crate) fn url(&self, base_url: &str) -> String {
+        let to = self.to.trim();
+        if to.contains("://") {
+            return to.to_string();
+        }
+        format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            to.trim_start_matches('/')
+        )
+    }
+}
+
+/// `find()` returns the redirect for the document `id`, if any. `id` can be a document id or
+/// an url path.
+pub(crate) fn find<'a>(package: &'a fpm::Package, id: &str) -> Option<&'a Redirect> {
+    let path = normalize(id);
+    package.redirects.iter().find(|r| r.path().eq(&path))
+}
+
+/// `normalize()` turns a document id or an url path into a path relative to the package root,
+/// eg both `guide/install.ftd` and `/guide/install/` become `guide/install`.
+pub(crate) fn normalize(id: &str) -> String {
+    fpm::utils::id_to_path(id)
+        .replace(std::path::MAIN_SEPARATOR, "/")
+        .trim_matches('/')
+        .to_string()
+}
+
+/// `write()` writes a redirect page in the build directory for each `fpm.redirect`. If the
+/// document at `from` still exists, its html is replaced by the redirect page. Redirecting the
+/// root of the package is an error, as it would replace the `index.html` of the package.
+pub(crate) fn write(config: &fpm::Config, base_url: &str) -> fpm::Result<()> {
+    use colorize::AnsiColor;
+
+    for redirect in config.package.redirects.iter() {
+        let path = redirect.path();
+        if path.is_empty() {
+            return Err(fpm::Error::UsageError {
+                message: format!(
+                    "fpm.redirect: `{}` is the root of the package, it can not be redirected",
+                    redirect.from
+                ),
+            });
+        }
+        let url = redirect.url(base_url);
+        let canonical = match config.package.canonical_url {
+            Some(_) => redirect.url(fpm::artifacts::base_url(&config.package).as_str()),
+            None => url.to_string(),
+        };
+        let dir = config.build_dir().join(path.as_str());
+        let file = dir.join("index.html");
+        if file.exists() {
+            warning!(format!(
+                "`{}` is redirected to `{}`, its built document is replaced by the redirect",
+                redirect.from, redirect.to
+            ));
+        }
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(
+            file,
+            format!(
+                indoc::indoc! {"
+                    <!DOCTYPE html>
+                    <html>
+                        <head>
+                            <meta charset=\"UTF-8\">
+                            <title>Redirecting to {url}</title>
+                            <meta http-equiv=\"refresh\" content=\"0; url={url}\">
+                            <link rel=\"canonical\" href=\"{canonical}\">
+                            <meta name=\"robots\" content=\"noindex\">
+                        </head>
+                        <body>
+                            <a href=\"{url}\">{url}</a>
+                        </body>
+                    </html>
+                "},
+                url = fpm::artifacts::escape(url.as_str()),
+                canonical = fpm::artifacts::escape(canonical.as_str()),
+            ),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    fn redirect(from: &str, to: &str, permanent: bool) -> super::Redirect {
+        super::Redirect {
+            from: from.to_string(),
+            to: to.to_string(),
+            permanent,
+        }
+    }
+
+    #[test]
+    fn path() {
+        assert_eq!(
+            redirect("guide/install.ftd", "/", true).path(),
+            "guide/install"
+        );
+        assert_eq!(
+            redirect("guide/install/", "/", true).path(),
+            "guide/install"
+        );
+        assert_eq!(redirect("/guide/index.ftd", "/", true).path(), "guide");
+        assert_eq!(redirect("guide/README.md", "/", true).path(), "guide");
+        assert_eq!(redirect("/", "/", true).path(), "");
+        assert_eq!(redirect("index.ftd", "/", true).path(), "");
+    }
+
+    #[test]
+    fn url() {
+        let r = redirect("a/", "/docs/install/", true);
+        assert_eq!(r.url("/"), "/docs/install/");
+        assert_eq!(
+            r.url("https://example.com/"),
+            "https://example.com/docs/install/"
+        );
+        let r = redirect("a/", "https://blog.example.com/", false);
+        assert_eq!(r.url("https://example.com/"), "https://blog.example.com/");
+    }
+
+    #[test]
+    fn find() {
+        let mut package = fpm::Package::new("fpm-redirect-test");
+        package.redirects = vec![
+            redirect("guide/install/", "/docs/install/", true),
+            redirect("old-blog.ftd", "https://blog.example.com/", false),
+        ];
+        let found = |id: &str| super::find(&package, id).map(|r| r.to.as_str());
+        assert_eq!(found("guide/install.ftd"), Some("/docs/install/"));
+        assert_eq!(found("guide/install/index.ftd"), Some("/docs/install/"));
+        assert_eq!(found("/guide/install/"), Some("/docs/install/"));
+        assert_eq!(found("old-blog/"), Some("https://blog.example.com/"));
+        assert_eq!(found("guide/"), None);
+        assert_eq!(found("docs/install/"), None);
+    }
+
+    #[test]
+    fn write() {
+        let root = camino::Utf8PathBuf::from_path_buf(
+            std::env::temp_dir().join(format!("fpm-redirect-{}", std::process::id())),
+        )
+        .unwrap();
+        let mut config = fpm::Config::for_test(root.clone());
+        config.package.redirects = vec![redirect("guide/install.ftd", "/docs/install/", true)];
+        super::write(&config, "/").unwrap();
+        let html =
+            std::fs::read_to_string(config.build_dir().join("guide/install/index.html")).unwrap();
+        assert!(html.contains("<meta http-equiv=\"refresh\" content=\"0; url=/docs/install/\">"));
+        assert!(html.contains("<link rel=\"canonical\" href=\"/docs/install/\">"));
+
+        for from in ["/", "index.ftd"] {
+            config.package.redirects = vec![redirect(from, "/docs/", true)];
+            assert!(matches!(
+                super::write(&config, "/"),
+                Err(fpm::Error::UsageError { .. })
+            ));
+        }
+        assert!(!config.build_dir().join("index.html").exists());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
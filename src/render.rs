@@ -10,10 +10,11 @@ pub async fn render(config: &fpm::Config, id: &str, base_url: &str) -> fpm::Resu
         _ => unreachable!(),
     };
 
+    let content = fpm::transclude::expand(config, &main.id, main.content.as_str())?;
     let new_main = fpm::Document {
         content: config
             .package
-            .get_prefixed_body(main.content.as_str(), &main.id, true),
+            .get_prefixed_body(content.as_str(), &main.id, true),
         id: main.id.to_owned(),
         parent_path: main.parent_path.to_owned(),
         package_name: main.package_name.to_owned(),
@@ -43,7 +44,12 @@ pub async fn render(config: &fpm::Config, id: &str, base_url: &str) -> fpm::Resu
                 Ok(v) => v,
                 Err(e) => {
                     return Err(fpm::Error::PackageError {
-                        message: format!("failed to parse {:?}", &e),
+                        message: format!(
+                            "failed to parse {:?}",
+                            config
+                                .transclusions
+                                .locate(&main.id, main.content.as_str(), e)
+                        ),
                     });
                 }
             };
@@ -55,10 +61,17 @@ pub async fn render(config: &fpm::Config, id: &str, base_url: &str) -> fpm::Resu
         Ok(fpm::utils::replace_markers(
             fpm::ftd_html(),
             config,
+            fpm::utils::document_language(config, None),
             main.id_to_path().as_str(),
             doc_title.as_str(),
             base_url,
             &ftd_doc,
+            &fpm::meta::Meta::from_document(
+                &config.package,
+                config.sitemap.as_ref(),
+                main.id.as_str(),
+                &main_ftd_doc,
+            ),
         ))
     }
 }
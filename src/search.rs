@@ -0,0 +1,401 @@
+/// `SearchIndex` collects the text of every document rendered by `fpm build`, so a prebuilt
+/// inverted index can be written in `.build` once all documents are processed.
+///
+/// The index is queried client side by the `search` component in `fpm-lib.ftd`. Each version of
+/// a versioned package gets its own index in `.build/<version>/-/search/index.json`, documents
+/// not belonging to any version go in `.build/-/search/index.json`. A translation package is
+/// built separately, so its index only contains documents in its own language.
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    scopes: std::sync::Arc<std::sync::Mutex<Scopes>>,
+}
+
+/// scope (version or empty string) -> document url -> entry
+type Scopes = std::collections::BTreeMap<String, std::collections::BTreeMap<String, Entry>>;
+
+#[derive(Debug, Clone)]
+struct Entry {
+    /// `language` is the language of the rendered document, the original language for a
+    /// document of a translation package which is rendered from the original
+    language: String,
+    title: String,
+    headings: Vec<String>,
+    body: String,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct IndexFile {
+    language: Option<String>,
+    documents: Vec<IndexDocument>,
+    /// term -> list of `[document index, weight]`
+    terms: std::collections::BTreeMap<String, Vec<(usize, usize)>>,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct IndexDocument {
+    url: String,
+    /// `language` is set only when it is not the `language` of the index
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    title: String,
+    snippet: String,
+}
+
+const TITLE_WEIGHT: usize = 10;
+const HEADING_WEIGHT: usize = 5;
+const BODY_WEIGHT: usize = 1;
+const SNIPPET_LENGTH: usize = 160;
+
+lazy_static! {
+    static ref SCRIPT_OR_STYLE: regex::Regex =
+        regex::Regex::new(r"(?is)<script[^>]*>.*?</script>|<style[^>]*>.*?</style>").unwrap();
+    static ref TAG: regex::Regex = regex::Regex::new(r"(?s)<[^>]*>").unwrap();
+}
+
+impl SearchIndex {
+    /// `add()` adds the document `id` in `language` to the index: its title, the headings of
+    /// `doc` and the body text of `rt`, the rendered `doc`. `fpm` generated pages (`-/`, `FPM/`)
+    /// are not indexed.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn add(
+        &self,
+        config: &fpm::Config,
+        id: &str,
+        language: &str,
+        title: &str,
+        doc: &ftd::p2::Document,
+        rt: &ftd::Document,
+    ) {
+        if id.starts_with("-.") || id.starts_with("-/") || id.starts_with("FPM/") {
+            return;
+        }
+        let html = SCRIPT_OR_STYLE.replace_all(rt.html.as_str(), " ");
+        let mut headings = vec![];
+        collect_headings(&doc.main.container.children, &mut headings);
+        let (scope, url) = scope_and_url(&config.package, id);
+        self.scopes
+            .lock()
+            .unwrap()
+            .entry(scope)
+            .or_default()
+            .insert(
+                url,
+                Entry {
+                    language: language.to_string(),
+                    title: title.to_string(),
+                    headings,
+                    body: to_text(&html),
+                },
+            );
+    }
+
+    /// `copy()` indexes document `to_id` with the content of the already indexed `from_id`.
+    /// Versioned builds copy the rendered html of documents unchanged since an older version
+    /// instead of building them again.
+    pub(crate) fn copy(&self, config: &fpm::Config, from_id: &str, to_id: &str) {
+        let (from_scope, from_url) = scope_and_url(&config.package, from_id);
+        let (to_scope, to_url) = scope_and_url(&config.package, to_id);
+        let mut scopes = self.scopes.lock().unwrap();
+        let entry = match scopes.get(&from_scope).and_then(|v| v.get(&from_url)) {
+            Some(entry) => entry.clone(),
+            None => return,
+        };
+        scopes.entry(to_scope).or_default().insert(to_url, entry);
+    }
+
+    /// `write()` writes one `-/search/index.json` per scope in the build directory.
+    pub(crate) fn write(&self, config: &fpm::Config) -> fpm::Result<()> {
+        let scopes = self.scopes.lock().unwrap();
+        for (scope, entries) in scopes.iter() {
+            let mut index = IndexFile {
+                language: config.package.language.clone(),
+                documents: vec![],
+                terms: Default::default(),
+            };
+            for (url, entry) in entries {
+                let doc_index = index.documents.len();
+                let mut weights: std::collections::BTreeMap<String, usize> = Default::default();
+                let mut count = |text: &str, weight: usize| {
+                    for term in tokenize(text) {
+                        *weights.entry(term).or_insert(0) += weight;
+                    }
+                };
+                count(entry.title.as_str(), TITLE_WEIGHT);
+                for heading in entry.headings.iter() {
+                    count(heading.as_str(), HEADING_WEIGHT);
+                }
+                count(entry.body.as_str(), BODY_WEIGHT);
+                for (term, weight) in weights {
+                    index
+                        .terms
+                        .entry(term)
+                        .or_default()
+                        .push((doc_index, weight));
+                }
+                index.documents.push(IndexDocument {
+                    url: url.to_string(),
+                    language: Some(entry.language.to_string())
+                        .filter(|v| Some(v) != config.package.language.as_ref()),
+                    title: entry.title.to_string(),
+                    snippet: entry.body.chars().take(SNIPPET_LENGTH).collect(),
+                });
+            }
+
+            let dir = if scope.is_empty() {
+                config.build_dir()
+            } else {
+                config.build_dir().join(scope)
+            }
+            .join("-")
+            .join("search");
+            std::fs::create_dir_all(&dir)?;
+            std::fs::write(dir.join("index.json"), serde_json::to_string(&index)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// `index_url()` returns the url of the search index of the scope the page at `path`, like
+/// `v1.0/guide/`, belongs to. Versioned pages are built with the url of their version as
+/// `base_url`, so the scope is stripped from it to get the url of the package.
+pub(crate) fn index_url(package: &fpm::Package, path: &str, base_url: &str) -> String {
+    let (scope, _) = scope_and_url(package, path);
+    if scope.is_empty() {
+        return format!("{}-/search/index.json", base_url);
+    }
+    let root = base_url
+        .strip_suffix(format!("{}/", scope).as_str())
+        .unwrap_or(base_url);
+    format!("{}{}/-/search/index.json", root, scope)
+}
+
+/// `scope_and_url()` returns the version the document `id` belongs to (empty string for
+/// non-versioned documents) and its url relative to that version.
+fn scope_and_url(package: &fpm::Package, id: &str) -> (String, String) {
+    let path = fpm::utils::id_to_path(id)
+        .replace(std::path::MAIN_SEPARATOR, "/")
+        .trim_start_matches('/')
+        .to_string();
+    if package.versioned {
+        if let Some((version, rest)) = path.split_once('/') {
+            if fpm::Version::parse(version).is_ok() {
+                return (version.to_string(), rest.to_string());
+            }
+        }
+    }
+    ("".to_string(), path)
+}
+
+/// `collect_headings()` collects the text of the headings in `children`, the sections ftd nests
+/// under an `h0` to `h7` region, in document order. The text of a heading is the same one
+/// `ftd::p2::Document::title()` uses for the title of the document.
+fn collect_headings(children: &[ftd::Element], headings: &mut Vec<String>) {
+    for child in children {
+        let (common, container) = match child {
+            ftd::Element::Column(ftd::Column {
+                common, container, ..
+            })
+            | ftd::Element::Row(ftd::Row {
+                common, container, ..
+            }) => (common, container),
+            _ => continue,
+        };
+        if let Some(region) = common.region.as_ref().filter(|r| r.is_heading()) {
+            if let Some(heading) =
+                ftd::p2::Document::get_heading(std::slice::from_ref(child), &|r| r == region)
+            {
+                let heading = to_text(heading.original.as_str());
+                if !heading.is_empty() {
+                    headings.push(heading);
+                }
+            }
+        }
+        collect_headings(&container.children, headings);
+    }
+}
+
+/// `to_text()` strips the tags from `html`, decodes the common entities and collapses the
+/// whitespace.
+fn to_text(html: &str) -> String {
+    TAG.replace_all(html, " ")
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// `tokenize()` lowercases `text` and splits it on whitespace and punctuation. Letters are not
+/// checked with `char::is_alphanumeric()` as that rejects the combining marks used by scripts
+/// like Devanagari, which would split the words of such languages.
+fn tokenize(text: &str) -> Vec<String> {
+    const PUNCTUATION: &[char] = &[
+        '।', '॥', '“', '”', '‘', '’', '—', '–', '…', '«', '»', '、', '。', '，', '؟', '،',
+    ];
+    text.to_lowercase()
+        .split(|c: char| c.is_whitespace() || c.is_ascii_punctuation() || PUNCTUATION.contains(&c))
+        .filter(|v| v.chars().count() > 1)
+        .map(|v| v.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn tokenize() {
+        assert_eq!(
+            super::tokenize("Hello, World! It's FPM's 2nd-release."),
+            vec!["hello", "world", "it", "fpm", "2nd", "release"]
+        );
+        // combining marks keep Devanagari words together, `।` ends a sentence
+        assert_eq!(
+            super::tokenize("नमस्ते दुनिया। फ़ाइल"),
+            vec!["नमस्ते", "दुनिया", "फ़ाइल"]
+        );
+    }
+
+    #[test]
+    fn to_text() {
+        assert_eq!(
+            super::to_text("<p>Hello&nbsp;<b>World</b></p>\n<p>a &lt; b &amp;&amp; c</p>"),
+            "Hello World a < b && c"
+        );
+    }
+
+    #[test]
+    fn scope_and_url() {
+        let mut package = fpm::Package::new("fifthtry.com");
+        assert_eq!(
+            super::scope_and_url(&package, "index.ftd"),
+            ("".to_string(), "".to_string())
+        );
+        assert_eq!(
+            super::scope_and_url(&package, "v1.0/guide/index.ftd"),
+            ("".to_string(), "v1.0/guide/".to_string())
+        );
+        package.versioned = true;
+        assert_eq!(
+            super::scope_and_url(&package, "v1.0/guide/index.ftd"),
+            ("v1.0".to_string(), "guide/".to_string())
+        );
+        assert_eq!(
+            super::scope_and_url(&package, "guide/install.ftd"),
+            ("".to_string(), "guide/install/".to_string())
+        );
+    }
+
+    #[test]
+    fn index_url() {
+        let mut package = fpm::Package::new("fifthtry.com");
+        assert_eq!(
+            super::index_url(&package, "guide/", "/"),
+            "/-/search/index.json"
+        );
+        package.versioned = true;
+        assert_eq!(
+            super::index_url(&package, "v1.0/guide/", "/v1.0/"),
+            "/v1.0/-/search/index.json"
+        );
+        assert_eq!(
+            super::index_url(&package, "v1.0/guide/", "/docs/"),
+            "/docs/v1.0/-/search/index.json"
+        );
+        // the pages of the latest version are built at the root too
+        assert_eq!(
+            super::index_url(&package, "guide/", "/"),
+            "/-/search/index.json"
+        );
+    }
+
+    #[test]
+    fn write() {
+        let root = camino::Utf8PathBuf::from_path_buf(
+            std::env::temp_dir().join(format!("fpm-search-{}", std::process::id())),
+        )
+        .unwrap();
+        let mut config = fpm::Config::for_test(root.clone());
+        config.package.versioned = true;
+        config.package.language = Some("hi".to_string());
+
+        let index = super::SearchIndex::default();
+        let entry = |language: &str, title: &str| super::Entry {
+            language: language.to_string(),
+            title: title.to_string(),
+            headings: vec![],
+            body: "".to_string(),
+        };
+        {
+            let mut scopes = index.scopes.lock().unwrap();
+            let v1 = scopes.entry("v1.0".to_string()).or_default();
+            v1.insert("guide/".to_string(), entry("hi", "गाइड"));
+            // not translated yet, so rendered from the original
+            v1.insert("install/".to_string(), entry("en", "Install"));
+            scopes
+                .entry("".to_string())
+                .or_default()
+                .insert("".to_string(), entry("hi", "मुखपृष्ठ"));
+        }
+        index.write(&config).unwrap();
+        let read = |path: &str| {
+            serde_json::from_str::<serde_json::Value>(
+                std::fs::read_to_string(config.build_dir().join(path))
+                    .unwrap()
+                    .as_str(),
+            )
+            .unwrap()
+        };
+        let (v1, latest) = (
+            read("v1.0/-/search/index.json"),
+            read("-/search/index.json"),
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(v1["language"], "hi");
+        assert_eq!(
+            v1["documents"],
+            serde_json::json!([
+                {"url": "guide/", "title": "गाइड", "snippet": ""},
+                {"url": "install/", "language": "en", "title": "Install", "snippet": ""},
+            ])
+        );
+        assert_eq!(latest["documents"][0]["title"], "मुखपृष्ठ");
+        assert_eq!(v1["terms"]["install"], serde_json::json!([[1, 10]]));
+    }
+
+    #[test]
+    fn collect_headings() {
+        let doc = fpm::doc::parse_ftd(
+            "index.ftd",
+            indoc::indoc! {"
+                -- ftd.column h1:
+                caption title:
+                region: h1
+
+                --- ftd.text: $title
+                region: title
+
+                -- ftd.column h2:
+                caption title:
+                region: h2
+
+                --- ftd.text: $title
+                region: title
+
+                -- h1: Getting Started
+
+                -- ftd.text: Not a heading
+
+                -- h2: Install <fpm>
+            "},
+            &fpm::FPMLibrary::default(),
+        )
+        .unwrap();
+        let mut headings = vec![];
+        super::collect_headings(&doc.main.container.children, &mut headings);
+        assert_eq!(headings, vec!["Getting Started", "Install <fpm>"]);
+    }
+}
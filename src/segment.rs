@@ -0,0 +1,436 @@
+/// `Segment` is a piece of translatable text of a document: the caption, a text header or the
+/// body of an ftd section, or a paragraph of a markdown document.
+///
+/// `id` identifies the segment in the document, and is the same in the original and in the
+/// translated document: `ftd.text#2.body` is the body of the second `ftd.text` section,
+/// `h1#1.caption` the caption of the first `h1`, `ftd.image#1.alt` the `alt` header of the
+/// first `ftd.image`. A section with an `id` header is identified by it instead of its position,
+/// `ftd.text#intro.body` for `id: intro`, so it keeps its id when sections are added or moved.
+/// Markdown paragraphs are `p#1`, `p#2` and so on.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Segment {
+    pub id: String,
+    /// `line` is the line number of the text in the document, starting from 1
+    pub line: usize,
+    pub text: String,
+    /// `prefix` is kept before the text of a single line segment, like `-- ftd.text: `
+    prefix: String,
+    /// lines `start..end` of the document contain the segment
+    start: usize,
+    end: usize,
+}
+
+/// headers whose values are text to be translated
+const TEXT_HEADERS: &[&str] = &[
+    "alt",
+    "description",
+    "heading",
+    "label",
+    "nav-title",
+    "placeholder",
+    "subtitle",
+    "text",
+    "title",
+];
+
+/// sections which do not contain translatable text
+const SKIPPED_SECTIONS: &[&str] = &["import", "record", "or-type", "list"];
+
+/// `segments()` returns the translatable segments of the document `id` with `source`. Only
+/// `.ftd` and `.md` documents have segments.
+pub(crate) fn segments(id: &str, source: &str) -> Vec<Segment> {
+    if id.ends_with(".md") {
+        markdown_segments(source)
+    } else if id.ends_with(".ftd") {
+        ftd_segments(source)
+    } else {
+        vec![]
+    }
+}
+
+/// `Section` is a section of an ftd document, or a paragraph or code block of a markdown
+/// document, with the hash of its content. It is identified like segments are, `ftd.text#2`,
+/// `ftd.text#intro` or `p#3`, see `fpm::translation::outdated_sections()` for how the sections of
+/// two versions of a document are compared.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Section {
+    pub id: String,
+    pub line: usize,
+    pub content: String,
+    pub hash: String,
+}
+
+/// `sections()` returns the sections of the document `id`. Trailing white space and blank lines
+/// around a section do not change its hash.
+pub(crate) fn sections(id: &str, source: &str) -> Vec<Section> {
+    use sha2::Digest;
+
+    let lines = source.lines().collect::<Vec<&str>>();
+    let blocks = if id.ends_with(".md") {
+        markdown_blocks(&lines)
+    } else if id.ends_with(".ftd") {
+        ftd_blocks(&lines)
+    } else {
+        vec![]
+    };
+    blocks
+        .into_iter()
+        .map(|block| {
+            let content = lines[block.start..block.end]
+                .iter()
+                .map(|v| v.trim_end())
+                .collect::<Vec<&str>>()
+                .join("\n")
+                .trim_matches('\n')
+                .to_string();
+            Section {
+                id: block.id,
+                line: block.start + 1,
+                hash: format!("{:x}", sha2::Sha256::digest(content.as_bytes())),
+                content,
+            }
+        })
+        .collect()
+}
+
+/// `replace()` returns `source` with the text of each segment for which `translate` returns a
+/// value replaced by it.
+pub(crate) fn replace<F>(id: &str, source: &str, translate: F) -> String
+where
+    F: Fn(&Segment) -> Option<String>,
+{
+    let lines = source.lines().collect::<Vec<&str>>();
+    let mut output: Vec<String> = vec![];
+    let mut next = 0;
+    for segment in segments(id, source) {
+        let text = match translate(&segment) {
+            Some(text) => text,
+            None => continue,
+        };
+        output.extend(lines[next..segment.start].iter().map(|v| v.to_string()));
+        if segment.prefix.is_empty() {
+            output.push(text.trim_end().to_string());
+        } else {
+            // captions and headers can not span lines
+            output.push(format!(
+                "{}{}",
+                segment.prefix,
+                text.split_whitespace().collect::<Vec<&str>>().join(" ")
+            ));
+        }
+        next = segment.end;
+    }
+    output.extend(lines[next..].iter().map(|v| v.to_string()));
+    let mut output = output.join("\n");
+    if source.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
+
+/// `section_line()` returns the kind and caption of the line if it starts a section or a
+/// sub-section.
+fn section_line(line: &str) -> Option<(&str, &str)> {
+    let rest = line
+        .strip_prefix("--- ")
+        .or_else(|| line.strip_prefix("-- "))?;
+    let (kind, caption) = rest.split_once(':').unwrap_or((rest, ""));
+    Some((kind.trim(), caption))
+}
+
+/// `Block` is the lines `start..end` of a document which make a section of an ftd document, or a
+/// paragraph or code block of a markdown document.
+struct Block<'a> {
+    /// `id` is `<kind>#<id header>`, or `<kind>#<n>` for the n-th section of the kind without
+    /// an `id` header, `p#<n>` for the n-th paragraph
+    id: String,
+    kind: &'a str,
+    caption: &'a str,
+    start: usize,
+    end: usize,
+}
+
+fn ftd_blocks<'a>(lines: &[&'a str]) -> Vec<Block<'a>> {
+    let mut counts: std::collections::BTreeMap<&str, usize> = Default::default();
+    let mut blocks = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let (kind, caption) = match section_line(lines[i]) {
+            Some(v) => v,
+            None => {
+                i += 1;
+                continue;
+            }
+        };
+        let start = i;
+        i += 1;
+        while i < lines.len() && section_line(lines[i]).is_none() && !lines[i].starts_with("/--") {
+            i += 1;
+        }
+        let id_header = lines[start + 1..i]
+            .iter()
+            .take_while(|l| !l.trim().is_empty())
+            .filter_map(|l| l.split_once(':'))
+            .find(|(k, _)| k.trim() == "id")
+            .map(|(_, v)| v.trim())
+            .filter(|v| !v.is_empty());
+        let id = match id_header {
+            Some(v) => format!("{}#{}", kind, v),
+            None => {
+                let count = counts.entry(kind).or_insert(0);
+                *count += 1;
+                format!("{}#{}", kind, count)
+            }
+        };
+        blocks.push(Block {
+            id,
+            kind,
+            caption,
+            start,
+            end: i,
+        });
+    }
+    blocks
+}
+
+fn markdown_blocks<'a>(lines: &[&'a str]) -> Vec<Block<'a>> {
+    let mut blocks = vec![];
+    let mut i = 0;
+    let (mut paragraphs, mut code) = (0, 0);
+    while i < lines.len() {
+        if lines[i].trim().is_empty() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        if lines[i].trim_start().starts_with("```") {
+            i += 1;
+            while i < lines.len() && !lines[i].trim_start().starts_with("```") {
+                i += 1;
+            }
+            i = (i + 1).min(lines.len());
+            code += 1;
+            blocks.push(Block {
+                id: format!("code#{}", code),
+                kind: "code",
+                caption: "",
+                start,
+                end: i,
+            });
+            continue;
+        }
+        while i < lines.len()
+            && !lines[i].trim().is_empty()
+            && !lines[i].trim_start().starts_with("```")
+        {
+            i += 1;
+        }
+        paragraphs += 1;
+        blocks.push(Block {
+            id: format!("p#{}", paragraphs),
+            kind: "p",
+            caption: "",
+            start,
+            end: i,
+        });
+    }
+    blocks
+}
+
+fn ftd_segments(source: &str) -> Vec<Segment> {
+    let lines = source.lines().collect::<Vec<&str>>();
+    let mut segments = vec![];
+    for Block {
+        id: section_id,
+        kind,
+        caption,
+        start,
+        end,
+    } in ftd_blocks(&lines)
+    {
+        let headers_end = (start + 1..end)
+            .find(|j| lines[*j].trim().is_empty())
+            .unwrap_or(end);
+        let headers = (start + 1..headers_end)
+            .filter_map(|j| {
+                lines[j]
+                    .split_once(':')
+                    .map(|(k, v)| (j, k.trim().to_string(), v))
+            })
+            .collect::<Vec<(usize, String, &str)>>();
+
+        // the variable or component name of a definition, like `-- string title:`, is not a
+        // kind, only its caption is text
+        let base = kind.rsplit(' ').next().unwrap_or(kind);
+        if kind.split(' ').any(|v| SKIPPED_SECTIONS.contains(&v))
+            || headers.iter().any(|(_, k, _)| k == "$processor$")
+        {
+            continue;
+        }
+        let is_code = base.ends_with("code") || headers.iter().any(|(_, k, _)| k == "lang");
+
+        if is_text(caption) {
+            segments.push(Segment {
+                id: format!("{}.caption", section_id),
+                line: start + 1,
+                text: caption.trim().to_string(),
+                prefix: format!("{} ", &lines[start][..lines[start].len() - caption.len()]),
+                start,
+                end: start + 1,
+            });
+        }
+
+        for (j, key, value) in headers.iter() {
+            if !TEXT_HEADERS.contains(&key.as_str()) || !is_text(value) {
+                continue;
+            }
+            segments.push(Segment {
+                id: format!("{}.{}", section_id, key),
+                line: j + 1,
+                text: value.trim().to_string(),
+                prefix: format!("{}: ", key),
+                start: *j,
+                end: j + 1,
+            });
+        }
+
+        if is_code || headers_end >= end {
+            continue;
+        }
+        let body_start = match (headers_end..end).find(|j| !lines[*j].trim().is_empty()) {
+            Some(v) => v,
+            None => continue,
+        };
+        let body_end = (body_start..end)
+            .rev()
+            .find(|j| !lines[*j].trim().is_empty())
+            .map(|j| j + 1)
+            .unwrap_or(end);
+        let body = lines[body_start..body_end].join("\n");
+        if is_text(body.as_str()) {
+            segments.push(Segment {
+                id: format!("{}.body", section_id),
+                line: body_start + 1,
+                text: body,
+                prefix: "".to_string(),
+                start: body_start,
+                end: body_end,
+            });
+        }
+    }
+    segments
+}
+
+fn markdown_segments(source: &str) -> Vec<Segment> {
+    let lines = source.lines().collect::<Vec<&str>>();
+    markdown_blocks(&lines)
+        .into_iter()
+        // code blocks are not translated
+        .filter(|v| v.kind == "p")
+        .map(|v| Segment {
+            id: v.id,
+            line: v.start + 1,
+            text: lines[v.start..v.end].join("\n"),
+            prefix: "".to_string(),
+            start: v.start,
+            end: v.end,
+        })
+        .collect()
+}
+
+/// `is_text()` tells if the value is text, and not empty, a reference or a number.
+fn is_text(value: &str) -> bool {
+    let value = value.trim();
+    !value.is_empty()
+        && !value.starts_with('$')
+        && value.parse::<f64>().is_err()
+        && value != "true"
+        && value != "false"
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn ftd_segments() {
+        let source = indoc::indoc! {"
+            -- import: fpm
+
+            -- ftd.text: Hello World
+            role: $fpm.type.heading-large
+
+            -- ftd.image:
+            src: $assets.files.logo.png
+            alt: Our logo
+
+            -- ftd.text:
+
+            Some *markdown* text,
+            over two lines.
+
+            -- ftd.code:
+            lang: rs
+
+            fn main() {}
+        "};
+        let segments = super::segments("index.ftd", source)
+            .into_iter()
+            .map(|v| (v.id, v.line, v.text))
+            .collect::<Vec<(String, usize, String)>>();
+        assert_eq!(
+            segments,
+            vec![
+                (
+                    "ftd.text#1.caption".to_string(),
+                    3,
+                    "Hello World".to_string()
+                ),
+                ("ftd.image#1.alt".to_string(), 8, "Our logo".to_string()),
+                (
+                    "ftd.text#2.body".to_string(),
+                    12,
+                    "Some *markdown* text,\nover two lines.".to_string()
+                ),
+            ]
+        );
+
+        let translated = super::replace("index.ftd", source, |v| match v.id.as_str() {
+            "ftd.text#1.caption" => Some("नमस्ते दुनिया".to_string()),
+            "ftd.text#2.body" => Some("कुछ *markdown* पाठ।".to_string()),
+            _ => None,
+        });
+        assert_eq!(
+            translated,
+            source
+                .replace("-- ftd.text: Hello World", "-- ftd.text: नमस्ते दुनिया")
+                .replace(
+                    "Some *markdown* text,\nover two lines.",
+                    "कुछ *markdown* पाठ।"
+                )
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let old = super::sections("index.ftd", "-- ftd.text: Helo\n\n-- ftd.text: World\n");
+        let new = super::sections(
+            "index.ftd",
+            "-- ftd.text: Hello\n\n-- ftd.text: World  \n\n",
+        );
+        assert_eq!(
+            old.iter().map(|v| v.id.as_str()).collect::<Vec<&str>>(),
+            vec!["ftd.text#1", "ftd.text#2"]
+        );
+        assert_ne!(old[0].hash, new[0].hash);
+        assert_eq!(old[1].hash, new[1].hash);
+
+        // sections with an `id` header do not change id when a section is added before them
+        let new = super::sections(
+            "index.ftd",
+            "-- ftd.text: Added\n\n-- ftd.text: Hello\nid: hello\n\n-- ftd.text: World\n",
+        );
+        assert_eq!(
+            new.iter().map(|v| v.id.as_str()).collect::<Vec<&str>>(),
+            vec!["ftd.text#1", "ftd.text#hello", "ftd.text#2"]
+        );
+    }
+}
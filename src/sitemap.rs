@@ -152,6 +152,26 @@ pub struct TocItem {
     pub children: Vec<TocItem>,
 }
 
+/// `SitemapTitle` is the translated title of an item of the sitemap of the original package,
+/// declared by a translation package in its `FPM.ftd`
+///
+/// ```ftd
+/// -- fpm.sitemap-title: /guide/
+/// title: मार्गदर्शिका
+/// nav-title: गाइड
+/// ```
+///
+/// The sitemap, and so the structure of the navigation, is owned by the original package, only
+/// its titles are translated. The title and `about` of the package itself are not translated
+/// this way, a translation package declares its own in `fpm.package`.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct SitemapTitle {
+    pub id: String,
+    pub title: Option<String>,
+    #[serde(rename = "nav-title")]
+    pub nav_title: Option<String>,
+}
+
 #[derive(Debug, Default, serde::Serialize)]
 pub struct SiteMapCompat {
     pub sections: Vec<TocItemCompat>,
@@ -555,6 +575,10 @@ impl Sitemap {
             base_url: &str,
             config: &fpm::Config,
         ) -> fpm::Result<()> {
+            // A moved document is listed at its new location
+            if let Some(redirect) = fpm::redirects::find(&config.package, section.id.as_str()) {
+                section.id = redirect.to.trim().to_string();
+            }
             let (file_location, translation_file_location) =
                 if fpm::utils::url_regex().find(section.id.as_str()).is_some() {
                     (None, None)
@@ -610,6 +634,13 @@ impl Sitemap {
             base_url: &str,
             config: &fpm::Config,
         ) -> fpm::Result<()> {
+            if let Some(redirect) = subsection
+                .id
+                .as_ref()
+                .and_then(|id| fpm::redirects::find(&config.package, id.as_str()))
+            {
+                subsection.id = Some(redirect.to.trim().to_string());
+            }
             if let Some(ref id) = subsection.id {
                 let (file_location, translation_file_location) = if fpm::utils::url_regex()
                     .find(id.as_str())
@@ -668,6 +699,12 @@ impl Sitemap {
             base_url: &str,
             config: &fpm::Config,
         ) -> fpm::Result<()> {
+            if let Some(redirect) = Some(toc.id.as_str())
+                .filter(|id| !id.trim().is_empty())
+                .and_then(|id| fpm::redirects::find(&config.package, id))
+            {
+                toc.id = redirect.to.trim().to_string();
+            }
             let (file_location, translation_file_location) = if toc.id.trim().is_empty()
                 || fpm::utils::url_regex().find(toc.id.as_str()).is_some()
             {
@@ -716,6 +753,80 @@ impl Sitemap {
         }
     }
 
+    /// `translate_titles()` replaces the titles and nav titles of the items of the sitemap with
+    /// their translations. It is an error if the id of a translated title is not in the sitemap,
+    /// or if it is translated more than once.
+    pub(crate) fn translate_titles(&mut self, titles: &[SitemapTitle]) -> fpm::Result<()> {
+        for (i, translated) in titles.iter().enumerate() {
+            if titles[..i]
+                .iter()
+                .any(|v| Sitemap::ids_matches(v.id.as_str(), translated.id.as_str()))
+            {
+                return Err(fpm::Error::PackageError {
+                    message: format!(
+                        "`{}` is declared more than once in fpm.sitemap-title",
+                        translated.id
+                    ),
+                });
+            }
+            let mut found = false;
+            for section in self.sections.iter_mut() {
+                if Sitemap::ids_matches(section.id.as_str(), translated.id.as_str()) {
+                    translate(&mut section.title, &mut section.nav_title, translated);
+                    found = true;
+                }
+                for subsection in section.subsections.iter_mut() {
+                    if subsection
+                        .id
+                        .as_ref()
+                        .map(|id| Sitemap::ids_matches(id.as_str(), translated.id.as_str()))
+                        .unwrap_or(false)
+                    {
+                        translate(&mut subsection.title, &mut subsection.nav_title, translated);
+                        found = true;
+                    }
+                    found |= translate_toc(subsection.toc.as_mut_slice(), translated);
+                }
+            }
+            if !found {
+                return Err(fpm::Error::PackageError {
+                    message: format!(
+                        "`{}` in fpm.sitemap-title is not in the sitemap of the original package",
+                        translated.id
+                    ),
+                });
+            }
+        }
+        return Ok(());
+
+        fn translate(
+            title: &mut Option<String>,
+            nav_title: &mut Option<String>,
+            translated: &SitemapTitle,
+        ) {
+            if translated.title.is_some() {
+                *title = translated.title.clone();
+            }
+            if translated.nav_title.is_some() {
+                *nav_title = translated.nav_title.clone();
+            }
+        }
+
+        fn translate_toc(toc: &mut [TocItem], translated: &SitemapTitle) -> bool {
+            let mut found = false;
+            for toc_item in toc.iter_mut() {
+                if !toc_item.id.trim().is_empty()
+                    && Sitemap::ids_matches(toc_item.id.as_str(), translated.id.as_str())
+                {
+                    translate(&mut toc_item.title, &mut toc_item.nav_title, translated);
+                    found = true;
+                }
+                found |= translate_toc(toc_item.children.as_mut_slice(), translated);
+            }
+            found
+        }
+    }
+
     /// `get_all_locations` returns the list of tuple containing the following values:
     /// (
     ///     file_location: &camino::Utf8PathBuf, // The location of the document in the file system.
@@ -793,7 +904,7 @@ impl Sitemap {
         }
     }
 
-    fn ids_matches(id1: &str, id2: &str) -> bool {
+    pub(crate) fn ids_matches(id1: &str, id2: &str) -> bool {
         return strip_id(id1).eq(&strip_id(id2));
 
         fn strip_id(id: &str) -> String {
@@ -1240,3 +1351,81 @@ fn construct_tree(elements: Vec<(TocItem, usize)>, smallest_level: usize) -> Vec
     }
     stack_tree
 }
+
+#[cfg(test)]
+mod test {
+    fn sitemap() -> super::Sitemap {
+        super::Sitemap {
+            sections: vec![super::Section {
+                id: "/".to_string(),
+                title: Some("Home".to_string()),
+                subsections: vec![super::Subsection {
+                    id: Some("guide/".to_string()),
+                    title: Some("Guide".to_string()),
+                    toc: vec![super::TocItem {
+                        id: "guide/install/".to_string(),
+                        title: Some("Install".to_string()),
+                        children: vec![super::TocItem {
+                            id: "guide/install/linux/".to_string(),
+                            title: Some("Linux".to_string()),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        }
+    }
+
+    fn title(id: &str, title: Option<&str>, nav_title: Option<&str>) -> super::SitemapTitle {
+        super::SitemapTitle {
+            id: id.to_string(),
+            title: title.map(ToString::to_string),
+            nav_title: nav_title.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn translate_titles() {
+        let mut sitemap = sitemap();
+        sitemap
+            .translate_titles(&[
+                title("/index.html", Some("मुखपृष्ठ"), None),
+                title("/guide/", Some("मार्गदर्शिका"), Some("गाइड")),
+                title("guide/install/linux", None, Some("लिनक्स")),
+            ])
+            .unwrap();
+
+        let section = &sitemap.sections[0];
+        assert_eq!(section.title.as_deref(), Some("मुखपृष्ठ"));
+        assert_eq!(section.nav_title, None);
+        let subsection = &section.subsections[0];
+        assert_eq!(subsection.title.as_deref(), Some("मार्गदर्शिका"));
+        assert_eq!(subsection.nav_title.as_deref(), Some("गाइड"));
+        // an item without a translated title keeps the title of the original
+        assert_eq!(subsection.toc[0].title.as_deref(), Some("Install"));
+        let toc_item = &subsection.toc[0].children[0];
+        assert_eq!(toc_item.title.as_deref(), Some("Linux"));
+        assert_eq!(toc_item.nav_title.as_deref(), Some("लिनक्स"));
+    }
+
+    #[test]
+    fn translate_titles_errors() {
+        let unknown = sitemap()
+            .translate_titles(&[title("/blog/", Some("ब्लॉग"), None)])
+            .unwrap_err()
+            .to_string();
+        assert!(unknown.contains("`/blog/` in fpm.sitemap-title is not in the sitemap"));
+
+        let duplicate = sitemap()
+            .translate_titles(&[
+                title("/guide/", Some("मार्गदर्शिका"), None),
+                title("guide", None, Some("गाइड")),
+            ])
+            .unwrap_err()
+            .to_string();
+        assert!(duplicate.contains("`guide` is declared more than once in fpm.sitemap-title"));
+    }
+}
@@ -0,0 +1,346 @@
+const DIRECTIVE: &str = "-- transclude:";
+
+/// `Transclusions` keeps track of the documents transcluding each file, so `fpm build <file>` can
+/// also rebuild the documents which transclude it. It is stored in `.cache/transclusions.json`.
+///
+/// It also keeps where each line of the expanded documents comes from, so errors in them can be
+/// reported at the line of the transcluded document, see `Transclusions::locate()`.
+#[derive(Debug, Clone, Default)]
+pub struct Transclusions {
+    /// transcluded path -> ids of the documents transcluding it
+    includers: std::sync::Arc<std::sync::Mutex<Includers>>,
+    /// id of the expanded document -> file and line number of each of its lines
+    sources: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, Vec<Source>>>>,
+}
+
+type Includers = std::collections::BTreeMap<String, std::collections::BTreeSet<String>>;
+
+/// file and line number a line of an expanded document comes from
+type Source = (String, usize);
+
+fn cache_path(config: &fpm::Config) -> camino::Utf8PathBuf {
+    config.root.join(".cache").join("transclusions.json")
+}
+
+impl Transclusions {
+    pub(crate) fn read(config: &fpm::Config) -> Transclusions {
+        let includers = std::fs::read_to_string(cache_path(config))
+            .ok()
+            .and_then(|v| serde_json::from_str(v.as_str()).ok())
+            .unwrap_or_default();
+        Transclusions {
+            includers: std::sync::Arc::new(std::sync::Mutex::new(includers)),
+            sources: Default::default(),
+        }
+    }
+
+    pub(crate) fn write(&self, config: &fpm::Config) -> fpm::Result<()> {
+        let includers = self.includers.lock().unwrap();
+        let path = cache_path(config);
+        if includers.is_empty() && !path.exists() {
+            return Ok(());
+        }
+        std::fs::create_dir_all(config.root.join(".cache"))?;
+        std::fs::write(path, serde_json::to_string_pretty(&*includers)?)?;
+        Ok(())
+    }
+
+    /// `dependents()` returns the ids of the documents transcluding `file`, directly or through
+    /// other transcluded documents.
+    pub(crate) fn dependents(&self, file: &str) -> Vec<String> {
+        let includers = self.includers.lock().unwrap();
+        let mut found: Vec<String> = vec![];
+        let mut pending = vec![file.trim_start_matches('/').to_string()];
+        while let Some(file) = pending.pop() {
+            for id in includers.get(file.as_str()).into_iter().flatten() {
+                if id != file && !found.contains(id) {
+                    found.push(id.to_string());
+                    pending.push(id.to_string());
+                }
+            }
+        }
+        found
+    }
+
+    /// `locate()` maps the line number of an error in the expanded document `id` to the line of
+    /// the document it comes from. `source` is the text that was interpreted, the lines added
+    /// before the expanded document, like auto imports, are not mapped.
+    pub(crate) fn locate(&self, id: &str, source: &str, e: ftd::p1::Error) -> ftd::p1::Error {
+        let sources = self.sources.lock().unwrap();
+        let (lines, message, doc_id, line_number) = match (sources.get(id), e) {
+            (
+                Some(lines),
+                ftd::p1::Error::ParseError {
+                    message,
+                    doc_id,
+                    line_number,
+                },
+            ) => (lines, message, doc_id, line_number),
+            (_, e) => return e,
+        };
+        let offset = source.lines().count().saturating_sub(lines.len());
+        match line_number
+            .checked_sub(offset + 1)
+            .and_then(|i| lines.get(i))
+        {
+            Some((file, line)) if file == id => ftd::p1::Error::ParseError {
+                message,
+                doc_id,
+                line_number: *line,
+            },
+            Some((file, line)) => ftd::p1::Error::ParseError {
+                message: format!("{} (transcluded in {})", message, id),
+                doc_id: file.to_string(),
+                line_number: *line,
+            },
+            None => ftd::p1::Error::ParseError {
+                message,
+                doc_id,
+                line_number,
+            },
+        }
+    }
+
+    fn forget(&self, id: &str) {
+        self.sources.lock().unwrap().remove(id);
+        let mut includers = self.includers.lock().unwrap();
+        includers.values_mut().for_each(|ids| {
+            ids.remove(id);
+        });
+        includers.retain(|_, ids| !ids.is_empty());
+    }
+
+    fn add(&self, id: &str, path: &str) {
+        self.includers
+            .lock()
+            .unwrap()
+            .entry(path.to_string())
+            .or_default()
+            .insert(id.to_string());
+    }
+}
+
+/// `expand()` replaces the `-- transclude: <path>` lines of document `id` by the content of the
+/// `.ftd` or `.md` document at `path`. It is called before the document is interpreted:
+///
+/// ```ftd
+/// -- transclude: shared/intro.ftd:summary
+/// ```
+///
+/// `path` is resolved, and the anchors and line ranges after it are selected, like they are for
+/// `$processor$: include`. Sections of an `.ftd` document are transcluded as is, and can
+/// themselves transclude other documents. A markdown document is transcluded as the body of a
+/// `ftd.text` section.
+pub(crate) fn expand(config: &fpm::Config, id: &str, source: &str) -> fpm::Result<String> {
+    config.transclusions.forget(id);
+    if !source.contains(DIRECTIVE) {
+        return Ok(source.to_string());
+    }
+    let mut lines = vec![];
+    expand_(
+        config,
+        id,
+        (id, 1),
+        source,
+        &mut vec![id.to_string()],
+        &mut lines,
+    )?;
+    let (lines, sources): (Vec<String>, Vec<Source>) = lines.into_iter().unzip();
+    config
+        .transclusions
+        .sources
+        .lock()
+        .unwrap()
+        .insert(id.to_string(), sources);
+    Ok(lines.join("\n"))
+}
+
+/// `expand_()` appends the lines of `source`, whose first line is line `first` of `file`, to
+/// `output`, along with where each of them comes from.
+fn expand_(
+    config: &fpm::Config,
+    id: &str,
+    (file, first): (&str, usize),
+    source: &str,
+    stack: &mut Vec<String>,
+    output: &mut Vec<(String, Source)>,
+) -> fpm::Result<()> {
+    for (i, line) in source.lines().enumerate() {
+        let here = (file.to_string(), first + i);
+        let path = match line.strip_prefix(DIRECTIVE) {
+            Some(path) => path.trim(),
+            None => {
+                output.push((line.to_string(), here));
+                continue;
+            }
+        };
+
+        let doc = match fpm::library::include::IncludeDocument::parse(path) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(fpm::Error::UsageError {
+                    message: format!("{}: failed to transclude `{}`: {}", id, path, e),
+                })
+            }
+        };
+        let included = doc.path().trim_start_matches('/').to_string();
+        if stack.contains(&included) {
+            return Err(fpm::Error::UsageError {
+                message: format!(
+                    "{}: transclusion cycle: {} -> {}",
+                    id,
+                    stack.join(" -> "),
+                    included
+                ),
+            });
+        }
+        config.transclusions.add(id, included.as_str());
+
+        let (content, line_number) = match doc.read(config, false) {
+            Ok(v) => v,
+            Err(e) => {
+                return Err(fpm::Error::UsageError {
+                    message: format!("{}: failed to transclude `{}`: {}", id, path, e),
+                })
+            }
+        };
+
+        if included.ends_with(".md") {
+            output.push(("-- ftd.text:".to_string(), here.clone()));
+            output.push((String::new(), here.clone()));
+            for (i, l) in fpm::library::include::escape(content.as_str())
+                .lines()
+                .enumerate()
+            {
+                let l = if l.starts_with("--") {
+                    format!("\\{}", l)
+                } else {
+                    l.to_string()
+                };
+                output.push((l, (included.to_string(), line_number + i)));
+            }
+            output.push((String::new(), here));
+        } else {
+            stack.push(included.to_string());
+            expand_(
+                config,
+                id,
+                (included.as_str(), line_number),
+                content.as_str(),
+                stack,
+                output,
+            )?;
+            stack.pop();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    fn config(name: &str, files: &[(&str, &str)]) -> fpm::Config {
+        let root =
+            std::env::temp_dir().join(format!("fpm-transclude-{}-{}", name, std::process::id()));
+        let root = camino::Utf8PathBuf::from_path_buf(root).unwrap();
+        for (file, content) in files {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        fpm::Config::for_test(root)
+    }
+
+    #[test]
+    fn cycle() {
+        let config = config(
+            "cycle",
+            &[
+                ("a.ftd", "-- transclude: b.ftd"),
+                ("b.ftd", "-- ftd.text: b\n\n-- transclude: a.ftd"),
+            ],
+        );
+        let e = super::expand(&config, "index.ftd", "-- transclude: a.ftd").unwrap_err();
+        assert!(e
+            .to_string()
+            .contains("transclusion cycle: index.ftd -> a.ftd -> b.ftd -> a.ftd"));
+        std::fs::remove_dir_all(&config.root).unwrap();
+    }
+
+    #[test]
+    fn dependents() {
+        let config = config(
+            "dependents",
+            &[
+                ("a.ftd", "-- ftd.text: a\n-- transclude: shared/b.ftd"),
+                ("shared/b.ftd", "-- ftd.text: b"),
+                ("notes.md", "# Notes"),
+            ],
+        );
+        assert_eq!(
+            super::expand(
+                &config,
+                "index.ftd",
+                "-- transclude: a.ftd\n\n-- transclude: notes.md"
+            )
+            .unwrap(),
+            "-- ftd.text: a\n-- ftd.text: b\n\n-- ftd.text:\n\n# Notes\n"
+        );
+        config.transclusions.write(&config).unwrap();
+
+        let transclusions = super::Transclusions::read(&config);
+        assert_eq!(transclusions.dependents("shared/b.ftd"), vec!["index.ftd"]);
+        assert_eq!(transclusions.dependents("/notes.md"), vec!["index.ftd"]);
+        assert!(transclusions.dependents("index.ftd").is_empty());
+
+        // transclusions removed from the document are forgotten
+        super::expand(&config, "index.ftd", "-- ftd.text: hello").unwrap();
+        assert!(config.transclusions.dependents("a.ftd").is_empty());
+        std::fs::remove_dir_all(&config.root).unwrap();
+    }
+
+    #[test]
+    fn locate() {
+        let config = config("locate", &[("a.ftd", "-- ftd.text: a\n\n-- ftd.txt: typo")]);
+        let content = super::expand(
+            &config,
+            "index.ftd",
+            "-- transclude: a.ftd\n\n-- ftd.text: b",
+        )
+        .unwrap();
+        // one line of auto imports is added before the document
+        let source = format!("-- import: lib\n{}", content);
+        let locate = |line_number| match config.transclusions.locate(
+            "index.ftd",
+            source.as_str(),
+            ftd::p1::Error::ParseError {
+                message: "error".to_string(),
+                doc_id: "foo/index.ftd".to_string(),
+                line_number,
+            },
+        ) {
+            ftd::p1::Error::ParseError {
+                message,
+                doc_id,
+                line_number,
+            } => (doc_id, line_number, message),
+            e => panic!("expected parse error, found: {:?}", e),
+        };
+        assert_eq!(
+            locate(4),
+            (
+                "a.ftd".to_string(),
+                3,
+                "error (transcluded in index.ftd)".to_string()
+            )
+        );
+        assert_eq!(
+            locate(6),
+            ("foo/index.ftd".to_string(), 3, "error".to_string())
+        );
+        assert_eq!(
+            locate(1),
+            ("foo/index.ftd".to_string(), 1, "error".to_string())
+        );
+        std::fs::remove_dir_all(&config.root).unwrap();
+    }
+}
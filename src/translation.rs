@@ -13,6 +13,7 @@ pub(crate) enum TranslatedDocument {
         last_marked_on: u128,
         original_latest: u128,
         translated_latest: u128,
+        sections: Vec<OutdatedSection>,
     },
     UptoDate {
         translated: fpm::File,
@@ -26,6 +27,7 @@ pub struct TranslationData {
     pub original_latest: Option<u128>,
     pub translated_latest: Option<u128>,
     pub status: Option<String>,
+    pub(crate) outdated_sections: Vec<OutdatedSection>,
 }
 
 impl TranslationData {
@@ -36,10 +38,135 @@ impl TranslationData {
             original_latest: None,
             translated_latest: None,
             status: Some(status.to_string()),
+            outdated_sections: vec![],
         }
     }
 }
 
+/// `OutdatedSection` is a section of an original document which changed since its translation
+/// was last marked up to date.
+#[derive(Debug, Clone)]
+pub(crate) struct OutdatedSection {
+    /// `id` is the id of the section, like `ftd.text#2`, see `fpm::segment::Section`
+    pub id: String,
+    /// `line` is the line of the section in the latest original, or in the marked original if
+    /// the section is removed
+    pub line: usize,
+    pub change: SectionChange,
+    /// `diff` is the diff of the section from the marked original
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SectionChange {
+    Added,
+    Changed,
+    Removed,
+}
+
+impl SectionChange {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            SectionChange::Added => "added",
+            SectionChange::Changed => "changed",
+            SectionChange::Removed => "removed",
+        }
+    }
+}
+
+/// `outdated_sections()` compares the sections of the original document `file` at
+/// `last_marked_on`, the version its translation was last marked up to date with, with its
+/// sections at `original_latest`, so a typo fixed in one section does not make the whole
+/// translation out-dated. An empty list means the sections did not change.
+///
+/// It returns `None` if either version is not in the history of the original package, in which
+/// case only the timestamps can tell the status of the translation.
+pub(crate) fn outdated_sections(
+    original_path: &camino::Utf8PathBuf,
+    file: &str,
+    last_marked_on: &u128,
+    original_latest: &u128,
+) -> Option<Vec<OutdatedSection>> {
+    let read = |timestamp: &u128| {
+        std::fs::read_to_string(fpm::utils::history_path(
+            file,
+            original_path.as_str(),
+            timestamp,
+        ))
+        .ok()
+        .map(|v| fpm::segment::sections(file, v.as_str()))
+    };
+    let (marked, latest) = (read(last_marked_on)?, read(original_latest)?);
+    Some(compare_sections(&marked, &latest))
+}
+
+/// `compare_sections()` returns the sections of `latest` which are not in `marked`, and the
+/// sections of `marked` which are not in `latest`.
+///
+/// Sections are first matched by their content, so a section which only moved, or whose
+/// position among the sections of its kind changed as one was added before it, is not outdated.
+/// The remaining sections are matched by id, these are changed, the others are added or removed.
+fn compare_sections(
+    marked: &[fpm::segment::Section],
+    latest: &[fpm::segment::Section],
+) -> Vec<OutdatedSection> {
+    let mut unmatched_marked = marked.iter().collect::<Vec<&fpm::segment::Section>>();
+    let mut unmatched_latest = vec![];
+    for section in latest.iter() {
+        // a section with the same id is preferred, for when two sections have the same content
+        let found = unmatched_marked
+            .iter()
+            .position(|v| v.hash == section.hash && v.id == section.id)
+            .or_else(|| unmatched_marked.iter().position(|v| v.hash == section.hash));
+        match found {
+            Some(i) => {
+                unmatched_marked.remove(i);
+            }
+            None => unmatched_latest.push(section),
+        }
+    }
+
+    let mut outdated = vec![];
+    for section in unmatched_latest {
+        let old = unmatched_marked
+            .iter()
+            .position(|v| v.id == section.id)
+            .map(|i| unmatched_marked.remove(i));
+        outdated.push(OutdatedSection {
+            id: section.id.to_string(),
+            line: section.line,
+            change: if old.is_some() {
+                SectionChange::Changed
+            } else {
+                SectionChange::Added
+            },
+            diff: section_diff(
+                old.map(|v| v.content.as_str()).unwrap_or_default(),
+                section.content.as_str(),
+            ),
+        });
+    }
+    for old in unmatched_marked {
+        outdated.push(OutdatedSection {
+            id: old.id.to_string(),
+            line: old.line,
+            change: SectionChange::Removed,
+            diff: section_diff(old.content.as_str(), ""),
+        });
+    }
+    outdated
+}
+
+fn section_diff(old: &str, new: &str) -> String {
+    diffy::create_patch(format!("{}\n", old).as_str(), format!("{}\n", new).as_str())
+        .to_string()
+        .lines()
+        // the patch header is not needed, the section is known
+        .filter(|l| !l.starts_with("--- ") && !l.starts_with("+++ "))
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
 impl TranslatedDocument {
     pub async fn html(
         &self,
@@ -69,6 +196,7 @@ impl TranslatedDocument {
                 last_marked_on,
                 original_latest,
                 translated_latest,
+                sections,
             } => {
                 // Gets the diff on original file between last_marked_on and original_latest timestamp
                 let diff = get_diff(config, original, last_marked_on, original_latest).await?;
@@ -78,6 +206,7 @@ impl TranslatedDocument {
                     original_latest: Some(*original_latest),
                     translated_latest: Some(*translated_latest),
                     status: Some("Outdated".to_string()),
+                    outdated_sections: sections.clone(),
                 };
 
                 (translated, Some(original), translated_data)
@@ -132,8 +261,8 @@ impl TranslatedDocument {
         original_documents: std::collections::BTreeMap<String, fpm::File>,
         translated_documents: std::collections::BTreeMap<String, fpm::File>,
     ) -> fpm::Result<std::collections::BTreeMap<String, TranslatedDocument>> {
-        let original_snapshots =
-            fpm::snapshot::get_latest_snapshots(&config.original_path()?).await?;
+        let original_path = config.original_path()?;
+        let original_snapshots = fpm::snapshot::get_latest_snapshots(&original_path).await?;
         let mut translation_status = std::collections::BTreeMap::new();
         for (file, timestamp) in original_snapshots {
             let original_document =
@@ -173,17 +302,26 @@ impl TranslatedDocument {
             }) = tracks.get(&file)
             {
                 if last_merged_version < &timestamp {
-                    translation_status.insert(
-                        file,
-                        TranslatedDocument::Outdated {
-                            original: original_document.clone(),
-                            translated: translated_document.clone(),
-                            last_marked_on: *last_merged_version,
-                            original_latest: timestamp,
-                            translated_latest: *self_timestamp,
-                        },
+                    let sections = outdated_sections(
+                        &original_path,
+                        file.as_str(),
+                        last_merged_version,
+                        &timestamp,
                     );
-                    continue;
+                    if sections.as_ref().map(|v| !v.is_empty()).unwrap_or(true) {
+                        translation_status.insert(
+                            file,
+                            TranslatedDocument::Outdated {
+                                original: original_document.clone(),
+                                translated: translated_document.clone(),
+                                last_marked_on: *last_merged_version,
+                                original_latest: timestamp,
+                                translated_latest: *self_timestamp,
+                                sections: sections.unwrap_or_default(),
+                            },
+                        );
+                        continue;
+                    }
                 }
                 translation_status.insert(
                     file,
@@ -208,12 +346,14 @@ impl TranslatedDocument {
 pub(crate) fn get_translation_status_counts(
     snapshots: &std::collections::BTreeMap<String, u128>,
     path: &camino::Utf8PathBuf,
+    original_path: &camino::Utf8PathBuf,
 ) -> fpm::Result<TranslationStatusSummary> {
     let mut translation_status_count = TranslationStatusSummary {
         never_marked: 0,
         missing: 0,
         out_dated: 0,
         upto_date: 0,
+        out_dated_sections: Some(0),
         last_modified_on: None,
     };
     for (file, timestamp) in snapshots {
@@ -233,8 +373,17 @@ pub(crate) fn get_translation_status_counts(
         }) = tracks.get(file)
         {
             if last_merged_version < timestamp {
-                translation_status_count.out_dated += 1;
-                continue;
+                match outdated_sections(original_path, file, last_merged_version, timestamp) {
+                    Some(sections) if sections.is_empty() => {}
+                    sections => {
+                        translation_status_count.out_dated += 1;
+                        translation_status_count.out_dated_sections = translation_status_count
+                            .out_dated_sections
+                            .zip(sections)
+                            .map(|(count, sections)| count + sections.len() as i32);
+                        continue;
+                    }
+                }
             }
             translation_status_count.upto_date += 1;
         } else {
@@ -246,7 +395,7 @@ pub(crate) fn get_translation_status_counts(
     Ok(translation_status_count)
 }
 
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 pub struct TranslationStatusSummary {
     #[serde(rename = "never-marked")]
     pub never_marked: i32,
@@ -255,24 +404,82 @@ pub struct TranslationStatusSummary {
     pub out_dated: i32,
     #[serde(rename = "upto-date")]
     pub upto_date: i32,
+    /// `out_dated_sections` is the number of changed sections in the out-dated documents, it is
+    /// not known if the history of an original document is not available
+    #[serde(rename = "out-dated-sections")]
+    pub out_dated_sections: Option<i32>,
     #[serde(rename = "last-modified-on")]
     pub last_modified_on: Option<String>,
 }
 
+impl TranslationStatusSummary {
+    /// `count()` returns the number of documents with the status `key`, see
+    /// `TranslationStatus::key()`.
+    pub(crate) fn count(&self, key: &str) -> i32 {
+        match key {
+            "missing" => self.missing,
+            "never-marked" => self.never_marked,
+            "outdated" => self.out_dated,
+            "upto-date" => self.upto_date,
+            _ => 0,
+        }
+    }
+}
+
 impl ToString for TranslationStatusSummary {
     fn to_string(&self) -> String {
         format!(
             indoc::indoc! {"
                 Never marked: {never_marked}
                 Missing: {missing}
-                Out-dated: {out_dated}
+                Out-dated: {out_dated}{out_dated_sections}
                 Up to date: {upto_date}
 
                 "},
             never_marked = self.never_marked,
             missing = self.missing,
             out_dated = self.out_dated,
+            out_dated_sections = self
+                .out_dated_sections
+                .map(|v| format!(" ({} sections)", v))
+                .unwrap_or_default(),
             upto_date = self.upto_date
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    fn compare(marked: &str, latest: &str) -> Vec<(String, &'static str)> {
+        super::compare_sections(
+            &fpm::segment::sections("index.ftd", marked),
+            &fpm::segment::sections("index.ftd", latest),
+        )
+        .into_iter()
+        .map(|v| (v.id, v.change.as_str()))
+        .collect()
+    }
+
+    #[test]
+    fn compare_sections() {
+        let marked = "-- ftd.text: Hello\n\n-- ftd.text: World\n";
+        assert!(compare(marked, marked).is_empty());
+        // the sections after the added one are not outdated
+        assert_eq!(
+            compare(
+                marked,
+                "-- ftd.text: Intro\n\n-- ftd.text: Hello\n\n-- ftd.text: World\n"
+            ),
+            vec![("ftd.text#1".to_string(), "added")]
+        );
+        assert_eq!(
+            compare(marked, "-- ftd.text: Helo\n\n-- ftd.text: World\n"),
+            vec![("ftd.text#1".to_string(), "changed")]
+        );
+        // `World` is now the first section, it is `Hello` which is removed
+        assert_eq!(
+            compare(marked, "-- ftd.text: World\n"),
+            vec![("ftd.text#1".to_string(), "removed")]
+        );
+    }
+}
@@ -39,12 +39,49 @@ pub(crate) fn get_timestamp_nanosecond() -> u128 {
     }
 }
 
+/// `language_code()` returns the language of a language tag, `pt` for `pt-BR`.
+fn language_code(language: &str) -> &str {
+    language
+        .split(|c| c == '-' || c == '_')
+        .next()
+        .unwrap_or(language)
+}
+
+/// `language_to_human()` returns the name of the language, `Portuguese` for `pt` or `pt-BR`.
 pub(crate) fn language_to_human(language: &str) -> String {
-    realm_lang::Language::from_2_letter_code(language)
+    realm_lang::Language::from_2_letter_code(language_code(language))
         .map(|v| v.human())
         .unwrap_or_else(|_| language.to_string())
 }
 
+/// languages written right to left
+const RTL_LANGUAGES: &[&str] = &[
+    "ar", "arc", "ckb", "dv", "fa", "he", "iw", "ps", "sd", "ug", "ur", "yi",
+];
+
+/// `language_direction()` returns `rtl` if the language, like `ar` or `ur-PK`, is written right to
+/// left, and `ltr` otherwise.
+pub(crate) fn language_direction(language: &str) -> &'static str {
+    if RTL_LANGUAGES.contains(&language_code(language).to_lowercase().as_str()) {
+        "rtl"
+    } else {
+        "ltr"
+    }
+}
+
+/// `document_language()` returns the language of the content of a document with the translation
+/// `status`: a document which is missing or never marked in a translation package is rendered
+/// from the original package, so it is in the language of the original.
+pub(crate) fn document_language<'a>(config: &'a fpm::Config, status: Option<&str>) -> &'a str {
+    let original = config.package.translation_of.as_ref().as_ref();
+    match (status, original) {
+        (Some("Missing" | "NeverMarked"), Some(original)) => {
+            original.language.as_deref().unwrap_or("en")
+        }
+        _ => config.package.language.as_deref().unwrap_or("en"),
+    }
+}
+
 pub(crate) fn nanos_to_rfc3339(nanos: &u128) -> String {
     let time = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_nanos(*nanos as u64);
     chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339()
@@ -216,24 +253,83 @@ pub(crate) fn id_to_path(id: &str) -> String {
         .replace(".md", std::path::MAIN_SEPARATOR.to_string().as_str())
 }
 
+/// `search_meta()` tells `fpm.js` where the search index of the scope of the page is, the index
+/// of its version for a page of a versioned package.
+fn search_meta(config: &fpm::Config, main_id: &str, base_url: &str) -> String {
+    if !config.package.search {
+        return "".to_string();
+    }
+    format!(
+        "\n<meta name=\"fpm-search-index\" content=\"{}\">",
+        fpm::artifacts::escape(fpm::search::index_url(&config.package, main_id, base_url).as_str())
+    )
+}
+
+/// `replace_language_markers()` sets the `lang`, and the `dir` of a right to left language, of
+/// the `<html>` element.
+fn replace_language_markers(s: &str, lang: &str) -> String {
+    // `ltr` is the default, so `dir` is set only for right to left languages
+    let dir = match language_direction(lang) {
+        "rtl" => " dir=\"rtl\"",
+        _ => "",
+    };
+    s.replace("__ftd_lang__", lang).replace("__ftd_dir__", dir)
+}
+
 pub(crate) fn replace_markers(
     s: &str,
     config: &fpm::Config,
+    lang: &str,
     main_id: &str,
     title: &str,
     base_url: &str,
     main_rt: &ftd::Document,
+    meta: &fpm::meta::Meta,
 ) -> String {
-    s.replace("__ftd_doc_title__", title)
+    // With `--optimize-assets` the shared js and css are linked instead of being inlined
+    let (stylesheets, scripts, ftd_js, ftd_css, fpm_js) = match config.shared_assets {
+        Some(ref assets) => (
+            assets.stylesheets(),
+            assets.scripts(),
+            String::new(),
+            "",
+            "",
+        ),
+        None => (
+            String::new(),
+            String::new(),
+            fpm::ftd_js(),
+            fpm::ftd_css(),
+            fpm::fpm_js(),
+        ),
+    };
+    replace_language_markers(s, lang)
+        .replace("__ftd_doc_title__", title)
         .replace(
             "__ftd_canonical_url__",
-            config.package.generate_canonical_url(main_id).as_str(),
+            format!(
+                "{}{}",
+                config.package.generate_canonical_url(main_id),
+                config.package.generate_alternate_urls(main_id)
+            )
+            .as_str(),
+        )
+        .replace(
+            "__ftd_meta__",
+            format!(
+                "{}{}",
+                meta.to_html(&config.package, main_id, title),
+                search_meta(config, main_id, base_url)
+            )
+            .as_str(),
         )
-        .replace("__ftd_js__", fpm::ftd_js().as_str())
+        .replace("__ftd_stylesheets__", stylesheets.as_str())
+        .replace("__ftd_scripts__", scripts.as_str())
+        .replace("__ftd_js__", ftd_js.as_str())
         .replace("__ftd_body_events__", main_rt.body_events.as_str())
-        .replace("__ftd_css__", fpm::ftd_css())
+        .replace("__ftd_css__", ftd_css)
         .replace("__ftd_element_css__", main_rt.css_collector.as_str())
-        .replace("__fpm_js__", fpm::fpm_js())
+        .replace("__fpm_js__", fpm_js)
         .replace(
             "__ftd_data_main__",
             fpm::font::escape(
@@ -268,3 +364,41 @@ pub(crate) fn url_regex() -> regex::Regex {
         r#"((([A-Za-z]{3,9}:(?://)?)(?:[-;:&=\+\$,\w]+@)?[A-Za-z0-9.-]+|(?:www.|[-;:&=\+\$,\w]+@)[A-Za-z0-9.-]+)((?:/[\+~%/.\w_]*)?\??(?:[-\+=&;%@.\w_]*)\#?(?:[\w]*))?)"#
     ).unwrap()
 }
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn language_direction() {
+        assert_eq!(super::language_direction("ar"), "rtl");
+        assert_eq!(super::language_direction("ur-PK"), "rtl");
+        assert_eq!(super::language_direction("he_IL"), "rtl");
+        assert_eq!(super::language_direction("hi"), "ltr");
+        assert_eq!(super::language_direction("en-GB"), "ltr");
+    }
+
+    #[test]
+    fn replace_language_markers() {
+        let html = include_str!("../ftd.html");
+        assert!(
+            super::replace_language_markers(html, "ar").contains("<html lang=\"ar\" dir=\"rtl\">")
+        );
+        assert!(super::replace_language_markers(html, "hi").contains("<html lang=\"hi\">"));
+    }
+
+    #[test]
+    fn document_language() {
+        let mut config = fpm::Config::for_test(camino::Utf8PathBuf::from("/tmp"));
+        config.package.language = Some("ar".to_string());
+        assert_eq!(super::document_language(&config, Some("Missing")), "ar");
+
+        let mut original = fpm::Package::new("example.com");
+        original.language = Some("en".to_string());
+        config.package.translation_of = Box::new(Some(original));
+        // missing and never marked documents are rendered from the original
+        assert_eq!(super::document_language(&config, Some("Missing")), "en");
+        assert_eq!(super::document_language(&config, Some("NeverMarked")), "en");
+        assert_eq!(super::document_language(&config, Some("Outdated")), "ar");
+        assert_eq!(super::document_language(&config, Some("UptoDate")), "ar");
+        assert_eq!(super::document_language(&config, None), "ar");
+    }
+}
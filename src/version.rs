@@ -121,6 +121,10 @@ pub(crate) async fn build_version(
                             .replace(from_pattern.as_str(), to_pattern.as_str())
                             .as_bytes(),
                     )?;
+                    if let Some(ref search_index) = config.search_index {
+                        search_index.copy(config, original_id.as_str(), new_id.as_str());
+                    }
+                    config.built_documents.add(new_id.as_str());
                     continue;
                 }
             }